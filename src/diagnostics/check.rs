@@ -0,0 +1,132 @@
+use crate::diagnostics::Diagnostic;
+use crate::types::ast::{walk, Arm, Node, Pattern, AST};
+
+use std::collections::HashMap;
+
+/**
+ * Look over a parsed program for problems that don't stop it from running,
+ * for now that's `match` statements with values no arm fits
+ */
+pub fn check(program: &[Node]) -> Vec<Diagnostic> {
+    // variants can be matched before the enum declaring them is reached
+    let mut enums: HashMap<&str, &[(String, Vec<String>)]> = HashMap::new();
+    walk(program, &mut |node| {
+        if let AST::Enum { variants, .. } = &node.node {
+            for (variant, _) in variants {
+                enums.insert(variant, variants);
+            }
+        }
+    });
+
+    let mut warnings = vec![];
+    walk(program, &mut |node| {
+        if let AST::Match { arms, .. } = &node.node {
+            if let Some(warning) = check_exhaustive(arms, &enums) {
+                warnings.push(warning.with_span(node.span.clone()));
+            }
+        }
+    });
+    warnings
+}
+
+/**
+ * Warn about a match unless every value fits one of its arms. Matches over
+ * records or anything else it can't tell the type of are given the benefit
+ * of the doubt.
+ */
+fn check_exhaustive(
+    arms: &[Arm],
+    enums: &HashMap<&str, &[(String, Vec<String>)]>,
+) -> Option<Diagnostic> {
+    let patterns: Vec<&Pattern> = arms.iter().map(|arm| &arm.pattern.node).collect();
+    if patterns.iter().any(|pattern| irrefutable(pattern, enums)) {
+        return None;
+    }
+
+    let warning = Diagnostic::warning("W0001", "match isn't exhaustive".to_string());
+    let variant = patterns.iter().find_map(|pattern| match pattern {
+        Pattern::Variant { identifier, .. } => Some(identifier),
+        _ => None,
+    });
+    match variant {
+        Some(variant) => {
+            let variants = enums.get(variant.as_str())?;
+            let missing: Vec<&str> = variants
+                .iter()
+                .map(|(variant, _)| variant.as_str())
+                .filter(|variant| {
+                    !patterns
+                        .iter()
+                        .any(|pattern| covers(pattern, variant, enums))
+                })
+                .collect();
+            if missing.is_empty() {
+                return None;
+            }
+            Some(
+                warning
+                    .with_label(format!("{} not covered", list(&missing)))
+                    .with_help(format!(
+                        "add {} or a `_` arm for anything else",
+                        if missing.len() == 1 {
+                            "an arm for it"
+                        } else {
+                            "arms for them"
+                        }
+                    )),
+            )
+        }
+        None => {
+            let covers_bool = |value: bool| {
+                patterns.iter().any(|pattern| match pattern {
+                    Pattern::Literal(literal) => literal.node == AST::Bool { value },
+                    _ => false,
+                })
+            };
+            if covers_bool(true) && covers_bool(false) {
+                return None;
+            }
+            Some(
+                warning
+                    .with_label("some values aren't covered".to_string())
+                    .with_help("add a `_` arm for anything else".to_string()),
+            )
+        }
+    }
+}
+
+/// Whether a pattern fits every value of the type it's for
+fn irrefutable(pattern: &Pattern, enums: &HashMap<&str, &[(String, Vec<String>)]>) -> bool {
+    match pattern {
+        Pattern::Wildcard | Pattern::Binding(_) => true,
+        Pattern::Literal(_) => false,
+        // a name that isn't a variant is a record type
+        Pattern::Variant { identifier, fields } => {
+            !enums.contains_key(identifier.as_str())
+                && fields.iter().all(|field| irrefutable(field, enums))
+        }
+    }
+}
+
+/// Whether a pattern fits every value made by `variant`
+fn covers(
+    pattern: &Pattern,
+    variant: &str,
+    enums: &HashMap<&str, &[(String, Vec<String>)]>,
+) -> bool {
+    match pattern {
+        Pattern::Variant { identifier, fields } => {
+            identifier == variant && fields.iter().all(|field| irrefutable(field, enums))
+        }
+        pattern => irrefutable(pattern, enums),
+    }
+}
+
+/// "`A`", "`A` and `B`" or "`A`, `B` and `C`"
+fn list(names: &[&str]) -> String {
+    let names: Vec<String> = names.iter().map(|name| format!("`{}`", name)).collect();
+    match names.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+        _ => names.concat(),
+    }
+}
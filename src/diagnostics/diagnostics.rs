@@ -0,0 +1,242 @@
+use crate::types::error::{ParseError, ParseErrorKind, RuntimeError, TokenError, TokenErrorKind};
+use crate::types::span::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Whether to render with ANSI escape codes for terminals
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Plain,
+    Colored,
+}
+
+impl Style {
+    /// Colored when stderr is a terminal and `NO_COLOR` isn't set
+    pub fn for_stderr() -> Style {
+        use std::io::IsTerminal;
+        if std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none() {
+            Style::Colored
+        } else {
+            Style::Plain
+        }
+    }
+
+    fn paint(&self, text: &str, ansi: &str) -> String {
+        match self {
+            Style::Plain => text.to_string(),
+            Style::Colored => format!("\x1b[{}m{}\x1b[0m", ansi, text),
+        }
+    }
+}
+
+const BOLD: &str = "1";
+const BOLD_RED: &str = "1;31";
+const BOLD_YELLOW: &str = "1;33";
+const BOLD_BLUE: &str = "1;34";
+
+/**
+ * A problem found in an eye program, rendered like rustc does:
+ *
+ * error[E0102]: unterminated block
+ *  --> main.eye:1:19
+ *   |
+ * 1 | define main to be {
+ *   |                   ^ expected `}` found end of input
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub span: Option<Span>,
+    pub label: Option<String>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(code: &'static str, message: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            code,
+            message,
+            span: None,
+            label: None,
+            notes: vec![],
+            help: None,
+        }
+    }
+
+    pub fn warning(code: &'static str, message: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::error(code, message)
+        }
+    }
+
+    pub fn with_span(mut self, span: Span) -> Diagnostic {
+        if !span.is_empty() {
+            self.span = Some(span);
+        }
+        self
+    }
+
+    pub fn with_label(mut self, label: String) -> Diagnostic {
+        self.label = Some(label);
+        self
+    }
+
+    pub fn with_note(mut self, note: String) -> Diagnostic {
+        self.notes.push(note);
+        self
+    }
+
+    pub fn with_help(mut self, help: String) -> Diagnostic {
+        self.help = Some(help);
+        self
+    }
+
+    /**
+     * Render the diagnostic with the line of `source` it points at
+     */
+    pub fn render(&self, source: &str, style: Style) -> String {
+        let (severity, color) = match self.severity {
+            Severity::Error => ("error", BOLD_RED),
+            Severity::Warning => ("warning", BOLD_YELLOW),
+        };
+        let mut out = format!(
+            "{}{}",
+            style.paint(&format!("{}[{}]", severity, self.code), color),
+            style.paint(&format!(": {}", self.message), BOLD)
+        );
+
+        let line_number = match &self.span {
+            Some(span) => span.start.line.to_string(),
+            None => "".to_string(),
+        };
+        let gutter = " ".repeat(line_number.len());
+        let bar = style.paint("|", BOLD_BLUE);
+
+        if let Some(span) = &self.span {
+            out += &format!("\n{}{} {}", gutter, style.paint("-->", BOLD_BLUE), span);
+
+            if let Some(line) = source.lines().nth(span.start.line - 1) {
+                // underline the span, or the rest of the line if it wraps
+                let start = span.start.column.max(1);
+                let end = if span.end.line == span.start.line {
+                    span.end.column
+                } else {
+                    line.chars().count() + 1
+                };
+                let mut underline = "^".repeat(end.saturating_sub(start).max(1));
+                if let Some(label) = &self.label {
+                    underline = format!("{} {}", underline, label);
+                }
+
+                out += &format!("\n{} {}", gutter, bar);
+                out += &format!(
+                    "\n{} {} {}",
+                    style.paint(&line_number, BOLD_BLUE),
+                    bar,
+                    line
+                );
+                out += &format!(
+                    "\n{} {} {}{}",
+                    gutter,
+                    bar,
+                    " ".repeat(start - 1),
+                    style.paint(&underline, color)
+                );
+            }
+        }
+
+        for note in &self.notes {
+            out += &format!("\n{} {} {}", gutter, style.paint("=", BOLD_BLUE), note);
+        }
+        if let Some(help) = &self.help {
+            out += &format!(
+                "\n{} {} {}: {}",
+                gutter,
+                style.paint("=", BOLD_BLUE),
+                style.paint("help", BOLD),
+                help
+            );
+        }
+
+        out
+    }
+}
+
+impl From<&TokenError> for Diagnostic {
+    fn from(error: &TokenError) -> Diagnostic {
+        let code = match error.kind {
+            TokenErrorKind::UnknownCharacter(_) => "E0001",
+            TokenErrorKind::UnterminatedString => "E0002",
+            TokenErrorKind::MalformedNumber(_) => "E0003",
+            TokenErrorKind::FloatOverflow(_) => "E0005",
+        };
+        let diagnostic =
+            Diagnostic::error(code, error.kind.to_string()).with_span(error.span.clone());
+
+        match error.kind {
+            TokenErrorKind::UnterminatedString => {
+                diagnostic.with_help("add a closing `\"` to the string".to_string())
+            }
+            _ => diagnostic,
+        }
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Diagnostic {
+        let code = match error.kind {
+            ParseErrorKind::UnexpectedToken => "E0100",
+            ParseErrorKind::MissingToBe => "E0101",
+            ParseErrorKind::UnterminatedBlock => "E0102",
+            ParseErrorKind::BadThrowOperand => "E0103",
+            ParseErrorKind::ChainedComparison => "E0104",
+            ParseErrorKind::DuplicateBinding => "E0105",
+        };
+        let diagnostic = Diagnostic::error(code, error.kind.to_string())
+            .with_span(error.span.clone())
+            .with_label(format!(
+                "expected {} found {}",
+                error.expected,
+                error.found.describe()
+            ));
+
+        match error.kind {
+            ParseErrorKind::MissingToBe => diagnostic
+                .with_help("names are defined with `define name to be value;`".to_string()),
+            ParseErrorKind::UnterminatedBlock => {
+                diagnostic.with_note("this `{` is never closed".to_string())
+            }
+            ParseErrorKind::BadThrowOperand => {
+                diagnostic.with_help("only string messages can be thrown".to_string())
+            }
+            ParseErrorKind::ChainedComparison => diagnostic.with_help(
+                "comparisons can't be chained, write `a < b and b < c` instead of `a < b < c`"
+                    .to_string(),
+            ),
+            // the pattern is what's wrong, not the token after it
+            ParseErrorKind::DuplicateBinding => diagnostic
+                .with_label(format!("expected {}", error.expected))
+                .with_help("give each value its own name, `_` skips one".to_string()),
+            ParseErrorKind::UnexpectedToken => diagnostic,
+        }
+    }
+}
+
+impl From<&RuntimeError> for Diagnostic {
+    fn from(error: &RuntimeError) -> Diagnostic {
+        let diagnostic = Diagnostic::error("E0200", error.message.clone());
+        match &error.span {
+            Some(span) => diagnostic.with_span(span.clone()),
+            None => diagnostic,
+        }
+    }
+}
@@ -0,0 +1,145 @@
+mod check;
+#[allow(clippy::module_inception)]
+mod diagnostics;
+pub use self::check::check;
+pub use self::diagnostics::{Diagnostic, Severity, Style};
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::error::RuntimeError;
+    use crate::types::span::{Location, Span};
+
+    fn span(line: usize, start: usize, end: usize) -> Span {
+        Span::new(
+            Some("main.eye".into()),
+            Location {
+                line,
+                column: start,
+            },
+            Location { line, column: end },
+        )
+    }
+
+    #[test]
+    fn plain() {
+        let source = "define a to be 1;\nprint a + b;";
+        let diagnostic = Diagnostic::error("E0200", "undefined symbol b".to_string())
+            .with_span(span(2, 11, 12))
+            .with_label("not defined".to_string())
+            .with_note("symbols must be defined before use".to_string())
+            .with_help("try `define b to be ...;`".to_string());
+
+        assert_eq!(
+            diagnostic.render(source, Style::Plain),
+            "error[E0200]: undefined symbol b
+ --> main.eye:2:11
+  |
+2 | print a + b;
+  |           ^ not defined
+  = symbols must be defined before use
+  = help: try `define b to be ...;`"
+        );
+    }
+
+    #[test]
+    fn colored() {
+        let diagnostic =
+            Diagnostic::warning("W0001", "careful".to_string()).with_span(span(1, 1, 6));
+        let rendered = diagnostic.render("print 1;", Style::Colored);
+
+        assert!(rendered.starts_with("\x1b[1;33mwarning[W0001]\x1b[0m"));
+        assert!(rendered.contains("\x1b[1;33m^^^^^\x1b[0m"));
+    }
+
+    #[test]
+    fn without_span() {
+        let error = RuntimeError::new("Found program in AST.".to_string());
+        assert_eq!(
+            Diagnostic::from(&error).render("", Style::Plain),
+            "error[E0200]: Found program in AST."
+        );
+    }
+
+    #[test]
+    fn non_exhaustive_match() {
+        let warnings = |source: &str| {
+            let tokens = crate::lexer::tokenize_file(source.to_string(), "main.eye").unwrap();
+            match crate::parser::build_program(tokens).unwrap() {
+                crate::types::ast::AST::Program { program } => check(&program)
+                    .iter()
+                    .map(|warning| warning.render(source, Style::Plain))
+                    .collect::<Vec<_>>(),
+                ast => panic!("Expected a program found {:?}", ast),
+            }
+        };
+        let shapes = "define Shape to be one of Circle(r) or Square(s) or Empty;\n";
+
+        assert_eq!(
+            warnings(&format!(
+                "{}define f to be {{\nmatch s {{ Circle(1) {{ }} Empty {{ }} }}\n}} given (s);",
+                shapes
+            )),
+            vec![
+                "warning[W0001]: match isn't exhaustive
+ --> main.eye:3:1
+  |
+3 | match s { Circle(1) { } Empty { } }
+  | ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ `Circle` and `Square` not covered
+  = help: add arms for them or a `_` arm for anything else"
+            ]
+        );
+        assert_eq!(
+            warnings("match b { true { } 1 { } }"),
+            vec![
+                "warning[W0001]: match isn't exhaustive
+ --> main.eye:1:1
+  |
+1 | match b { true { } 1 { } }
+  | ^^^^^^^^^^^^^^^^^^^^^^^^^^ some values aren't covered
+  = help: add a `_` arm for anything else"
+            ]
+        );
+
+        // every variant, a catch all arm, both bools or a record pattern cover everything
+        let exhaustive = [
+            "match s { Circle(_) { } Square(s) { } Empty { } }",
+            "match s { Circle(1) { } other { } }",
+            "match b { true { } false { } }",
+            "match p { Point(x, y) { } }",
+        ];
+        for source in exhaustive {
+            assert!(warnings(&format!("{}{}", shapes, source)).is_empty());
+        }
+    }
+
+    #[test]
+    fn from_parse_error() {
+        let source = "define main to be {\n    print 1;\n";
+        let tokens = crate::lexer::tokenize_file(source.to_string(), "main.eye").unwrap();
+        let errors = crate::parser::build_program(tokens).unwrap_err();
+
+        assert_eq!(
+            Diagnostic::from(&errors[0]).render(source, Style::Plain),
+            "error[E0102]: unterminated block
+ --> main.eye:1:19
+  |
+1 | define main to be {
+  |                   ^ expected `}` found end of input
+  = this `{` is never closed"
+        );
+
+        let source = "match p {\n    Pair(a, a) { }\n}";
+        let tokens = crate::lexer::tokenize_file(source.to_string(), "main.eye").unwrap();
+        let errors = crate::parser::build_program(tokens).unwrap_err();
+        assert_eq!(
+            Diagnostic::from(&errors[0]).render(source, Style::Plain),
+            "error[E0105]: duplicate binding
+ --> main.eye:2:5
+  |
+2 |     Pair(a, a) { }
+  |     ^^^^^^^^^^ expected `a` to be bound only once in a pattern
+  = help: give each value its own name, `_` skips one"
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use crate::diagnostics::{self, Diagnostic};
+use crate::file;
+use crate::interpreter::run_program;
+use crate::lexer::tokenize_file;
+use crate::parser::build_program;
+use crate::types::ast::{Node, AST};
+use crate::types::error::{Error, RuntimeError};
+use crate::types::native_function::IntoNative;
+use crate::types::options::Options;
+use crate::types::primitive_value::PrimitiveValue;
+use crate::types::symbol_store::SymbolStore;
+
+/**
+ * Runs eye code for a host program.
+ *
+ * Symbols defined at the top level of one program stay defined for the next,
+ * so an engine can be loaded with a library and then evaluate expressions:
+ *
+ * ```
+ * use eye::{Engine, PrimitiveValue};
+ *
+ * let engine = Engine::new();
+ * engine.run("define twice to be { return n * 2; } given (n);").unwrap();
+ * assert_eq!(engine.eval("twice(21)").unwrap(), PrimitiveValue::Num(42));
+ * ```
+ */
+pub struct Engine {
+    symbols: SymbolStore,
+    options: Options,
+}
+
+impl Engine {
+    pub fn new() -> Engine {
+        Engine::with_options(Options::default())
+    }
+
+    pub fn with_options(options: Options) -> Engine {
+        Engine {
+            symbols: SymbolStore::new(),
+            options,
+        }
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    pub fn options_mut(&mut self) -> &mut Options {
+        &mut self.options
+    }
+
+    /// The top level scope programs run in
+    pub fn globals(&self) -> &SymbolStore {
+        &self.symbols
+    }
+
+    pub fn get(&self, identifier: &str) -> Option<PrimitiveValue> {
+        self.symbols.get(identifier)
+    }
+
+    /// Define a global symbol, replacing any existing one
+    pub fn set(&self, identifier: &str, value: PrimitiveValue) {
+        self.symbols.define(identifier.to_string(), value);
+    }
+
+    /**
+     * Expose a Rust closure to eye code as a global function, its parameter
+     * types decide the arity and how arguments are converted:
+     *
+     * ```
+     * # let engine = eye::Engine::new();
+     * engine.register_fn("add", |a: i32, b: i32| a + b);
+     * assert_eq!(engine.eval("run add given (1, 2)").unwrap(), eye::PrimitiveValue::Num(3));
+     * ```
+     */
+    pub fn register_fn<Args, F: IntoNative<Args>>(&self, identifier: &str, function: F) {
+        self.set(
+            identifier,
+            PrimitiveValue::Native(function.into_native(identifier)),
+        );
+    }
+
+    /// Lex and parse `source`, `file_name` is used in error locations
+    pub fn parse(&self, source: &str, file_name: &str) -> Result<Vec<Node>, Error> {
+        let tokens = tokenize_file(source.to_string(), file_name)?;
+        match build_program(tokens)? {
+            AST::Program { program } => Ok(program),
+            ast => Err(Error::Runtime(RuntimeError::new(format!(
+                "Expected a program found {:?}",
+                ast
+            )))),
+        }
+    }
+
+    /// Warnings about a parsed program that would still run, ie a `match` not every value fits
+    pub fn check(&self, program: &[Node]) -> Vec<Diagnostic> {
+        diagnostics::check(program)
+    }
+
+    /// Run a program, returns the value of a top level `return` if there is one
+    pub fn run(&self, source: &str) -> Result<Option<PrimitiveValue>, Error> {
+        self.run_parsed(self.parse(source, "<eval>")?)
+    }
+
+    /// Run a program `parse` gave back, like `run` does
+    pub fn run_parsed(&self, program: Vec<Node>) -> Result<Option<PrimitiveValue>, Error> {
+        Ok(run_program(program, &self.symbols, &self.options)?)
+    }
+
+    /// Read a source file, the `.eye` extension is optional
+    pub fn read_file(path: &str) -> Result<String, Error> {
+        file::read_source_file(path).map_err(|error| Error::Io {
+            path: file::source_path(path).display().to_string(),
+            error,
+        })
+    }
+
+    /// Read and run a source file, the `.eye` extension is optional
+    pub fn run_file(&self, path: &str) -> Result<Option<PrimitiveValue>, Error> {
+        let source = Engine::read_file(path)?;
+        self.run_parsed(self.parse(&source, path)?)
+    }
+
+    /**
+     * Evaluate `source` and return its value, either the value of a top level
+     * `return` or of the expression the program ends with
+     */
+    pub fn eval(&self, source: &str) -> Result<PrimitiveValue, Error> {
+        match self.eval_optional(source, "<eval>")? {
+            Some(value) => Ok(value),
+            None => Err(Error::Runtime(RuntimeError::new(
+                "Program didn't produce a value".to_string(),
+            ))),
+        }
+    }
+
+    /// Like `eval`, but programs that end with a statement give back `None`
+    pub fn eval_optional(
+        &self,
+        source: &str,
+        file_name: &str,
+    ) -> Result<Option<PrimitiveValue>, Error> {
+        let mut program = self.parse(source, file_name)?;
+        return_last_expression(&mut program);
+        Ok(run_program(program, &self.symbols, &self.options)?)
+    }
+
+    /// Forget every global symbol, including registered native functions
+    pub fn reset(&mut self) {
+        self.symbols = SymbolStore::new();
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Engine {
+        Engine::new()
+    }
+}
+
+/// Turn a trailing expression into a `return` so running the program gives its value
+fn return_last_expression(program: &mut [Node]) {
+    let last = program
+        .iter_mut()
+        .rev()
+        .find(|node| !matches!(node.node, AST::Semicolon | AST::EOF));
+
+    if let Some(node) = last {
+        if node.node.is_expression() {
+            let value = Box::new(node.clone());
+            node.node = AST::Return { value };
+        }
+    }
+}
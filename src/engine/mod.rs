@@ -0,0 +1,203 @@
+#[allow(clippy::module_inception)]
+mod engine;
+pub use self::engine::Engine;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::error::{Error, RuntimeError};
+    use crate::types::options::{Backend, Options};
+    use crate::types::primitive_value::PrimitiveValue;
+
+    #[test]
+    fn eval_returns_trailing_expression() {
+        let engine = Engine::new();
+        assert_eq!(engine.eval("1 + 2 * 3").unwrap(), PrimitiveValue::Num(7));
+        assert_eq!(
+            engine.eval("define a to be 2; return a * 4;").unwrap(),
+            PrimitiveValue::Num(8)
+        );
+        assert!(engine.eval("define b to be 1;").is_err());
+    }
+
+    #[test]
+    fn globals_persist_between_runs() {
+        let engine = Engine::new();
+        engine
+            .run("define twice to be { return n * 2; } given (n);")
+            .unwrap();
+        engine.set("x", PrimitiveValue::Num(21));
+        assert_eq!(engine.eval("twice(x)").unwrap(), PrimitiveValue::Num(42));
+
+        engine.run("define y to be x + 1;").unwrap();
+        assert_eq!(engine.get("y"), Some(PrimitiveValue::Num(22)));
+        assert_eq!(engine.globals().get("x"), Some(PrimitiveValue::Num(21)));
+
+        // so do the types records can use for their fields
+        engine
+            .run("define Point to be record { x: number }; define Shape to be one of Dot or Empty;")
+            .unwrap();
+        engine
+            .run("define Marker to be record { at: Point, shape: Shape };")
+            .unwrap();
+        assert!(engine.run("define Bad to be record { at: Pont };").is_err());
+    }
+
+    #[test]
+    fn bytecode_backend() {
+        let mut engine = Engine::with_options(Options::debug());
+        engine.options_mut().backend = Backend::Bytecode;
+        assert_eq!(engine.options().backend, Backend::Bytecode);
+        assert_eq!(
+            engine.eval("define a to be 5; a * a").unwrap(),
+            PrimitiveValue::Num(25)
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let engine = Engine::new();
+        assert!(matches!(engine.eval("1 @ 2"), Err(Error::Lex(_))));
+        assert!(matches!(
+            engine.eval("define to be 1;"),
+            Err(Error::Parse(_))
+        ));
+        assert!(matches!(engine.eval("missing"), Err(Error::Runtime(_))));
+        assert!(matches!(
+            engine.run_file("examples/does-not-exist"),
+            Err(Error::Io { .. })
+        ));
+
+        let error = engine.eval("print missing;").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "<eval>:1:7: Tried to access undefined symbol: missing"
+        );
+    }
+
+    /// An engine for each backend
+    fn engines() -> Vec<Engine> {
+        Options::every_backend()
+            .into_iter()
+            .map(Engine::with_options)
+            .collect()
+    }
+
+    #[test]
+    fn native_functions() {
+        for engine in engines() {
+            engine.register_fn("add", |a: i32, b: i32| a + b);
+            engine.register_fn("answer", || 42);
+            engine.register_fn("shout", |s: String| format!("{}!", s));
+            assert_eq!(
+                engine.eval("run add given (1, 2)").unwrap(),
+                PrimitiveValue::Num(3)
+            );
+            assert_eq!(
+                engine.eval("add(answer(), 1)").unwrap(),
+                PrimitiveValue::Num(43)
+            );
+            assert_eq!(
+                engine.eval("shout(\"hi\")").unwrap(),
+                PrimitiveValue::Str("hi!".to_string())
+            );
+            engine.register_fn("square", |n: i64| n * n);
+            assert_eq!(
+                engine.eval("square(3000000000 / 10)").unwrap().to_string(),
+                "90000000000000000"
+            );
+
+            // eye functions can be passed around the same as native ones
+            assert_eq!(
+                engine
+                    .eval("define f to be { return add(n, n); } given (n); f(4)")
+                    .unwrap(),
+                PrimitiveValue::Num(8)
+            );
+        }
+    }
+
+    #[test]
+    fn native_functions_with_state() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        for engine in engines() {
+            let logged = Rc::new(RefCell::new(vec![]));
+            let log = logged.clone();
+            engine.register_fn("log", move |value: PrimitiveValue| {
+                log.borrow_mut().push(value.to_string())
+            });
+            engine
+                .run("run log given (1); do { run log given (i); } 2 times given i;")
+                .unwrap();
+            assert_eq!(*logged.borrow(), vec!["1", "0", "1"]);
+
+            // nothing is returned from `()`
+            assert_eq!(
+                engine.eval("print log(1) + 1;").unwrap_err().to_string(),
+                "<eval>:1:7: Function log didn't return value"
+            );
+        }
+    }
+
+    #[test]
+    fn native_function_errors() {
+        for engine in engines() {
+            engine.register_fn("add", |a: i32, b: i32| a + b);
+            engine.register_fn("fail", |message: String| -> Result<i32, RuntimeError> {
+                Err(RuntimeError::new(message))
+            });
+
+            assert_eq!(
+                engine.eval("add(1)").unwrap_err().to_string(),
+                "<eval>:1:1: Function add takes 2 arguments but was given 1"
+            );
+            assert_eq!(
+                engine.eval("add(1, true)").unwrap_err().to_string(),
+                "<eval>:1:1: Function add expected a number for argument 2, found true"
+            );
+            assert_eq!(
+                engine.eval("\n  fail(\"no\")").unwrap_err().to_string(),
+                "<eval>:2:3: no"
+            );
+
+            // hosts calling a native function directly get the same check
+            let add = match engine.get("add") {
+                Some(PrimitiveValue::Native(add)) => add,
+                value => panic!("Expected a native function found {:?}", value),
+            };
+            assert_eq!(
+                add.call(vec![PrimitiveValue::Num(1)])
+                    .unwrap_err()
+                    .to_string(),
+                "Function add takes 2 arguments but was given 1"
+            );
+        }
+    }
+
+    #[test]
+    fn run_file() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/fibonacci");
+        let engine = Engine::with_options(Options::debug());
+        engine.run_file(path).unwrap();
+        assert!(engine.get("fib").is_some());
+    }
+
+    #[test]
+    fn check_and_run_a_parsed_program() {
+        let engine = Engine::new();
+        let program = engine
+            .parse("match 1 { 2 { print 2; } }\ndefine a to be 1;", "main.eye")
+            .unwrap();
+        let warnings = engine.check(&program);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, "W0001");
+        assert!(engine.run_parsed(program).is_err());
+
+        let program = engine.parse("define a to be 1;", "main.eye").unwrap();
+        assert!(engine.check(&program).is_empty());
+        engine.run_parsed(program).unwrap();
+        assert_eq!(engine.get("a"), Some(PrimitiveValue::Num(1)));
+    }
+}
@@ -1,16 +1,21 @@
 use std::fs;
+use std::path::PathBuf;
 
 /**
- * Read a source file
- * **filename does not include the extension**
+ * Path of a source file, the `.eye` extension is optional
  */
-pub fn read_source_file(mut filename: String, work_dir: String) -> String {
-    if !filename.ends_with(".eye") {
-        filename += ".eye";
+pub fn source_path(filename: &str) -> PathBuf {
+    if filename.ends_with(".eye") {
+        PathBuf::from(filename)
+    } else {
+        PathBuf::from(format!("{}.eye", filename))
     }
-    let source_file_path = format!("{}/{}", work_dir, filename);
-
-    let fail_str = format!("Failed to read file: {}", source_file_path).to_string();
+}
 
-    fs::read_to_string(source_file_path).expect(&fail_str)
+/**
+ * Read a source file relative to the working directory
+ * **filename does not need to include the extension**
+ */
+pub fn read_source_file(filename: &str) -> std::io::Result<String> {
+    fs::read_to_string(source_path(filename))
 }
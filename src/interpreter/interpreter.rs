@@ -1,37 +1,113 @@
+use crate::interpreter::{iterate, match_pattern, no_arm_value_error, no_match_error, operands};
+use crate::types::ast::walk;
+use crate::types::ast::Arm;
 use crate::types::ast::FunctionBody;
+use crate::types::ast::Node;
 use crate::types::ast::AST;
 use crate::types::binary_operator::BinaryOperator;
 use crate::types::error::RuntimeError;
-use crate::types::options::Options;
-use crate::types::primitive_value::PrimitiveValue;
-use crate::types::symbol_store::SymbolStore;
+use crate::types::map::Map;
+use crate::types::options::{Backend, Options};
+use crate::types::primitive_value::{expect_key, PrimitiveValue};
+use crate::types::record::{RecordType, BUILT_IN_TYPES};
+use crate::types::span::Span;
+use crate::types::symbol_store::{ScopeLink, SymbolStore};
+use crate::types::unary_operator::UnaryOperator;
+use crate::vm;
 
-use std::time::Instant;
+use std::collections::HashSet;
+use std::rc::Rc;
 
 /**
- * Run AST program and handle errors
+ * Run AST program, errors are handed back to the caller to report
  */
-pub fn interpret(root_program: AST, mut symbols: SymbolStore, options: &Options) {
+pub fn interpret(
+    root_program: AST,
+    symbols: SymbolStore,
+    options: &Options,
+) -> Result<(), RuntimeError> {
     if let AST::Program { program } = root_program {
-        let now = Instant::now();
-        match run_body_and_return(program, &mut symbols, options) {
-            Err(error) => println!("Runtime Error! {}", error.message),
-            Ok(..) => println!("Done in {}ms", now.elapsed().as_millis()),
-        };
+        run_program(program, &symbols, options)?;
+        Ok(())
     } else {
         panic!("root_program not of type AST::Program, {:?}", root_program);
     }
 }
 
+/**
+ * Run the statements of a program on the backend `options` selects,
+ * returns anything the top level returns
+ */
+pub fn run_program(
+    program: Vec<Node>,
+    symbols: &SymbolStore,
+    options: &Options,
+) -> Result<Option<PrimitiveValue>, RuntimeError> {
+    check_field_types(&program, symbols)?;
+    match options.backend {
+        Backend::TreeWalk => run_body_and_return(program, symbols, options),
+        Backend::Bytecode => vm::run(&program, symbols, options),
+    }
+}
+
+/**
+ * Make sure every record the program declares gives its fields types that
+ * exist before any of it runs. A type can be used before the record or enum
+ * declaring it and the ones an earlier program defined in `symbols` count too.
+ */
+fn check_field_types(program: &[Node], symbols: &SymbolStore) -> Result<(), RuntimeError> {
+    let mut types: HashSet<String> = symbols
+        .bindings()
+        .into_iter()
+        .filter_map(|(_, value)| match value {
+            PrimitiveValue::RecordType(record_type) => Some(
+                record_type
+                    .variant_of
+                    .clone()
+                    .unwrap_or_else(|| record_type.name.clone()),
+            ),
+            PrimitiveValue::Record(record) => record.borrow().record_type.variant_of.clone(),
+            _ => None,
+        })
+        .collect();
+    walk(program, &mut |node| {
+        if let AST::Record { identifier, .. } | AST::Enum { identifier, .. } = &node.node {
+            types.insert(identifier.to_string());
+        }
+    });
+
+    let mut error = None;
+    walk(program, &mut |node| {
+        if let AST::Record { identifier, fields } = &node.node {
+            let unknown = fields.iter().find(|(_, type_name)| {
+                !BUILT_IN_TYPES.contains(&type_name.as_str()) && !types.contains(type_name)
+            });
+            if let (Some((field, type_name)), None) = (unknown, &error) {
+                error = Some(
+                    RuntimeError::new(format!(
+                        "Field {} of {} has the type {}, which isn't defined.",
+                        field, identifier, type_name
+                    ))
+                    .or_span(&node.span),
+                );
+            }
+        }
+    });
+    error.map_or(Ok(()), Err)
+}
+
 // Get a primitive value from an AST
 pub fn value_from_ast(
-    ast: AST,
-    symbols: &mut SymbolStore,
+    ast: Node,
+    symbols: &SymbolStore,
     options: &Options,
 ) -> Result<PrimitiveValue, RuntimeError> {
+    let span = ast.span.clone();
     let new_ast = ast.clone();
-    match ast {
+    match ast.node {
         AST::Number { value } => Ok(PrimitiveValue::Num(value)),
+        AST::BigNumber { value } => Ok(PrimitiveValue::from(value)),
+        AST::Float { value } => Ok(PrimitiveValue::Float(value)),
         AST::Bool { value } => Ok(PrimitiveValue::Bool(value)),
         AST::Str { value } => Ok(PrimitiveValue::Str(value)),
         AST::Binary {
@@ -39,52 +115,308 @@ pub fn value_from_ast(
             right,
             operator,
         } => apply_binary_operator(*left, *right, operator, symbols, options),
+        AST::Unary { operator, value } => {
+            apply_unary_operator(operator, value_from_ast(*value, symbols, options)?)
+        }
+        AST::List { items } => {
+            let mut values = vec![];
+            for item in items {
+                values.push(value_from_ast(item, symbols, options)?);
+            }
+            Ok(PrimitiveValue::from(values))
+        }
+        AST::Map { entries } => {
+            // every entry is evaluated before any key is checked, like on the VM
+            let mut values = vec![];
+            for (key, value) in entries {
+                values.push(value_from_ast(key, symbols, options)?);
+                values.push(value_from_ast(value, symbols, options)?);
+            }
+            let mut map = Map::new();
+            for entry in values.chunks(2) {
+                map.insert(expect_key(&entry[0])?.to_string(), entry[1].clone());
+            }
+            Ok(PrimitiveValue::from(map))
+        }
+        AST::Index { collection, index } => {
+            let collection = value_from_ast(*collection, symbols, options)?;
+            let index = value_from_ast(*index, symbols, options)?;
+            Ok(collection.get_index(index)?)
+        }
+        AST::Remove { key, map } => {
+            let key = value_from_ast(*key, symbols, options)?;
+            Ok(value_from_ast(*map, symbols, options)?.remove(key)?)
+        }
+        AST::Field { record, field } => {
+            Ok(value_from_ast(*record, symbols, options)?.get_field(&field)?)
+        }
         AST::Call {
             identifier,
             args: _,
         } => {
             if let Some(value) = run_ast(new_ast, symbols, options)? {
-                return Ok(value);
+                Ok(value)
             } else {
-                Err(RuntimeError {
-                    message: format!("Function {} didn't return value", identifier),
-                })
+                Err(RuntimeError::new(format!(
+                    "Function {} didn't return value",
+                    identifier
+                )))
             }
         }
+        AST::Match { value, arms } => {
+            let value = match_value(*value, arms, &span, true, symbols, options)?;
+            Ok(value.expect("a required match value is always there"))
+        }
         AST::Symbol { identifier } => {
             if let Some(value) = symbols.get(&identifier) {
-                let new_value = value.clone();
-                return Ok(new_value);
+                Ok(value)
             } else {
-                Err(RuntimeError {
-                    message: format!("Could not get value from Symbol: {:?}", identifier),
-                })
+                Err(RuntimeError::new(format!(
+                    "Tried to access undefined symbol: {}",
+                    identifier
+                )))
             }
         }
-        _ => Err(RuntimeError {
-            message: format!("Value of AST could not be determined {:?}", ast),
-        }),
+        ast => Err(RuntimeError::new(format!(
+            "Value of AST could not be determined {:?}",
+            ast
+        ))),
     }
+    .map_err(|error| error.or_span(&span))
 }
 
 /**
  * Get value from asts and then apply binary operator
  */
 fn apply_binary_operator(
-    left: AST,
-    right: AST,
+    left: Node,
+    right: Node,
     operator: BinaryOperator,
-    symbols: &mut SymbolStore,
+    symbols: &SymbolStore,
     options: &Options,
 ) -> Result<PrimitiveValue, RuntimeError> {
+    if operator == BinaryOperator::Assign {
+        return assign(left, right, symbols, options);
+    }
+
     let left_value = value_from_ast(left, symbols, options)?;
+    if short_circuits(operator, &left_value)? {
+        return Ok(left_value);
+    }
     let right_value = value_from_ast(right, symbols, options)?;
+    apply_operator(operator, left_value, right_value)
+}
+
+/**
+ * Whether the left side of `and`/`or` decides the result on its own,
+ * the right side isn't evaluated when it does
+ */
+pub fn short_circuits(
+    operator: BinaryOperator,
+    left: &PrimitiveValue,
+) -> Result<bool, RuntimeError> {
+    Ok(match operator {
+        BinaryOperator::And => !left.expect_bool(operator)?,
+        BinaryOperator::Or => left.expect_bool(operator)?,
+        _ => false,
+    })
+}
+
+/**
+ * Apply a unary operator to a value, shared with the bytecode VM
+ */
+pub fn apply_unary_operator(
+    operator: UnaryOperator,
+    value: PrimitiveValue,
+) -> Result<PrimitiveValue, RuntimeError> {
+    match operator {
+        UnaryOperator::Not => Ok((!value)?),
+        UnaryOperator::Negate => Ok(value.negate()?),
+        UnaryOperator::Length => Ok(value.length()?),
+        UnaryOperator::Pop => Ok(value.pop()?),
+    }
+}
+
+/**
+ * Apply a binary operator to two values, shared with the bytecode VM
+ */
+pub fn apply_operator(
+    operator: BinaryOperator,
+    left_value: PrimitiveValue,
+    right_value: PrimitiveValue,
+) -> Result<PrimitiveValue, RuntimeError> {
     match operator {
         BinaryOperator::Add => Ok(left_value.add(right_value)?),
         BinaryOperator::Subtract => Ok(left_value.subtract(right_value)?),
         BinaryOperator::Multiply => Ok(left_value.multiply(right_value)?),
+        BinaryOperator::Divide => Ok(left_value.divide(right_value)?),
+        BinaryOperator::Modulo => Ok(left_value.modulo(right_value)?),
         BinaryOperator::IsEq => Ok(left_value.is_equal(right_value)?),
-        _ => panic!("Operator not implemented {}", operator.to_string()),
+        BinaryOperator::IsNEq => Ok(left_value.is_not_equal(right_value)?),
+        BinaryOperator::Less => Ok(left_value.is_less(right_value)?),
+        BinaryOperator::LessEq => Ok(left_value.is_less_or_equal(right_value)?),
+        BinaryOperator::Greater => Ok(left_value.is_greater(right_value)?),
+        BinaryOperator::GreaterEq => Ok(left_value.is_greater_or_equal(right_value)?),
+        BinaryOperator::And => Ok(left_value.and(right_value)?),
+        BinaryOperator::Or => Ok(left_value.or(right_value)?),
+        BinaryOperator::Range => Ok(left_value.range(right_value)?),
+        BinaryOperator::Has => Ok(left_value.has(right_value)?),
+        _ => Err(RuntimeError::new(format!(
+            "Operator not implemented {}",
+            operator
+        ))),
+    }
+}
+
+/**
+ * Check `function` is something `identifier(...)` can call with `arg_count` arguments
+ */
+pub fn check_call(
+    identifier: &str,
+    function: &PrimitiveValue,
+    arg_count: usize,
+) -> Result<(), RuntimeError> {
+    let arity = match function {
+        PrimitiveValue::Function(block) => block.args.len(),
+        PrimitiveValue::Closure(closure) => closure.proto.arity,
+        PrimitiveValue::Native(function) => function.arity,
+        PrimitiveValue::RecordType(record_type) => {
+            let fields = record_type.fields.len();
+            if arg_count != fields {
+                let kind = match record_type.variant_of {
+                    Some(_) => "Variant",
+                    None => "Record",
+                };
+                return Err(RuntimeError::new(format!(
+                    "{} {} has {} fields but was given {}",
+                    kind, identifier, fields, arg_count
+                )));
+            }
+            fields
+        }
+        value => {
+            return Err(RuntimeError::new(format!(
+                "Symbol {} is not a function, found {}",
+                identifier, value
+            )))
+        }
+    };
+
+    if arg_count != arity {
+        return Err(RuntimeError::new(format!(
+            "Function {} takes {} arguments but was given {}",
+            identifier, arity, arg_count
+        )));
+    }
+    Ok(())
+}
+
+/**
+ * Call a function value with evaluated arguments, whichever backend created it
+ */
+pub fn call_function(
+    function: PrimitiveValue,
+    args: Vec<PrimitiveValue>,
+    options: &Options,
+) -> Result<Option<PrimitiveValue>, RuntimeError> {
+    match function {
+        PrimitiveValue::Function(block) => {
+            // calls run in a new scope inside the one the function was defined in
+            let f_symbols = block.scope.scope().child();
+            for (name, value) in block.args.iter().zip(args) {
+                f_symbols.define(name.clone(), value);
+            }
+
+            run_body_and_return(block.body, &f_symbols, options)
+        }
+        PrimitiveValue::Closure(closure) => vm::call(&closure, args, options),
+        PrimitiveValue::Native(function) => function.call(args),
+        PrimitiveValue::RecordType(record_type) => Ok(Some(record_type.construct(args)?)),
+        value => Err(RuntimeError::new(format!("{} is not a function", value))),
+    }
+}
+
+/**
+ * Update the nearest existing binding of the symbol on the left
+ */
+fn assign(
+    left: Node,
+    right: Node,
+    symbols: &SymbolStore,
+    options: &Options,
+) -> Result<PrimitiveValue, RuntimeError> {
+    let span = left.span;
+    match left.node {
+        AST::Symbol { identifier } => {
+            let value = value_from_ast(right, symbols, options)?;
+            if symbols.assign(&identifier, value.clone()) {
+                Ok(value)
+            } else {
+                Err(
+                    RuntimeError::new(format!("Tried to assign undefined symbol: {}", identifier))
+                        .or_span(&span),
+                )
+            }
+        }
+        // `xs[i] = value` changes the list in place
+        AST::Index { collection, index } => {
+            let collection = value_from_ast(*collection, symbols, options)?;
+            let index = value_from_ast(*index, symbols, options)?;
+            let value = value_from_ast(right, symbols, options)?;
+            collection
+                .set_index(index, value.clone())
+                .map_err(|error| RuntimeError::from(error).or_span(&span))?;
+            Ok(value)
+        }
+        AST::Field { record, field } => {
+            let record = value_from_ast(*record, symbols, options)?;
+            let value = value_from_ast(right, symbols, options)?;
+            record
+                .set_field(&field, value.clone())
+                .map_err(|error| RuntimeError::from(error).or_span(&span))?;
+            Ok(value)
+        }
+        node => Err(RuntimeError::new(format!("Can't assign to {:?}", node)).or_span(&span)),
+    }
+}
+
+/// How running a block of statements finished
+enum Flow {
+    /// every statement ran
+    Normal,
+    /// a `return` ran, with its value if it has one
+    Return(Option<PrimitiveValue>),
+    /// a `break` or `continue` ran, the span is for reporting one outside of a loop
+    Break(Span),
+    Continue(Span),
+}
+
+impl Flow {
+    /// What a function or program gives back once the flow reaches its edge
+    fn into_value(self) -> Result<Option<PrimitiveValue>, RuntimeError> {
+        match self {
+            Flow::Normal => Ok(None),
+            Flow::Return(value) => Ok(value),
+            Flow::Break(span) => Err(outside_loop_error("break").or_span(&span)),
+            Flow::Continue(span) => Err(outside_loop_error("continue").or_span(&span)),
+        }
+    }
+}
+
+pub fn outside_loop_error(keyword: &str) -> RuntimeError {
+    RuntimeError::new(format!("Can't {} outside of a loop", keyword))
+}
+
+/// A statement used as a value goes to the expression around it, a `return` can't skip that
+pub fn return_in_value_error(statement: &str) -> RuntimeError {
+    RuntimeError::new(format!("Can't return from {} used as a value", statement))
+}
+
+/// What errors call a statement used as a value
+pub fn statement_name(ast: &AST) -> &'static str {
+    match ast {
+        AST::If { .. } => "an if",
+        _ => "a loop",
     }
 }
 
@@ -92,48 +424,254 @@ fn apply_binary_operator(
  * Run given set of ASTs and return any value returned by the ASTs
  */
 pub fn run_body_and_return(
-    body: Vec<Box<AST>>,
-    symbols: &mut SymbolStore,
+    body: Vec<Node>,
+    symbols: &SymbolStore,
     options: &Options,
 ) -> Result<Option<PrimitiveValue>, RuntimeError> {
+    run_block(body, symbols, options)?.into_value()
+}
+
+/**
+ * Run statements until one of them changes the flow, a `return` in an `if`
+ * stops the whole block the `if` is in and so on up to the function
+ */
+fn run_block(
+    body: Vec<Node>,
+    symbols: &SymbolStore,
+    options: &Options,
+) -> Result<Flow, RuntimeError> {
     for ast in body {
-        let new_ast = *ast.clone();
-        match *ast {
-            AST::Return { value: _ } => return run_ast(new_ast, symbols, options),
-            AST::Do {
-                count: _,
-                body: _,
-                identifier: _,
-            } => {
-                if let Some(val) = run_ast(new_ast, symbols, options)? {
-                    return Ok(Some(val));
+        match run_statement(ast, symbols, options)? {
+            Flow::Normal => (),
+            flow => return Ok(flow),
+        }
+    }
+    Ok(Flow::Normal)
+}
+
+fn run_statement(
+    ast: Node,
+    symbols: &SymbolStore,
+    options: &Options,
+) -> Result<Flow, RuntimeError> {
+    let span = ast.span;
+    match ast.node {
+        AST::Return { value } => Ok(Flow::Return(run_ast(*value, symbols, options)?)),
+        AST::Break => Ok(Flow::Break(span)),
+        AST::Continue => Ok(Flow::Continue(span)),
+        // every branch and loop iteration runs in its own nested scope
+        AST::If { this, elifs, el } => {
+            if let Some(PrimitiveValue::Bool(val)) = run_ast(*this.conditional, symbols, options)? {
+                if val {
+                    return run_block(this.body, &symbols.child(), options);
+                }
+
+                // go through each elif
+                if let Some(elifs) = elifs {
+                    for elif in elifs {
+                        if let Some(PrimitiveValue::Bool(elif_val)) =
+                            run_ast(*elif.conditional, symbols, options)?
+                        {
+                            if elif_val {
+                                return run_block(elif.body, &symbols.child(), options);
+                            }
+                        }
+                    }
+                }
+
+                // if we fall through to here
+                // we haven't found anything
+                if let Some(el) = el {
+                    return run_block(el, &symbols.child(), options);
+                }
+            }
+            Ok(Flow::Normal)
+        }
+        AST::Do {
+            count,
+            body,
+            identifier,
+        } => {
+            let count = match value_from_ast(*count, symbols, options)? {
+                PrimitiveValue::Num(count) => count,
+                value => return Err(repeat_count_error(&value).or_span(&span)),
+            };
+            let loop_symbols = symbols.child();
+            for i in 0..count {
+                if let Some(identifier_value) = identifier.clone() {
+                    loop_symbols.define(identifier_value, PrimitiveValue::Num(i));
+                }
+                if let Some(flow) = run_iteration(body.clone(), &loop_symbols.child(), options)? {
+                    return Ok(flow);
+                }
+            }
+            Ok(Flow::Normal)
+        }
+        AST::ForEach {
+            identifier,
+            index,
+            collection,
+            body,
+        } => {
+            let collection_span = collection.span.clone();
+            let collection = value_from_ast(*collection, symbols, options)?;
+            let items = iterate(collection).map_err(|error| error.or_span(&collection_span))?;
+            let loop_symbols = symbols.child();
+            for (item, position) in items {
+                loop_symbols.define(identifier.clone(), item);
+                if let Some(index) = index.clone() {
+                    loop_symbols.define(index, position);
+                }
+                if let Some(flow) = run_iteration(body.clone(), &loop_symbols.child(), options)? {
+                    return Ok(flow);
                 }
             }
-            AST::If {
-                this: _,
-                elifs: _,
-                el: _,
-            } => {
-                if let Some(val) = run_ast(new_ast, symbols, options)? {
-                    return Ok(Some(val));
+            Ok(Flow::Normal)
+        }
+        AST::Match { value, arms } => {
+            let (arm, arm_symbols) = find_arm(*value, arms, &span, symbols, options)?;
+            run_block(arm.body, &arm_symbols, options)
+        }
+        AST::While {
+            conditional,
+            body,
+            until,
+        } => {
+            let keyword = if until { "until" } else { "while" };
+            loop {
+                let condition_span = conditional.span.clone();
+                let condition = value_from_ast(*conditional.clone(), symbols, options)?;
+                let value = condition
+                    .expect_bool(keyword)
+                    .map_err(|error| RuntimeError::from(error).or_span(&condition_span))?;
+                if value == until {
+                    return Ok(Flow::Normal);
+                }
+                if let Some(flow) = run_iteration(body.clone(), &symbols.child(), options)? {
+                    return Ok(flow);
                 }
             }
-            _ => {
-                run_ast(new_ast, symbols, options)?;
+        }
+        node => {
+            run_ast(Node::new(node, span), symbols, options)?;
+            Ok(Flow::Normal)
+        }
+    }
+}
+
+/**
+ * The arm of a match that fits its value along with the scope to run the
+ * arm's body in, the arm's bindings get a scope of their own around it
+ */
+fn find_arm(
+    value: Node,
+    arms: Vec<Arm>,
+    span: &Span,
+    symbols: &SymbolStore,
+    options: &Options,
+) -> Result<(Arm, SymbolStore), RuntimeError> {
+    let value = value_from_ast(value, symbols, options)?;
+    for arm in arms {
+        let pattern_span = arm.pattern.span.clone();
+        let mut values = vec![];
+        for operand in operands(&arm.pattern.node, &pattern_span) {
+            values.push(value_from_ast(operand, symbols, options)?);
+        }
+        let mut bound = vec![];
+        if match_pattern(
+            &arm.pattern.node,
+            &value,
+            &mut values.into_iter(),
+            &mut bound,
+        )
+        .map_err(|error| error.or_span(&pattern_span))?
+        {
+            let arm_symbols = symbols.child();
+            for (name, value) in arm.pattern.node.bindings().into_iter().zip(bound) {
+                arm_symbols.define(name, value);
             }
+            return Ok((arm, arm_symbols.child()));
         }
     }
+    Err(no_match_error(&value).or_span(span))
+}
+
+/**
+ * Run a match used as a value, its value is the one the arm that ran ends
+ * with. `required` is for operands, they fail rather than go without one.
+ */
+fn match_value(
+    value: Node,
+    arms: Vec<Arm>,
+    span: &Span,
+    required: bool,
+    symbols: &SymbolStore,
+    options: &Options,
+) -> Result<Option<PrimitiveValue>, RuntimeError> {
+    let (arm, arm_symbols) = find_arm(value, arms, span, symbols, options)?;
+    let (body, value) = arm.split_value();
+    match run_block(body.to_vec(), &arm_symbols, options)? {
+        Flow::Return(_) => return Err(return_in_value_error("a match").or_span(span)),
+        flow => flow.into_value()?,
+    };
+    match value {
+        Some(value) if required => Ok(Some(value_from_ast(value.clone(), &arm_symbols, options)?)),
+        Some(value) => run_ast(value.clone(), &arm_symbols, options),
+        None if required => Err(no_arm_value_error().or_span(span)),
+        None => Ok(None),
+    }
+}
 
-    Ok(None)
+/// Run one pass of a loop body, gives back the flow to stop the loop with
+fn run_iteration(
+    body: Vec<Node>,
+    symbols: &SymbolStore,
+    options: &Options,
+) -> Result<Option<Flow>, RuntimeError> {
+    match run_block(body, symbols, options)? {
+        Flow::Normal | Flow::Continue(_) => Ok(None),
+        Flow::Break(_) => Ok(Some(Flow::Normal)),
+        flow => Ok(Some(flow)),
+    }
 }
 
+/**
+ * Run a single node, errors without a location are pointed at the node
+ */
 fn run_ast(
+    ast: Node,
+    symbols: &SymbolStore,
+    options: &Options,
+) -> Result<Option<PrimitiveValue>, RuntimeError> {
+    let span = ast.span;
+    match ast.node {
+        AST::Match { value, arms } => match_value(*value, arms, &span, false, symbols, options),
+        // only reached when a statement is used as a value, ie `print if ...`
+        node @ (AST::If { .. }
+        | AST::Do { .. }
+        | AST::While { .. }
+        | AST::ForEach { .. }
+        | AST::Break
+        | AST::Continue) => {
+            let statement = statement_name(&node);
+            match run_statement(Node::new(node, span.clone()), symbols, options)? {
+                Flow::Return(_) => Err(return_in_value_error(statement).or_span(&span)),
+                flow => flow.into_value(),
+            }
+        }
+        node => run_node(node, symbols, options).map_err(|error| error.or_span(&span)),
+    }
+}
+
+fn run_node(
     ast: AST,
-    symbols: &mut SymbolStore,
+    symbols: &SymbolStore,
     options: &Options,
 ) -> Result<Option<PrimitiveValue>, RuntimeError> {
     match ast {
         AST::Number { value } => Ok(Some(PrimitiveValue::Num(value))),
+        AST::BigNumber { value } => Ok(Some(PrimitiveValue::from(value))),
+        AST::Float { value } => Ok(Some(PrimitiveValue::Float(value))),
         AST::Bool { value } => Ok(Some(PrimitiveValue::Bool(value))),
         AST::Str { value } => Ok(Some(PrimitiveValue::Str(value))),
         AST::Binary {
@@ -143,145 +681,115 @@ fn run_ast(
         } => Ok(Some(apply_binary_operator(
             *left, *right, operator, symbols, options,
         )?)),
+        AST::Unary { operator, value } => Ok(Some(apply_unary_operator(
+            operator,
+            value_from_ast(*value, symbols, options)?,
+        )?)),
+        ast @ (AST::List { .. }
+        | AST::Map { .. }
+        | AST::Index { .. }
+        | AST::Remove { .. }
+        | AST::Field { .. }) => Ok(Some(value_from_ast(Node::from(ast), symbols, options)?)),
+        AST::Append { value, list } => {
+            let value = value_from_ast(*value, symbols, options)?;
+            value_from_ast(*list, symbols, options)?.append(value)?;
+            Ok(None)
+        }
+        AST::Record { identifier, fields } => {
+            let record_type = RecordType {
+                name: identifier.clone(),
+                fields,
+                variant_of: None,
+            };
+            symbols.define(identifier, PrimitiveValue::RecordType(Rc::new(record_type)));
+            Ok(None)
+        }
+        // only the variants are bound, the enum's name is used as a field type
+        AST::Enum {
+            identifier,
+            variants,
+        } => {
+            for (variant, fields) in variants {
+                let value = RecordType::variant(&identifier, &variant, &fields);
+                symbols.define(variant, value);
+            }
+            Ok(None)
+        }
         AST::Proc {
             identifier,
             body,
             args,
         } => {
-            symbols.insert(
+            symbols.define(
                 identifier,
                 PrimitiveValue::Function(FunctionBody {
-                    body: body,
-                    args: args,
+                    body,
+                    args,
+                    scope: ScopeLink::from(symbols.clone()),
                 }),
             );
             Ok(None)
         }
-        AST::Call { identifier, args } => {
-            if !symbols.contains_key(&identifier) {
-                return Err(RuntimeError {
-                    message: format!("Symbol {} does not exist.", identifier),
-                });
-            }
-
-            if let Some(PrimitiveValue::Function(block)) = symbols.get(&identifier) {
-                // this sets up the function's "scope"
-                let mut f_symbols = symbols.clone();
-
-                let args_requested = block.args.clone();
-                let args_given = args;
-
-                // make sure we get all of the arguments
-                assert_eq!(args_given.len(), args_requested.len());
-
-                for i in 0..args_requested.len() {
-                    if let Some(value) =
-                        run_ast(*args_given[i].clone(), &mut symbols.clone(), options)?
-                    {
-                        f_symbols.insert(args_requested[i].clone(), value);
-                    } else {
-                        return Err(RuntimeError {
-                            message: format!("Could not evaluate arguemnt {:?}", args_given[i]),
-                        });
-                    }
+        AST::Call { identifier, args } => match symbols.get(&identifier) {
+            Some(function) => {
+                check_call(&identifier, &function, args.len())?;
+                let mut values = vec![];
+                for arg in args {
+                    values.push(value_from_ast(arg, symbols, options)?);
                 }
-
-                return run_body_and_return(block.body.clone(), &mut f_symbols, options);
+                call_function(function, values, options)
             }
-
-            Ok(None)
-        }
+            None => Err(RuntimeError::new(format!(
+                "Symbol {} does not exist.",
+                identifier
+            ))),
+        },
         AST::Return { value } => run_ast(*value, symbols, options),
         AST::Semicolon => Ok(None),
         AST::Assign { identifier, value } => {
             if let Some(symbol_value) = run_ast(*value, symbols, options)? {
-                symbols.insert(identifier, symbol_value);
+                symbols.define(identifier, symbol_value);
             }
 
             Ok(None)
         }
-        // we hook into options.print_fn as it's a great way to setup a debugging harness
+        // everything goes through the options.print_fn hook so hosts can capture output
         AST::Print { value } => {
             if let Some(value) = run_ast(*value, symbols, options)? {
-                (options.print_fn)(value);
+                options.print(value);
             } else {
-                println!("''");
+                options.print(PrimitiveValue::Str("''".to_string()));
             }
             Ok(None)
         }
         AST::Symbol { identifier } => {
             if let Some(value) = symbols.get(&identifier) {
-                let new_value = value.clone();
-                return Ok(Some(new_value));
+                Ok(Some(value))
             } else {
-                Err(RuntimeError {
-                    message: format!("Tried to access undefined symbol: {}", identifier),
-                })
-            }
-        }
-        AST::If { this, elifs, el } => {
-            if let Some(PrimitiveValue::Bool(val)) =
-                run_ast(*this.conditional, &mut symbols.clone(), options)?
-            {
-                if val {
-                    // run if statement body and return if needed
-                    return run_body_and_return(this.body, &mut symbols.clone(), options);
-                }
-
-                // go through each elif
-                if let Some(elifs) = elifs {
-                    for elif in elifs {
-                        if let Some(PrimitiveValue::Bool(elif_val)) =
-                            run_ast(*elif.conditional, &mut symbols.clone(), options)?
-                        {
-                            if elif_val {
-                                return run_body_and_return(
-                                    elif.body.clone(),
-                                    &mut symbols.clone(),
-                                    options,
-                                );
-                            }
-                        }
-                    }
-                }
-
-                // if we fall through to here
-                // we haven't found anything
-                if let Some(el) = el {
-                    return run_body_and_return(el.clone(), &mut symbols.clone(), options);
-                }
-            }
-            Ok(None)
-        }
-        AST::Do {
-            count,
-            body,
-            identifier,
-        } => {
-            if let Ok(PrimitiveValue::Num(count)) =
-                value_from_ast(*count, &mut symbols.clone(), options)
-            {
-                let mut f_symbols = symbols.clone();
-                for i in 0..count {
-                    if let Some(identifier_value) = identifier.clone() {
-                        f_symbols.insert(identifier_value.clone(), PrimitiveValue::Num(i));
-                    }
-                    // only return if a return value is given
-                    if let Some(return_value) =
-                        run_body_and_return(body.clone(), &mut f_symbols, options)?
-                    {
-                        return Ok(Some(return_value));
-                    }
-                }
+                Err(RuntimeError::new(format!(
+                    "Tried to access undefined symbol: {}",
+                    identifier
+                )))
             }
-            return Ok(None);
         }
-        AST::Throw { message } => Err(RuntimeError {
-            message: format!("{}", message),
-        }),
+        AST::If { .. }
+        | AST::Match { .. }
+        | AST::Do { .. }
+        | AST::While { .. }
+        | AST::ForEach { .. }
+        | AST::Break
+        | AST::Continue => Err(RuntimeError::new(
+            "Found statement in expression.".to_string(),
+        )),
+        AST::Throw { message } => Err(RuntimeError::new(message)),
         AST::EOF => Ok(None),
-        AST::Program { program: _ } => Err(RuntimeError {
-            message: format!("Found program in AST."),
-        }),
+        AST::Program { program: _ } => Err(RuntimeError::new("Found program in AST.".to_string())),
     }
 }
+
+pub fn repeat_count_error(count: &PrimitiveValue) -> RuntimeError {
+    RuntimeError::new(format!(
+        "Expected a number of times to repeat, found {}",
+        count
+    ))
+}
@@ -0,0 +1,74 @@
+use crate::types::error::RuntimeError;
+use crate::types::primitive_value::PrimitiveValue;
+
+/**
+ * The items a `for each` loop walks over, each one comes with its index.
+ * Both backends get their items from `iterate` so every kind of collection
+ * loops the same way on either of them.
+ */
+pub struct Items {
+    items: Box<dyn Iterator<Item = (PrimitiveValue, PrimitiveValue)>>,
+}
+
+impl Iterator for Items {
+    /// an item and its index
+    type Item = (PrimitiveValue, PrimitiveValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.items.next()
+    }
+}
+
+impl Items {
+    fn new(items: impl Iterator<Item = (PrimitiveValue, PrimitiveValue)> + 'static) -> Items {
+        Items {
+            items: Box::new(items),
+        }
+    }
+
+    /// Items indexed by their position, starting from 0
+    fn positioned(items: impl Iterator<Item = PrimitiveValue> + 'static) -> Items {
+        Items::new(
+            items
+                .enumerate()
+                .map(|(position, item)| (item, PrimitiveValue::Num(position as i32))),
+        )
+    }
+}
+
+/**
+ * Start iterating over `collection`: ranges give their numbers, lists give
+ * their items and strings give their characters. Maps give their values in
+ * insertion order, indexed by their keys.
+ */
+pub fn iterate(collection: PrimitiveValue) -> Result<Items, RuntimeError> {
+    match collection {
+        PrimitiveValue::Range(start, end) => {
+            Ok(Items::positioned((start..=end).map(PrimitiveValue::Num)))
+        }
+        // the loop sees the items the list had when it started
+        PrimitiveValue::List(items) => {
+            let items = items.borrow().clone();
+            Ok(Items::positioned(items.into_iter()))
+        }
+        PrimitiveValue::Map(map) => {
+            let entries: Vec<_> = map
+                .borrow()
+                .iter()
+                .map(|(key, value)| (value.clone(), PrimitiveValue::Str(key.to_string())))
+                .collect();
+            Ok(Items::new(entries.into_iter()))
+        }
+        PrimitiveValue::Str(value) => {
+            let characters: Vec<PrimitiveValue> = value
+                .chars()
+                .map(|character| PrimitiveValue::Str(character.to_string()))
+                .collect();
+            Ok(Items::positioned(characters.into_iter()))
+        }
+        value => Err(RuntimeError::new(format!(
+            "Expected a range, list, map or string to loop over, found {}",
+            value
+        ))),
+    }
+}
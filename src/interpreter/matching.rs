@@ -0,0 +1,107 @@
+use crate::types::ast::{Node, Pattern, AST};
+use crate::types::error::RuntimeError;
+use crate::types::primitive_value::PrimitiveValue;
+use crate::types::span::Span;
+
+/**
+ * The values a pattern is checked against, in the order `match_pattern` takes
+ * them: the type or value each variant name refers to and each literal.
+ * Both backends evaluate all of them before matching so errors come out the
+ * same on either of them.
+ */
+pub fn operands(pattern: &Pattern, span: &Span) -> Vec<Node> {
+    let mut nodes = vec![];
+    collect_operands(pattern, span, &mut nodes);
+    nodes
+}
+
+fn collect_operands(pattern: &Pattern, span: &Span, nodes: &mut Vec<Node>) {
+    match pattern {
+        Pattern::Wildcard | Pattern::Binding(_) => (),
+        Pattern::Literal(literal) => nodes.push(*literal.clone()),
+        Pattern::Variant { identifier, fields } => {
+            let symbol = AST::Symbol {
+                identifier: identifier.to_string(),
+            };
+            nodes.push(Node::new(symbol, span.clone()));
+            for field in fields {
+                collect_operands(field, span, nodes);
+            }
+        }
+    }
+}
+
+/**
+ * Check `value` against a pattern, `operands` are the evaluated `operands` of
+ * the pattern. When it fits the value of each of `Pattern::bindings` is added
+ * to `bound`.
+ */
+pub fn match_pattern(
+    pattern: &Pattern,
+    value: &PrimitiveValue,
+    operands: &mut impl Iterator<Item = PrimitiveValue>,
+    bound: &mut Vec<PrimitiveValue>,
+) -> Result<bool, RuntimeError> {
+    match pattern {
+        Pattern::Wildcard => Ok(true),
+        Pattern::Binding(_) => {
+            bound.push(value.clone());
+            Ok(true)
+        }
+        // values of different types are never equal here, rather than an error
+        Pattern::Literal(_) => {
+            let literal = operands.next().expect("every literal has an operand");
+            Ok(matches!(
+                value.clone().is_equal(literal),
+                Ok(PrimitiveValue::Bool(true))
+            ))
+        }
+        Pattern::Variant { identifier, fields } => {
+            let constructor = operands.next().expect("every variant has an operand");
+            match constructor {
+                PrimitiveValue::RecordType(record_type) => {
+                    if fields.len() != record_type.fields.len() {
+                        return Err(RuntimeError::new(format!(
+                            "{} has {} fields but the pattern gives {}",
+                            identifier,
+                            record_type.fields.len(),
+                            fields.len()
+                        )));
+                    }
+                    let values = match value {
+                        PrimitiveValue::Record(record)
+                            if record.borrow().record_type == record_type =>
+                        {
+                            record.borrow().values.clone()
+                        }
+                        _ => return Ok(false),
+                    };
+                    for (field, value) in fields.iter().zip(&values) {
+                        if !match_pattern(field, value, operands, bound)? {
+                            return Ok(false);
+                        }
+                    }
+                    Ok(true)
+                }
+                // a variant without fields is bound to its only value
+                PrimitiveValue::Record(_) if fields.is_empty() => Ok(matches!(
+                    value.clone().is_equal(constructor),
+                    Ok(PrimitiveValue::Bool(true))
+                )),
+                constructor => Err(RuntimeError::new(format!(
+                    "Expected a variant or record type for the pattern {}, found {}",
+                    identifier, constructor
+                ))),
+            }
+        }
+    }
+}
+
+pub fn no_match_error(value: &PrimitiveValue) -> RuntimeError {
+    RuntimeError::new(format!("No arm of the match fits {}", value))
+}
+
+/// An arm of a match used as a value ended without an expression to give
+pub fn no_arm_value_error() -> RuntimeError {
+    RuntimeError::new("Match arm didn't produce a value".to_string())
+}
@@ -1,19 +1,45 @@
+#[allow(clippy::module_inception)]
 mod interpreter;
-pub use self::interpreter::interpret;
+mod iteration;
+mod matching;
+pub use self::interpreter::{
+    apply_operator, apply_unary_operator, call_function, check_call, interpret, outside_loop_error,
+    repeat_count_error, return_in_value_error, run_program, short_circuits, statement_name,
+};
+pub use self::iteration::{iterate, Items};
+pub use self::matching::{match_pattern, no_arm_value_error, no_match_error, operands};
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::types::ast::AST;
+    use crate::types::ast::{Node, AST};
     use crate::types::binary_operator::BinaryOperator;
+    use crate::types::error::RuntimeError;
     use crate::types::options::Options;
     use crate::types::primitive_value::PrimitiveValue;
-    use std::collections::HashMap;
+    use crate::types::symbol_store::SymbolStore;
+
+    /// Run `source` and return whatever its top level `return` gives back
+    fn run(source: &str) -> Result<Option<PrimitiveValue>, RuntimeError> {
+        let tokens = crate::lexer::tokenize_file(source.to_string(), "test.eye").unwrap();
+        if let AST::Program { program } = crate::parser::build_program(tokens).unwrap() {
+            interpreter::run_body_and_return(program, &SymbolStore::new(), &Options::debug())
+        } else {
+            panic!("Expected a program");
+        }
+    }
+
+    fn run_num(source: &str) -> i32 {
+        match run(source).unwrap() {
+            Some(PrimitiveValue::Num(value)) => value,
+            value => panic!("Expected a number found {:?}", value),
+        }
+    }
 
     #[test]
     fn run_operator_on_numbers() {
         // we have to return the value to read it
-        let program = vec![Box::from(AST::Return {
+        let program = vec![Node::from(AST::Return {
             value: Box::from(AST::Binary {
                 operator: BinaryOperator::Add,
                 right: Box::from(AST::Binary {
@@ -25,8 +51,8 @@ mod test {
             }),
         })];
 
-        let mut symbols = HashMap::new();
-        let result = interpreter::run_body_and_return(program, &mut symbols, &Options::debug())
+        let symbols = SymbolStore::new();
+        let result = interpreter::run_body_and_return(program, &symbols, &Options::debug())
             .unwrap()
             .unwrap();
 
@@ -34,4 +60,143 @@ mod test {
             assert_eq!(result, 50);
         }
     }
+
+    #[test]
+    fn errors_point_to_source() {
+        let source = "define a to be 1;\nif a is 1 {\n    throw \"bad a\";\n}";
+        let tokens = crate::lexer::tokenize_file(source.to_string(), "throw.eye").unwrap();
+        if let AST::Program { program } = crate::parser::build_program(tokens).unwrap() {
+            let symbols = SymbolStore::new();
+            let error =
+                interpreter::run_body_and_return(program, &symbols, &Options::debug()).unwrap_err();
+
+            assert_eq!(error.to_string(), "throw.eye:3:5: bad a");
+        }
+    }
+
+    #[test]
+    fn assign_outer_from_block() {
+        assert_eq!(run_num("define x to be 1; if true { x = 2; } return x;"), 2);
+        assert_eq!(
+            run_num(
+                "define total to be 0; do { total = total + i; } 5 times given i; return total;"
+            ),
+            10
+        );
+    }
+
+    #[test]
+    fn define_shadows_outer() {
+        assert_eq!(
+            run_num("define x to be 1; if true { define x to be 2; x = 3; } return x;"),
+            1
+        );
+        // parameters shadow globals with the same name
+        assert_eq!(
+            run_num("define n to be 1; define f to be { return n; } given (n); return f(5) + n;"),
+            6
+        );
+    }
+
+    #[test]
+    fn block_symbols_stay_in_block() {
+        let error = run("if true { define y to be 1; } return y;").unwrap_err();
+        assert_eq!(error.message, "Tried to access undefined symbol: y");
+    }
+
+    #[test]
+    fn functions_assign_outer() {
+        assert_eq!(
+            run_num(
+                "define count to be 0;
+                define inc to be { count = count + 1; };
+                run inc; run inc;
+                return count;"
+            ),
+            2
+        );
+    }
+
+    #[test]
+    fn functions_capture_defining_scope() {
+        assert_eq!(
+            run_num(
+                "define make to be {
+                    define n to be 10;
+                    define get to be { return n; };
+                    return get;
+                };
+                define n to be 1;
+                define g to be run make;
+                return g();"
+            ),
+            10
+        );
+    }
+
+    #[test]
+    fn assign_undefined() {
+        let error = run("x = 1;").unwrap_err();
+        assert_eq!(error.message, "Tried to assign undefined symbol: x");
+    }
+
+    #[test]
+    fn wrong_argument_count() {
+        let error = run("define f to be { return a; } given (a); return f(1, 2);").unwrap_err();
+        assert_eq!(
+            error.message,
+            "Function f takes 1 arguments but was given 2"
+        );
+    }
+
+    #[test]
+    fn division_and_modulo() {
+        assert_eq!(run_num("return 17 / 5;"), 3);
+        assert_eq!(run_num("return 17 % 5;"), 2);
+    }
+
+    #[test]
+    fn arithmetic_errors() {
+        let error = run("define a to be 0;\nprint 1 / a;").unwrap_err();
+        assert_eq!(error.message, "Division by zero.");
+        assert_eq!(error.span.unwrap().to_string(), "test.eye:2:7");
+
+        let error = run("print 5 % 0;").unwrap_err();
+        assert_eq!(error.message, "Division by zero.");
+
+        let error = run("print 1e308 * 10;").unwrap_err();
+        assert_eq!(error.message, "Arithmetic overflow in 1e308 * 10.");
+    }
+
+    #[test]
+    fn comparison_errors() {
+        let error = run("return \"one\" < 2;").unwrap_err();
+        assert_eq!(error.message, "Operator not implemented for one and 2.");
+        let error = run("return true is at least false;").unwrap_err();
+        assert_eq!(
+            error.message,
+            "Operator not implemented for true and false."
+        );
+    }
+
+    #[test]
+    fn big_numbers() {
+        let value = run("return 2147483647 + 1;").unwrap().unwrap();
+        assert_eq!(value.to_string(), "2147483648");
+        let value = run("return 123456789012345678901234567890 - 1;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(value.to_string(), "123456789012345678901234567889");
+        assert_eq!(run_num("return 4294967296 / 65536;"), 65536);
+    }
+
+    #[test]
+    fn floats() {
+        let value = run("define miles to be 26.2; return miles * 1.609344;").unwrap();
+        assert_eq!(value, Some(PrimitiveValue::Float(26.2 * 1.609344)));
+        let value = run("return 2 + -1.5;").unwrap();
+        assert_eq!(value, Some(PrimitiveValue::Float(0.5)));
+        let value = run("return 2.0 is 2;").unwrap();
+        assert_eq!(value, Some(PrimitiveValue::Bool(true)));
+    }
 }
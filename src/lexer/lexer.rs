@@ -1,30 +1,73 @@
 extern crate regex;
 use regex::Regex;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
 
 use crate::types::binary_operator::BinaryOperator;
-use crate::types::error::TokenError;
+use crate::types::error::{TokenError, TokenErrorKind};
+use crate::types::span::{Location, Span, Spanned};
 use crate::types::token::Token;
 
+/// Operators spelled as words, longer forms come first so they win over `is`
+const WORD_OPERATORS: &[(&str, BinaryOperator)] = &[
+    ("is less than or equal to", BinaryOperator::LessEq),
+    ("is greater than or equal to", BinaryOperator::GreaterEq),
+    ("is less than", BinaryOperator::Less),
+    ("is greater than", BinaryOperator::Greater),
+    ("is at most", BinaryOperator::LessEq),
+    ("is at least", BinaryOperator::GreaterEq),
+    ("is not", BinaryOperator::IsNEq),
+    ("is", BinaryOperator::IsEq),
+    ("and", BinaryOperator::And),
+    ("or", BinaryOperator::Or),
+    ("has", BinaryOperator::Has),
+];
+
 struct Position {
-    index: u64,
+    index: usize,
+    location: Location,
     text: String,
 }
 
 impl Position {
     fn increment_by_str(&mut self, s: String) {
-        self.index += s.len() as u64;
+        self.increment(s.len());
     }
 
-    fn increment(&mut self, n: u64) {
+    fn increment(&mut self, n: usize) {
+        self.location = self.location_after(n);
         self.index += n;
     }
 
-    fn next(&self) -> Box<String> {
-        Box::new(self.text[(self.index as usize)..self.text.len()].to_string())
+    /// Location `n` bytes ahead of the current one
+    fn location_after(&self, n: usize) -> Location {
+        let mut location = self.location;
+        for c in self.text[self.index..self.index + n].chars() {
+            if c == '\n' {
+                location.line += 1;
+                location.column = 1;
+            } else {
+                location.column += 1;
+            }
+        }
+        location
+    }
+
+    /// Error covering the next `n` bytes of source
+    fn error(&self, kind: TokenErrorKind, n: usize, file: &Option<Rc<str>>) -> TokenError {
+        TokenError {
+            kind,
+            span: Span::new(file.clone(), self.location, self.location_after(n)),
+        }
+    }
+
+    fn next(&self) -> String {
+        self.text[self.index..self.text.len()].to_string()
     }
 
     fn has_chars_left(&self) -> bool {
-        self.text.len() > (self.index as usize)
+        self.text.len() > self.index
     }
 
     fn current_char_is(&self, c: char) -> bool {
@@ -32,77 +75,123 @@ impl Position {
     }
 
     fn is_whitespace(&self) -> bool {
-        self.current_char_is(' ') || self.current_char_is('\n') || self.current_char_is('\t')
+        self.current_char_is(' ')
+            || self.current_char_is('\n')
+            || self.current_char_is('\t')
+            || self.current_char_is('\r')
     }
 
     fn current_char(&self) -> char {
-        self.text
-            .chars()
-            .nth(self.index as usize)
-            .unwrap_or_default()
+        self.text[self.index..].chars().next().unwrap_or_default()
     }
 
-    fn re_find(&self, re: &Result<regex::Regex, regex::Error>) -> Result<String, TokenError> {
+    fn re_find(&self, re: &Result<regex::Regex, regex::Error>) -> Option<String> {
         if let Ok(regex) = re {
             let data = self.next();
-            if let Some(result) = regex.find(&data) {
-                Ok(result.as_str().to_string())
-            } else {
-                Err(TokenError)
-            }
+            regex.find(&data).map(|result| result.as_str().to_string())
         } else {
-            Ok("".to_string())
+            None
         }
     }
 
     fn is_keyword(&self, s: &str) -> bool {
-        if let Ok(regex) = Regex::new(&format!(r"^{}", s.to_string())) {
-            regex.is_match(&self.next().clone().to_string())
-        } else {
-            false
+        self.keyword_length(s).is_some()
+    }
+
+    /**
+     * Length of the keyword `s` if the source continues with it, words have to
+     * end there so `island` isn't read as `is` and words can be separated by
+     * any whitespace
+     */
+    fn keyword_length(&self, s: &str) -> Option<usize> {
+        // skip building a regex when it can't match
+        let first_word = s.split(' ').next().unwrap_or_default();
+        if !self.text[self.index..].starts_with(first_word) {
+            return None;
         }
+        KEYWORD_REGEXES.with(|regexes| {
+            let mut regexes = regexes.borrow_mut();
+            let regex = regexes
+                .entry(s.to_string())
+                .or_insert_with(|| keyword_regex(s));
+            regex
+                .find(&self.text[self.index..])
+                .map(|found| found.end())
+        })
+    }
+}
+
+thread_local! {
+    /// keywords show up over and over, only build each one's regex once
+    static KEYWORD_REGEXES: RefCell<HashMap<String, Regex>> = RefCell::new(HashMap::new());
+}
+
+fn keyword_regex(s: &str) -> Regex {
+    let mut pattern = format!("^{}", regex::escape(s).replace(' ', r"\s+"));
+    if s.ends_with(|c: char| c.is_ascii_alphanumeric()) {
+        pattern.push_str(r"\b");
     }
+    Regex::new(&pattern).expect("keywords are valid regexes once escaped")
 }
 
-fn is_match(next_data_str: &Box<String>, re: &Result<regex::Regex, regex::Error>) -> bool {
+fn is_match(next_data_str: &str, re: &Result<regex::Regex, regex::Error>) -> bool {
     if let Ok(regex) = re {
-        regex.is_match(&next_data_str)
+        regex.is_match(next_data_str)
     } else {
         false
     }
 }
 
-pub fn tokenize(source_text: String) -> Result<Vec<Token>, TokenError> {
-    let mut tokens: Vec<Token> = vec![];
+/**
+ * Tokenize the contents of `file_name`, every token's span will point into it
+ */
+pub fn tokenize_file(
+    source_text: String,
+    file_name: &str,
+) -> Result<Vec<Spanned<Token>>, TokenError> {
+    let file: Option<Rc<str>> = Some(Rc::from(file_name));
+    let mut tokens: Vec<Spanned<Token>> = vec![];
     let mut data = Position {
         index: 0,
+        location: Location::start(),
         text: source_text,
     };
 
     let num_regex_result = Regex::new(r"^[-]?\d+");
-    let symbol_regex_result = Regex::new(r"^[A-z][A-z0-9_]*");
-    let type_regex_result = Regex::new(r"^: [0-9A-z]+");
-    let string_regex_result = Regex::new(r#"^"([^"]|\\")*""#);
+    // floats need a fraction, an exponent or both, ie 3.14, 1e6 or 2.5e-3
+    let float_regex_result = Regex::new(r"^\d+(\.\d+([eE][-+]?\d+)?|[eE][-+]?\d+)");
+    let malformed_num_regex_result = Regex::new(r"^\d+[A-Za-z_.][A-Za-z0-9_.]*");
+    let symbol_regex_result = Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*");
+    // a backslash escapes the character after it, so `\"` doesn't end the string
+    let string_regex_result = Regex::new(r#"^"(\\.|[^"\\])*""#);
     let comment_regex_result = Regex::new(r"^//.*");
     // TODO: this is gross
     while data.has_chars_left() {
         let next_data_str = data.next();
+        let start = data.location;
+        let mut found: Option<Token> = None;
 
         // comments
         if is_match(&next_data_str, &comment_regex_result) {
             // skip the comment
-            data.increment_by_str(
-                data.re_find(&comment_regex_result)
-                    .unwrap_or("".to_string()),
-            );
+            data.increment_by_str(data.re_find(&comment_regex_result).unwrap_or_default());
         }
         // multi-char operators
-        else if data.is_keyword("is") {
-            data.increment(2);
-            tokens.push(Token::Operator(BinaryOperator::IsEq))
+        else if let Some((length, operator)) = WORD_OPERATORS
+            .iter()
+            .find_map(|(word, operator)| Some((data.keyword_length(word)?, *operator)))
+        {
+            data.increment(length);
+            found = Some(Token::Operator(operator))
         } else if data.is_keyword("!=") {
             data.increment(2);
-            tokens.push(Token::Operator(BinaryOperator::IsNEq))
+            found = Some(Token::Operator(BinaryOperator::IsNEq))
+        } else if data.is_keyword("<=") {
+            data.increment(2);
+            found = Some(Token::Operator(BinaryOperator::LessEq))
+        } else if data.is_keyword(">=") {
+            data.increment(2);
+            found = Some(Token::Operator(BinaryOperator::GreaterEq))
         }
         // single char operators
         else if let Some(token) = match data.current_char() {
@@ -110,99 +199,189 @@ pub fn tokenize(source_text: String) -> Result<Vec<Token>, TokenError> {
             '-' => Some(Token::Operator(BinaryOperator::Subtract)),
             '*' => Some(Token::Operator(BinaryOperator::Multiply)),
             '/' => Some(Token::Operator(BinaryOperator::Divide)),
+            '%' => Some(Token::Operator(BinaryOperator::Modulo)),
+            '<' => Some(Token::Operator(BinaryOperator::Less)),
+            '>' => Some(Token::Operator(BinaryOperator::Greater)),
             ';' => Some(Token::Semicolon),
             '}' => Some(Token::RBrace),
             '{' => Some(Token::LBrace),
             '(' => Some(Token::LParen),
             ')' => Some(Token::RParen),
+            '[' => Some(Token::LBracket),
+            ']' => Some(Token::RBracket),
             ',' => Some(Token::Comma),
+            '.' => Some(Token::Dot),
+            ':' => Some(Token::Colon),
             '=' => Some(Token::Operator(BinaryOperator::Assign)),
             _ => None,
         } {
             data.increment(1);
-            tokens.push(token)
+            found = Some(token)
         }
         // match whitespace characters
         else if data.is_whitespace() {
             data.increment(1);
         }
         // keywords
-        else if data.is_keyword("is") {
-            data.increment(2);
-            tokens.push(Token::Operator(BinaryOperator::IsEq))
-        } else if data.is_keyword("throw") {
+        else if data.is_keyword("throw") {
             data.increment(5);
-            tokens.push(Token::Throw);
+            found = Some(Token::Throw);
         } else if data.is_keyword("do") {
             data.increment(2);
-            tokens.push(Token::Do);
+            found = Some(Token::Do);
         } else if data.is_keyword("times") {
             data.increment(5);
-            tokens.push(Token::Times);
+            found = Some(Token::Times);
+        } else if data.is_keyword("while") {
+            data.increment(5);
+            found = Some(Token::While);
+        } else if data.is_keyword("until") {
+            data.increment(5);
+            found = Some(Token::Until);
+        } else if data.is_keyword("break") {
+            data.increment(5);
+            found = Some(Token::Break);
+        } else if data.is_keyword("continue") {
+            data.increment(8);
+            found = Some(Token::Continue);
         } else if data.is_keyword("run") {
             data.increment(3);
-            tokens.push(Token::Run);
+            found = Some(Token::Run);
         } else if data.is_keyword("given") {
             data.increment(5);
-            tokens.push(Token::Given);
+            found = Some(Token::Given);
         } else if data.is_keyword("return") {
             data.increment(6);
-            tokens.push(Token::Return);
+            found = Some(Token::Return);
         } else if data.is_keyword("true") {
             data.increment(4);
-            tokens.push(Token::Bool(true));
+            found = Some(Token::Bool(true));
         } else if data.is_keyword("false") {
             data.increment(5);
-            tokens.push(Token::Bool(false));
+            found = Some(Token::Bool(false));
         } else if data.is_keyword("print") {
             data.increment(5);
-            tokens.push(Token::Print);
+            found = Some(Token::Print);
         } else if data.is_keyword("define") {
             data.increment(6);
-            tokens.push(Token::Define);
+            found = Some(Token::Define);
         } else if data.is_keyword("if") {
             data.increment(2);
-            tokens.push(Token::If);
-        } else if data.is_keyword("else") {
+            found = Some(Token::If);
+        } else if data.is_keyword("else") || data.is_keyword("with") {
             data.increment(4);
-            tokens.push(Token::Else);
-        } else if data.is_keyword("with") {
-            data.increment(4);
-            tokens.push(Token::Else);
-        } else if data.is_keyword("to be") {
+            found = Some(Token::Else);
+        } else if data.is_keyword("not") {
+            data.increment(3);
+            found = Some(Token::Not);
+        } else if let Some(length) = data.keyword_length("length of") {
+            data.increment(length);
+            found = Some(Token::LengthOf);
+        } else if let Some(length) = data.keyword_length("pop from") {
+            data.increment(length);
+            found = Some(Token::PopFrom);
+        } else if data.is_keyword("append") {
+            data.increment(6);
+            found = Some(Token::Append);
+        } else if data.is_keyword("record") {
+            data.increment(6);
+            found = Some(Token::Record);
+        } else if let Some(length) = data.keyword_length("one of") {
+            data.increment(length);
+            found = Some(Token::OneOf);
+        } else if data.is_keyword("match") {
             data.increment(5);
-            tokens.push(Token::ToBe);
+            found = Some(Token::Match);
+        } else if data.is_keyword("remove") {
+            data.increment(6);
+            found = Some(Token::Remove);
+        } else if data.is_keyword("from") {
+            data.increment(4);
+            found = Some(Token::From);
+        } else if let Some(length) = data.keyword_length("to be") {
+            data.increment(length);
+            found = Some(Token::ToBe);
+        } else if data.is_keyword("to") {
+            data.increment(2);
+            found = Some(Token::Operator(BinaryOperator::Range));
+        } else if let Some(length) = data.keyword_length("for each") {
+            data.increment(length);
+            found = Some(Token::ForEach);
+        } else if data.is_keyword("in") {
+            data.increment(2);
+            found = Some(Token::In);
         }
         // variable sequences ie numbers, symbols, strings
-        else if is_match(&next_data_str, &num_regex_result) {
+        else if let Some(num) = data.re_find(&float_regex_result) {
+            // anything glued onto the end makes it malformed, ie 1.5.2 or 1e5x
+            if let Some(rest) = data.text[data.index + num.len()..].chars().next() {
+                if rest.is_ascii_alphanumeric() || rest == '_' || rest == '.' {
+                    let word: String = data.text[data.index..]
+                        .chars()
+                        .take_while(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '.')
+                        .collect();
+                    return Err(data.error(
+                        TokenErrorKind::MalformedNumber(word.clone()),
+                        word.len(),
+                        &file,
+                    ));
+                }
+            }
+            match num.parse::<f64>() {
+                Ok(value) if value.is_finite() => found = Some(Token::Float(value)),
+                _ => {
+                    return Err(data.error(
+                        TokenErrorKind::FloatOverflow(num.clone()),
+                        num.len(),
+                        &file,
+                    ))
+                }
+            }
+            data.increment_by_str(num);
+        } else if is_match(&next_data_str, &malformed_num_regex_result) {
+            let word = data
+                .re_find(&malformed_num_regex_result)
+                .unwrap_or_default();
+            return Err(data.error(
+                TokenErrorKind::MalformedNumber(word.clone()),
+                word.len(),
+                &file,
+            ));
+        } else if is_match(&next_data_str, &num_regex_result) {
             let num = data
                 .re_find(&num_regex_result)
-                .unwrap_or("".to_string())
+                .unwrap_or_default()
                 .to_string();
-            data.increment_by_str(num.clone());
-            tokens.push(Token::Number(num.parse().unwrap()));
+            // anything too big for a number is read as a bignum
+            found = Some(match num.parse() {
+                Ok(value) => Token::Number(value),
+                Err(_) => Token::BigNumber(num.parse().unwrap_or_default()),
+            });
+            data.increment_by_str(num);
         } else if is_match(&next_data_str, &symbol_regex_result) {
-            if let Ok(symbol_name) = data.re_find(&symbol_regex_result) {
+            if let Some(symbol_name) = data.re_find(&symbol_regex_result) {
                 data.increment_by_str(symbol_name.clone());
-                tokens.push(Token::Symbol(symbol_name));
+                found = Some(Token::Symbol(symbol_name));
             }
-        } else if is_match(&next_data_str, &type_regex_result) {
-            let type_value = data
-                .re_find(&type_regex_result)
-                .unwrap_or("".to_string())
-                .to_string();
-            data.increment_by_str(type_value.clone());
-
-            let value_without_colon = type_value[1..type_value.len()].to_string();
-            tokens.push(Token::Type(value_without_colon));
         } else if is_match(&next_data_str, &string_regex_result) {
-            let type_value = data.re_find(&string_regex_result).unwrap_or("".to_string());
+            let type_value = data.re_find(&string_regex_result).unwrap_or_default();
             data.increment_by_str(type_value.clone());
 
             let value_without_quotes = type_value[1..type_value.len() - 1].to_string();
-            tokens.push(Token::Str(value_without_quotes));
+            found = Some(Token::Str(value_without_quotes));
+        } else if data.current_char_is('"') {
+            // the string regex only fails when there's no closing quote
+            let line_length = next_data_str.find('\n').unwrap_or(next_data_str.len());
+            return Err(data.error(TokenErrorKind::UnterminatedString, line_length, &file));
         } else {
-            panic!("Could not find token for {}", next_data_str);
+            let c = data.current_char();
+            return Err(data.error(TokenErrorKind::UnknownCharacter(c), c.len_utf8(), &file));
+        }
+
+        // attach the span of whatever was just read to the new token
+        if let Some(token) = found {
+            let span = Span::new(file.clone(), start, data.location);
+            tokens.push(Spanned::new(token, span));
         }
     }
 
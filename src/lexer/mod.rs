@@ -1,15 +1,28 @@
+#[allow(clippy::module_inception)]
 mod lexer;
-pub use self::lexer::tokenize;
+pub use self::lexer::tokenize_file;
 
 #[cfg(test)]
 mod tokens {
     use super::*;
+    use crate::diagnostics::{Diagnostic, Style};
     use crate::types::binary_operator::BinaryOperator;
+    use crate::types::error::TokenErrorKind;
+    use crate::types::span::Location;
     use crate::types::token::Token;
+
+    fn token_kinds(program: String) -> Vec<Token> {
+        lexer::tokenize_file(program, "test.eye")
+            .unwrap()
+            .into_iter()
+            .map(|token| token.node)
+            .collect()
+    }
+
     #[test]
     fn neg_numbers() {
         let program = "-1;".to_string();
-        let tokens = lexer::tokenize(program).unwrap();
+        let tokens = token_kinds(program);
         assert_eq!(
             tokens,
             vec![
@@ -22,7 +35,7 @@ mod tokens {
     #[test]
     fn add_numbers() {
         let program = "10 + 20 * 1;".to_string();
-        let tokens = lexer::tokenize(program).unwrap();
+        let tokens = token_kinds(program);
         assert_eq!(
             tokens,
             vec![
@@ -36,10 +49,189 @@ mod tokens {
         )
     }
 
+    #[test]
+    fn comparisons() {
+        let tokens = token_kinds("a < b <= c > d >= e".to_string());
+        let operators: Vec<Token> = tokens.into_iter().skip(1).step_by(2).collect();
+        assert_eq!(operators, token_kinds("< <= > >=".to_string()));
+        assert_eq!(
+            operators,
+            vec![
+                Token::Operator(BinaryOperator::Less),
+                Token::Operator(BinaryOperator::LessEq),
+                Token::Operator(BinaryOperator::Greater),
+                Token::Operator(BinaryOperator::GreaterEq),
+            ]
+        );
+
+        let words = "is less than is at most is greater than\nor equal to is at least is not is";
+        assert_eq!(
+            token_kinds(words.to_string()),
+            vec![
+                Token::Operator(BinaryOperator::Less),
+                Token::Operator(BinaryOperator::LessEq),
+                Token::Operator(BinaryOperator::GreaterEq),
+                Token::Operator(BinaryOperator::GreaterEq),
+                Token::Operator(BinaryOperator::IsNEq),
+                Token::Operator(BinaryOperator::IsEq),
+            ]
+        );
+    }
+
+    #[test]
+    fn escaped_quotes() {
+        assert_eq!(
+            token_kinds(r#""a\"{" "b\\""#.to_string()),
+            vec![
+                Token::Str(r#"a\"{"#.to_string()),
+                Token::Str(r#"b\\"#.to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn logic_keywords() {
+        assert_eq!(
+            token_kinds("not a and b or nothing".to_string()),
+            vec![
+                Token::Not,
+                Token::Symbol("a".to_string()),
+                Token::Operator(BinaryOperator::And),
+                Token::Symbol("b".to_string()),
+                Token::Operator(BinaryOperator::Or),
+                Token::Symbol("nothing".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn keywords_end_at_word_boundaries() {
+        assert_eq!(
+            token_kinds("island double".to_string()),
+            vec![
+                Token::Symbol("island".to_string()),
+                Token::Symbol("double".to_string())
+            ]
+        );
+        assert_eq!(
+            token_kinds("x is lesser".to_string()),
+            vec![
+                Token::Symbol("x".to_string()),
+                Token::Operator(BinaryOperator::IsEq),
+                Token::Symbol("lesser".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn loop_keywords() {
+        assert_eq!(
+            token_kinds("while until break continue whiled".to_string()),
+            vec![
+                Token::While,
+                Token::Until,
+                Token::Break,
+                Token::Continue,
+                Token::Symbol("whiled".to_string()),
+            ]
+        );
+        assert_eq!(
+            token_kinds("for  each c in 1 to total define x to be".to_string()),
+            vec![
+                Token::ForEach,
+                Token::Symbol("c".to_string()),
+                Token::In,
+                Token::Number(1),
+                Token::Operator(BinaryOperator::Range),
+                Token::Symbol("total".to_string()),
+                Token::Define,
+                Token::Symbol("x".to_string()),
+                Token::ToBe,
+            ]
+        );
+    }
+
+    #[test]
+    fn lists() {
+        assert_eq!(
+            token_kinds("append xs[0] to _ys; pop from length of".to_string()),
+            vec![
+                Token::Append,
+                Token::Symbol("xs".to_string()),
+                Token::LBracket,
+                Token::Number(0),
+                Token::RBracket,
+                Token::Operator(BinaryOperator::Range),
+                Token::Symbol("_ys".to_string()),
+                Token::Semicolon,
+                Token::PopFrom,
+                Token::LengthOf,
+            ]
+        );
+    }
+
+    #[test]
+    fn maps() {
+        assert_eq!(
+            token_kinds("[\"a\": b, c: number] has remove from".to_string()),
+            vec![
+                Token::LBracket,
+                Token::Str("a".to_string()),
+                Token::Colon,
+                Token::Symbol("b".to_string()),
+                Token::Comma,
+                Token::Symbol("c".to_string()),
+                Token::Colon,
+                Token::Symbol("number".to_string()),
+                Token::RBracket,
+                Token::Operator(BinaryOperator::Has),
+                Token::Remove,
+                Token::From,
+            ]
+        );
+    }
+
+    #[test]
+    fn records() {
+        assert_eq!(
+            token_kinds("record { x:number, y: Point } p.x".to_string()),
+            vec![
+                Token::Record,
+                Token::LBrace,
+                Token::Symbol("x".to_string()),
+                Token::Colon,
+                Token::Symbol("number".to_string()),
+                Token::Comma,
+                Token::Symbol("y".to_string()),
+                Token::Colon,
+                Token::Symbol("Point".to_string()),
+                Token::RBrace,
+                Token::Symbol("p".to_string()),
+                Token::Dot,
+                Token::Symbol("x".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn enums() {
+        assert_eq!(
+            token_kinds("one  of A or B match x".to_string()),
+            vec![
+                Token::OneOf,
+                Token::Symbol("A".to_string()),
+                Token::Operator(BinaryOperator::Or),
+                Token::Symbol("B".to_string()),
+                Token::Match,
+                Token::Symbol("x".to_string()),
+            ]
+        );
+    }
+
     #[test]
     fn def_proc() {
         let program = "define a to be {return false;}".to_string();
-        let tokens = lexer::tokenize(program).unwrap();
+        let tokens = token_kinds(program);
         assert_eq!(
             tokens,
             vec![
@@ -54,4 +246,113 @@ mod tokens {
             ]
         )
     }
+
+    #[test]
+    fn spans() {
+        let program = "define a to be 10;\nprint a;".to_string();
+        let tokens = lexer::tokenize_file(program, "spans.eye").unwrap();
+
+        let print = &tokens[5];
+        assert_eq!(print.node, Token::Print);
+        assert_eq!(print.span.start, Location { line: 2, column: 1 });
+        assert_eq!(print.span.end, Location { line: 2, column: 6 });
+        assert_eq!(print.span.to_string(), "spans.eye:2:1");
+
+        let ten = &tokens[3];
+        assert_eq!(
+            ten.span.start,
+            Location {
+                line: 1,
+                column: 16
+            }
+        );
+        assert_eq!(
+            ten.span.end,
+            Location {
+                line: 1,
+                column: 18
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_character() {
+        let program = "define a to be 1;\nprint a @ 2;".to_string();
+        let error = lexer::tokenize_file(program.clone(), "unknown.eye").unwrap_err();
+        assert_eq!(error.kind, TokenErrorKind::UnknownCharacter('@'));
+        assert_eq!(error.span.start, Location { line: 2, column: 9 });
+        assert_eq!(
+            Diagnostic::from(&error).render(&program, Style::Plain),
+            "error[E0001]: unknown character `@`
+ --> unknown.eye:2:9
+  |
+2 | print a @ 2;
+  |         ^"
+        );
+    }
+
+    #[test]
+    fn unterminated_string() {
+        let error =
+            lexer::tokenize_file("print \"abc;\nprint 1;".to_string(), "test.eye").unwrap_err();
+        assert_eq!(error.kind, TokenErrorKind::UnterminatedString);
+        assert_eq!(error.span.start, Location { line: 1, column: 7 });
+        assert_eq!(
+            error.span.end,
+            Location {
+                line: 1,
+                column: 12
+            }
+        );
+    }
+
+    #[test]
+    fn malformed_number() {
+        let error = lexer::tokenize_file("print 12ab;".to_string(), "test.eye").unwrap_err();
+        assert_eq!(
+            error.kind,
+            TokenErrorKind::MalformedNumber("12ab".to_string())
+        );
+        assert_eq!(error.span.end.column, 11);
+    }
+
+    #[test]
+    fn big_numbers() {
+        let tokens = lexer::tokenize_file("print 2147483648;".to_string(), "test.eye").unwrap();
+        assert_eq!(
+            tokens[1].node,
+            Token::BigNumber("2147483648".parse().unwrap())
+        );
+        let tokens = lexer::tokenize_file("print 2147483647;".to_string(), "test.eye").unwrap();
+        assert_eq!(tokens[1].node, Token::Number(2147483647));
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(
+            token_kinds("2.75 1e6 2.5E-3 10".to_string()),
+            vec![
+                Token::Float(2.75),
+                Token::Float(1e6),
+                Token::Float(2.5e-3),
+                Token::Number(10),
+            ]
+        );
+
+        let error = lexer::tokenize_file("print 1.5.2;".to_string(), "test.eye").unwrap_err();
+        assert_eq!(
+            error.kind,
+            TokenErrorKind::MalformedNumber("1.5.2".to_string())
+        );
+        let error = lexer::tokenize_file("print 1e5x;".to_string(), "test.eye").unwrap_err();
+        assert_eq!(
+            error.kind,
+            TokenErrorKind::MalformedNumber("1e5x".to_string())
+        );
+        let error = lexer::tokenize_file("print 1e999;".to_string(), "test.eye").unwrap_err();
+        assert_eq!(
+            error.kind,
+            TokenErrorKind::FloatOverflow("1e999".to_string())
+        );
+    }
 }
@@ -0,0 +1,21 @@
+//! eyelang, a small scripting language that reads like English.
+//!
+//! `Engine` is the entry point for running eye code from Rust, the lexer,
+//! parser, interpreter and bytecode VM it's built on are public too.
+
+pub mod diagnostics;
+mod engine;
+pub mod file;
+pub mod interpreter;
+pub mod lexer;
+pub mod parser;
+pub mod repl;
+mod tests;
+pub mod types;
+pub mod vm;
+
+pub use engine::Engine;
+pub use types::error::{Error, RuntimeError};
+pub use types::native_function::NativeFunction;
+pub use types::options::{Backend, Options};
+pub use types::primitive_value::PrimitiveValue;
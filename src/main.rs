@@ -1,29 +1,50 @@
-mod file;
-mod interpreter;
-mod lexer;
-mod parser;
-mod tests;
-mod types;
+use eye::diagnostics::Style;
+use eye::repl::Repl;
+use eye::{Backend, Engine, Error, Options};
 
-use types::options::Options;
-use types::symbol_store::create_symbol_store;
+use std::time::Instant;
 
 fn main() {
-    let root_dir = std::env::current_dir()
-        .unwrap()
-        .to_str()
-        .unwrap()
-        .to_string();
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() > 1 {
-        let first_source_path = args[1].to_string();
-        let source_text = file::read_source_file(first_source_path, root_dir);
-        if let Ok(tokens) = lexer::tokenize(source_text) {
-            let ast = parser::build_program(tokens);
-            let symbols = create_symbol_store();
-            interpreter::interpret(ast, symbols, &Options::default());
+    let mut options = Options::default();
+    let mut args: Vec<String> = vec![];
+    let mut time = false;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--bytecode" => options.backend = Backend::Bytecode,
+            "--time" => time = true,
+            _ => args.push(arg),
+        }
+    }
+
+    if let Some(path) = args.first() {
+        let engine = Engine::with_options(options);
+        // the program is parsed once for both the warnings and the run
+        let source = Engine::read_file(path).unwrap_or_else(|error| exit_with(error, ""));
+        let program = engine
+            .parse(&source, path)
+            .unwrap_or_else(|error| exit_with(error, &source));
+        for warning in engine.check(&program) {
+            eprintln!("{}", warning.render(&source, Style::for_stderr()));
+        }
+        let now = Instant::now();
+        if let Err(error) = engine.run_parsed(program) {
+            exit_with(error, &source);
+        }
+        if time {
+            eprintln!("Done in {}ms", now.elapsed().as_millis());
         }
     } else {
-        println!("First argument must be a source file.")
+        let mut repl = Repl::new(options, Style::for_stderr());
+        let stdin = std::io::stdin();
+        if let Err(error) = repl.run(stdin.lock(), std::io::stdout()) {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
     }
 }
+
+/// Report an error in `source` and stop
+fn exit_with(error: Error, source: &str) -> ! {
+    eprintln!("{}", error.render(source, Style::for_stderr()));
+    std::process::exit(1);
+}
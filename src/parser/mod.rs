@@ -1,12 +1,25 @@
+#[allow(clippy::module_inception)]
 mod parser;
 pub use self::parser::build_program;
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::types::ast::AST;
+    use crate::types::ast::{Arm, Node, Pattern, AST};
     use crate::types::binary_operator::BinaryOperator;
+    use crate::types::error::{ParseError, ParseErrorKind};
+    use crate::types::span::Spanned;
     use crate::types::token::Token;
+    use crate::types::unary_operator::UnaryOperator;
+
+    fn parse_errors(source: &str) -> Vec<ParseError> {
+        let tokens = crate::lexer::tokenize_file(source.to_string(), "test.eye").unwrap();
+        parser::build_program(tokens).unwrap_err()
+    }
+
+    fn build(tokens: Vec<Token>) -> AST {
+        parser::build_program(tokens.into_iter().map(Spanned::from).collect()).unwrap()
+    }
 
     #[test]
     fn add_numbers() {
@@ -18,12 +31,12 @@ mod test {
             Token::Number(1),
             Token::Semicolon,
         ];
-        let ast = parser::build_program(tokens);
+        let ast = build(tokens);
 
         assert_eq!(
             ast,
             AST::Program {
-                program: vec![Box::from(AST::Binary {
+                program: vec![Node::from(AST::Binary {
                     operator: BinaryOperator::Add,
                     right: Box::from(AST::Binary {
                         operator: BinaryOperator::Multiply,
@@ -36,6 +49,45 @@ mod test {
         )
     }
 
+    #[test]
+    fn assignment() {
+        let symbol = |identifier: &str| {
+            Box::from(AST::Symbol {
+                identifier: identifier.to_string(),
+            })
+        };
+        // `a = b = 1 or 2` is `a = (b = (1 or 2))`
+        let tokens = vec![
+            Token::Symbol("a".to_string()),
+            Token::Operator(BinaryOperator::Assign),
+            Token::Symbol("b".to_string()),
+            Token::Operator(BinaryOperator::Assign),
+            Token::Number(1),
+            Token::Operator(BinaryOperator::Or),
+            Token::Number(2),
+            Token::Semicolon,
+        ];
+
+        assert_eq!(
+            build(tokens),
+            AST::Program {
+                program: vec![Node::from(AST::Binary {
+                    operator: BinaryOperator::Assign,
+                    left: symbol("a"),
+                    right: Box::from(AST::Binary {
+                        operator: BinaryOperator::Assign,
+                        left: symbol("b"),
+                        right: Box::from(AST::Binary {
+                            operator: BinaryOperator::Or,
+                            left: Box::from(AST::Number { value: 1 }),
+                            right: Box::from(AST::Number { value: 2 })
+                        })
+                    })
+                })]
+            }
+        )
+    }
+
     #[test]
     fn print_is_eq() {
         let tokens = vec![
@@ -45,12 +97,12 @@ mod test {
             Token::Number(20),
             Token::Semicolon,
         ];
-        let ast = parser::build_program(tokens);
+        let ast = build(tokens);
 
         assert_eq!(
             ast,
             AST::Program {
-                program: vec![Box::from(AST::Print {
+                program: vec![Node::from(AST::Print {
                     value: Box::from(AST::Binary {
                         operator: BinaryOperator::IsEq,
                         left: Box::from(AST::Number { value: 10 }),
@@ -60,4 +112,470 @@ mod test {
             }
         )
     }
+
+    #[test]
+    fn print_is_not_eq() {
+        let tokens = crate::lexer::tokenize_file("print a != b;".to_string(), "test.eye").unwrap();
+        let words =
+            crate::lexer::tokenize_file("print a is not b;".to_string(), "test.eye").unwrap();
+        let expected = AST::Program {
+            program: vec![Node::from(AST::Print {
+                value: Box::from(AST::Binary {
+                    operator: BinaryOperator::IsNEq,
+                    left: Box::from(AST::Symbol {
+                        identifier: "a".to_string(),
+                    }),
+                    right: Box::from(AST::Symbol {
+                        identifier: "b".to_string(),
+                    }),
+                }),
+            })],
+        };
+        assert_eq!(
+            build(tokens.into_iter().map(|token| token.node).collect()),
+            expected
+        );
+        assert_eq!(
+            build(words.into_iter().map(|token| token.node).collect()),
+            expected
+        );
+    }
+
+    #[test]
+    fn parentheses_and_negation() {
+        let tokens = crate::lexer::tokenize_file("-(a + 1) * 2;".to_string(), "test.eye").unwrap();
+        let ast = build(tokens.into_iter().map(|token| token.node).collect());
+        let sum = AST::Binary {
+            operator: BinaryOperator::Add,
+            left: Box::from(Node::from(AST::Symbol {
+                identifier: "a".to_string(),
+            })),
+            right: Box::from(Node::from(AST::Number { value: 1 })),
+        };
+        assert_eq!(
+            ast,
+            AST::Program {
+                program: vec![Node::from(AST::Binary {
+                    operator: BinaryOperator::Multiply,
+                    left: Box::from(Node::from(AST::Unary {
+                        operator: UnaryOperator::Negate,
+                        value: Box::from(Node::from(sum)),
+                    })),
+                    right: Box::from(Node::from(AST::Number { value: 2 })),
+                })]
+            }
+        );
+
+        let errors = parse_errors("print (1 + 2;");
+        assert_eq!(errors[0].kind, ParseErrorKind::UnexpectedToken);
+        assert_eq!(errors[0].expected, "`)`");
+    }
+
+    #[test]
+    fn logic_precedence() {
+        let tokens =
+            crate::lexer::tokenize_file("not a is b and c or d;".to_string(), "test.eye").unwrap();
+        let ast = build(tokens.into_iter().map(|token| token.node).collect());
+        let symbol = |identifier: &str| {
+            Box::from(Node::from(AST::Symbol {
+                identifier: identifier.to_string(),
+            }))
+        };
+        let not = AST::Unary {
+            operator: UnaryOperator::Not,
+            value: Box::from(Node::from(AST::Binary {
+                operator: BinaryOperator::IsEq,
+                left: symbol("a"),
+                right: symbol("b"),
+            })),
+        };
+        let and = AST::Binary {
+            operator: BinaryOperator::And,
+            left: Box::from(Node::from(not)),
+            right: symbol("c"),
+        };
+        assert_eq!(
+            ast,
+            AST::Program {
+                program: vec![Node::from(AST::Binary {
+                    operator: BinaryOperator::Or,
+                    left: Box::from(Node::from(and)),
+                    right: symbol("d"),
+                })]
+            }
+        );
+    }
+
+    #[test]
+    fn while_loops() {
+        let tokens =
+            crate::lexer::tokenize_file("until done { break; continue; }".to_string(), "test.eye")
+                .unwrap();
+        let ast = build(tokens.into_iter().map(|token| token.node).collect());
+        assert_eq!(
+            ast,
+            AST::Program {
+                program: vec![Node::from(AST::While {
+                    conditional: Box::from(Node::from(AST::Symbol {
+                        identifier: "done".to_string(),
+                    })),
+                    body: vec![
+                        Node::from(AST::Break),
+                        Node::from(AST::Semicolon),
+                        Node::from(AST::Continue),
+                        Node::from(AST::Semicolon),
+                    ],
+                    until: true,
+                })]
+            }
+        );
+    }
+
+    #[test]
+    fn lists() {
+        let tokens =
+            crate::lexer::tokenize_file("xs[length of xs - 1] = [1, [2]];".to_string(), "test.eye")
+                .unwrap();
+        let ast = build(tokens.into_iter().map(|token| token.node).collect());
+        let xs = || {
+            Box::from(Node::from(AST::Symbol {
+                identifier: "xs".to_string(),
+            }))
+        };
+        let last = AST::Binary {
+            operator: BinaryOperator::Subtract,
+            left: Box::from(Node::from(AST::Unary {
+                operator: UnaryOperator::Length,
+                value: xs(),
+            })),
+            right: Box::from(Node::from(AST::Number { value: 1 })),
+        };
+        let list = AST::List {
+            items: vec![
+                Node::from(AST::Number { value: 1 }),
+                Node::from(AST::List {
+                    items: vec![Node::from(AST::Number { value: 2 })],
+                }),
+            ],
+        };
+        assert_eq!(
+            ast,
+            AST::Program {
+                program: vec![Node::from(AST::Binary {
+                    operator: BinaryOperator::Assign,
+                    left: Box::from(Node::from(AST::Index {
+                        collection: xs(),
+                        index: Box::from(Node::from(last)),
+                    })),
+                    right: Box::from(Node::from(list)),
+                })]
+            }
+        );
+
+        let errors = parse_errors("print [1 2];");
+        assert_eq!(errors[0].expected, "`,` or `]`");
+        let errors = parse_errors("append 1 xs;");
+        assert_eq!(errors[0].expected, "`to`");
+    }
+
+    #[test]
+    fn maps() {
+        let tokens = crate::lexer::tokenize_file(
+            "remove \"a\" from [\"a\": 1, \"b\": [:]];".to_string(),
+            "test.eye",
+        )
+        .unwrap();
+        let ast = build(tokens.into_iter().map(|token| token.node).collect());
+        let string = |value: &str| {
+            Node::from(AST::Str {
+                value: value.to_string(),
+            })
+        };
+        let map = AST::Map {
+            entries: vec![
+                (string("a"), Node::from(AST::Number { value: 1 })),
+                (string("b"), Node::from(AST::Map { entries: vec![] })),
+            ],
+        };
+        assert_eq!(
+            ast,
+            AST::Program {
+                program: vec![Node::from(AST::Remove {
+                    key: Box::from(string("a")),
+                    map: Box::from(Node::from(map)),
+                })]
+            }
+        );
+
+        // a map's entries all need keys and a list's items can't have them
+        let errors = parse_errors("print [\"a\": 1, 2];");
+        assert_eq!(errors[0].expected, "`:`");
+        let errors = parse_errors("print [1, \"a\": 2];");
+        assert_eq!(errors[0].expected, "`,` or `]`");
+    }
+
+    #[test]
+    fn records() {
+        let tokens = crate::lexer::tokenize_file(
+            "define Point to be record { x: number, y: number }; p.x = q.y;".to_string(),
+            "test.eye",
+        )
+        .unwrap();
+        let ast = build(tokens.into_iter().map(|token| token.node).collect());
+        let field = |record: &str, field: &str| {
+            Box::from(Node::from(AST::Field {
+                record: Box::from(Node::from(AST::Symbol {
+                    identifier: record.to_string(),
+                })),
+                field: field.to_string(),
+            }))
+        };
+        assert_eq!(
+            ast,
+            AST::Program {
+                program: vec![
+                    Node::from(AST::Record {
+                        identifier: "Point".to_string(),
+                        fields: vec![
+                            ("x".to_string(), "number".to_string()),
+                            ("y".to_string(), "number".to_string()),
+                        ],
+                    }),
+                    Node::from(AST::Binary {
+                        operator: BinaryOperator::Assign,
+                        left: field("p", "x"),
+                        right: field("q", "y"),
+                    }),
+                ]
+            }
+        );
+
+        let errors = parse_errors("define Point to be record { x, y };");
+        assert_eq!(errors[0].expected, "`:`");
+        let errors = parse_errors("define Point to be record { x: 1 };");
+        assert_eq!(errors[0].expected, "field type");
+        let errors = parse_errors("print p.;");
+        assert_eq!(errors[0].expected, "field name");
+    }
+
+    #[test]
+    fn enums_and_match() {
+        let tokens = crate::lexer::tokenize_file(
+            "define Shape to be one of Circle(r) or Pair(a, b) or Empty;
+            match s { Pair(Circle(r), _) { } -1 { } Empty { } other { } }"
+                .to_string(),
+            "test.eye",
+        )
+        .unwrap();
+        let ast = build(tokens.into_iter().map(|token| token.node).collect());
+        let arm = |pattern: Pattern| Arm {
+            pattern: Spanned::from(pattern),
+            body: vec![],
+        };
+        let variant = |identifier: &str, fields: Vec<Pattern>| Pattern::Variant {
+            identifier: identifier.to_string(),
+            fields,
+        };
+        assert_eq!(
+            ast,
+            AST::Program {
+                program: vec![
+                    Node::from(AST::Enum {
+                        identifier: "Shape".to_string(),
+                        variants: vec![
+                            ("Circle".to_string(), vec!["r".to_string()]),
+                            ("Pair".to_string(), vec!["a".to_string(), "b".to_string()]),
+                            ("Empty".to_string(), vec![]),
+                        ],
+                    }),
+                    Node::from(AST::Match {
+                        value: Box::from(Node::from(AST::Symbol {
+                            identifier: "s".to_string(),
+                        })),
+                        arms: vec![
+                            arm(variant(
+                                "Pair",
+                                vec![
+                                    variant("Circle", vec![Pattern::Binding("r".to_string())]),
+                                    Pattern::Wildcard,
+                                ]
+                            )),
+                            arm(Pattern::Literal(Box::from(Node::from(AST::Number {
+                                value: -1
+                            })))),
+                            arm(variant("Empty", vec![])),
+                            arm(Pattern::Binding("other".to_string())),
+                        ],
+                    }),
+                ]
+            }
+        );
+
+        let errors = parse_errors("define Shape to be one of circle(r);");
+        assert_eq!(
+            errors[0].expected,
+            "variant name starting with a capital letter"
+        );
+        let errors = parse_errors("match s { Pair(a, a) { } }");
+        assert_eq!(errors[0].kind, ParseErrorKind::DuplicateBinding);
+        assert_eq!(errors[0].expected, "`a` to be bound only once in a pattern");
+        let errors = parse_errors("match s { -x { } }");
+        assert_eq!(errors[0].expected, "pattern");
+        let errors = parse_errors("match s { 1 { }");
+        assert_eq!(errors[0].kind, ParseErrorKind::UnterminatedBlock);
+    }
+
+    #[test]
+    fn for_each_loops() {
+        let tokens = crate::lexer::tokenize_file(
+            "for each n in 1 to last - 1 given i {}".to_string(),
+            "test.eye",
+        )
+        .unwrap();
+        let ast = build(tokens.into_iter().map(|token| token.node).collect());
+        let end = AST::Binary {
+            operator: BinaryOperator::Subtract,
+            left: Box::from(Node::from(AST::Symbol {
+                identifier: "last".to_string(),
+            })),
+            right: Box::from(Node::from(AST::Number { value: 1 })),
+        };
+        assert_eq!(
+            ast,
+            AST::Program {
+                program: vec![Node::from(AST::ForEach {
+                    identifier: "n".to_string(),
+                    index: Some("i".to_string()),
+                    collection: Box::from(Node::from(AST::Binary {
+                        operator: BinaryOperator::Range,
+                        left: Box::from(Node::from(AST::Number { value: 1 })),
+                        right: Box::from(Node::from(end)),
+                    })),
+                    body: vec![],
+                })]
+            }
+        );
+
+        let errors = parse_errors("for each in xs {}");
+        assert_eq!(errors[0].expected, "name for each item");
+        let errors = parse_errors("for each x of xs {}");
+        assert_eq!(errors[0].expected, "`in`");
+    }
+
+    #[test]
+    fn node_spans() {
+        let tokens =
+            crate::lexer::tokenize_file("define a to be\n  10 + 2;".to_string(), "test.eye")
+                .unwrap();
+        let ast = parser::build_program(tokens).unwrap();
+
+        if let AST::Program { program } = ast {
+            let define = &program[0];
+            assert_eq!(define.span.start.line, 1);
+            assert_eq!(define.span.end.line, 2);
+
+            if let AST::Assign { value, .. } = &define.node {
+                assert_eq!(value.span.start.column, 3);
+                assert_eq!(value.span.end.column, 9);
+            } else {
+                panic!("Expected assign found {:?}", define);
+            }
+        }
+    }
+
+    #[test]
+    fn unexpected_token() {
+        let errors = parse_errors("define add to be { return a + b; } given (a, b;");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ParseErrorKind::UnexpectedToken);
+        assert_eq!(errors[0].expected, "`)`");
+        assert_eq!(errors[0].found, Token::Semicolon);
+    }
+
+    #[test]
+    fn missing_to_be() {
+        let errors = parse_errors("define a 10;");
+        assert_eq!(errors[0].kind, ParseErrorKind::MissingToBe);
+        assert_eq!(errors[0].found, Token::Number(10));
+        assert_eq!(errors[0].span.start.column, 10);
+    }
+
+    #[test]
+    fn unterminated_block() {
+        let errors = parse_errors("define main to be {\n    print 1;\n");
+        assert_eq!(errors[0].kind, ParseErrorKind::UnterminatedBlock);
+        assert_eq!(errors[0].found, Token::EOF);
+        // points at the opening brace
+        assert_eq!(errors[0].span.start.line, 1);
+        assert_eq!(errors[0].span.start.column, 19);
+        assert_eq!(
+            errors[0].to_string(),
+            "test.eye:1:19: unterminated block, expected `}` found end of input"
+        );
+    }
+
+    #[test]
+    fn bad_throw_operand() {
+        let errors = parse_errors("throw 10;");
+        assert_eq!(errors[0].kind, ParseErrorKind::BadThrowOperand);
+        assert_eq!(errors[0].found, Token::Number(10));
+    }
+
+    #[test]
+    fn reports_every_error() {
+        let source = "define a to be 1;
+            define b 2;
+            define main to be {
+                print a;
+                throw 10;
+                print ) + 1;
+                print b;
+            };
+            }
+            print a + ;
+            print main;";
+        let tokens = crate::lexer::tokenize_file(source.to_string(), "test.eye").unwrap();
+        let (ast, errors) = parser::parse_program(tokens);
+
+        let kinds: Vec<ParseErrorKind> = errors.iter().map(|error| error.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ParseErrorKind::MissingToBe,
+                ParseErrorKind::BadThrowOperand,
+                ParseErrorKind::UnexpectedToken,
+                ParseErrorKind::UnexpectedToken,
+                ParseErrorKind::UnexpectedToken,
+            ]
+        );
+        let lines: Vec<usize> = errors.iter().map(|error| error.span.start.line).collect();
+        assert_eq!(lines, vec![2, 5, 6, 9, 10]);
+
+        // the statements without errors are still there
+        if let AST::Program { program } = ast {
+            assert_eq!(program.len(), 3);
+            if let AST::Proc { body, .. } = &program[1].node {
+                let statements = body.iter().filter(|node| node.node != AST::Semicolon);
+                assert_eq!(statements.count(), 2);
+            } else {
+                panic!("Expected main to be parsed found {:?}", program[1]);
+            }
+            assert_eq!(
+                program[2].node,
+                AST::Print {
+                    value: Box::from(AST::Symbol {
+                        identifier: "main".to_string()
+                    })
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn unterminated_block_reported_once() {
+        let errors = parse_errors("define main to be {\n if true {\n print 1;\n");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ParseErrorKind::UnterminatedBlock);
+        assert_eq!(errors[0].span.start.line, 2);
+    }
 }
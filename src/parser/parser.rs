@@ -1,52 +1,148 @@
 use crate::types::ast::If;
+use crate::types::ast::Node;
 use crate::types::ast::AST;
-use crate::types::binary_operator::BinaryOperator;
+use crate::types::ast::{Arm, Pattern};
+use crate::types::binary_operator::{Associativity, BinaryOperator};
+use crate::types::error::{ParseError, ParseErrorKind};
+use crate::types::span::{Span, Spanned};
 use crate::types::symbol_store::Identifier;
 use crate::types::token::Token;
-use std::cell::Cell;
+use crate::types::unary_operator::UnaryOperator;
+use std::cell::{Cell, RefCell};
+use std::convert::TryFrom;
+
+type ParseResult<T> = Result<T, ParseError>;
+
+pub fn build_program(tokens: Vec<Spanned<Token>>) -> Result<AST, Vec<ParseError>> {
+    let (program, errors) = parse_program(tokens);
+    if errors.is_empty() {
+        Ok(program)
+    } else {
+        Err(errors)
+    }
+}
+
+/**
+ * Parse as much of the program as possible,
+ * statements with syntax errors are left out of the returned program
+ */
+pub fn parse_program(mut tokens: Vec<Spanned<Token>>) -> (AST, Vec<ParseError>) {
+    // mark the end of input so the parser never has to look past the last token
+    let end = match tokens.last() {
+        Some(last) => Span::new(last.span.file.clone(), last.span.end, last.span.end),
+        None => Span::default(),
+    };
+    tokens.push(Spanned::new(Token::EOF, end));
 
-pub fn build_program(tokens: Vec<Token>) -> AST {
-    let mut prog: Vec<Box<AST>> = vec![];
+    let mut prog: Vec<Node> = vec![];
     let parse_state = ParseState {
-        tokens: tokens,
+        tokens,
         curr_index: Cell::from(0),
+        errors: RefCell::from(vec![]),
     };
 
     while parse_state.has_next() {
-        prog.push(Box::from(parse_state.parse_atom()));
+        let start = parse_state.curr_index.get();
+        match parse_state.parse_atom() {
+            Ok(atom) => prog.push(atom),
+            Err(error) => parse_state.recover(error, start, false),
+        }
         parse_state.skip(&Token::Semicolon);
     }
 
-    let prog = AST::Program { program: prog };
-
-    return prog;
+    (
+        AST::Program { program: prog },
+        parse_state.errors.into_inner(),
+    )
 }
 
 struct ParseState {
-    tokens: Vec<Token>,
+    tokens: Vec<Spanned<Token>>,
     curr_index: Cell<usize>,
+    errors: RefCell<Vec<ParseError>>,
 }
 
 impl ParseState {
     fn next(&self) -> &Token {
-        self.curr_index.set(self.curr_index.get() + 1);
+        if self.has_next() {
+            self.curr_index.set(self.curr_index.get() + 1);
+        }
         self.current()
     }
 
     fn has_next(&self) -> bool {
-        self.curr_index.get() + 1 < self.tokens.len()
+        *self.current() != Token::EOF
     }
 
     fn current(&self) -> &Token {
-        &self.tokens[self.curr_index.get()]
+        &self.tokens[self.curr_index.get()].node
     }
 
-    fn is_tok(&self, token: &Token) -> bool {
-        if self.has_next() {
-            return self.current() == token;
+    fn current_span(&self) -> &Span {
+        &self.tokens[self.curr_index.get()].span
+    }
+
+    /// Span of the last token that was consumed
+    fn previous_span(&self) -> &Span {
+        let index = self.curr_index.get();
+        if index == 0 {
+            return self.current_span();
         }
+        &self.tokens[index - 1].span
+    }
 
-        false
+    /// Wrap `ast` in a node spanning from `start` up to the last consumed token
+    fn spanned(&self, ast: AST, start: &Span) -> Node {
+        Node::new(ast, start.to(self.previous_span()))
+    }
+
+    /// Error pointing at the current token
+    fn error(&self, kind: ParseErrorKind, expected: &str) -> ParseError {
+        ParseError {
+            kind,
+            expected: expected.to_string(),
+            found: self.current().clone(),
+            span: self.current_span().clone(),
+        }
+    }
+
+    /**
+     * Record `error` then skip ahead to a point where parsing can resume:
+     * just past a `;`, before a top level `define`, or at the `}` closing the current block
+     */
+    fn recover(&self, error: ParseError, start: usize, in_block: bool) {
+        self.errors.borrow_mut().push(error);
+
+        let mut depth = 0;
+        while self.has_next() {
+            match self.current() {
+                Token::LBrace => depth += 1,
+                Token::RBrace if depth == 0 => {
+                    // a stray '}' at the top level can't close anything, so drop it
+                    if !in_block {
+                        self.next();
+                    }
+                    break;
+                }
+                Token::RBrace => depth -= 1,
+                Token::Semicolon if depth == 0 => {
+                    self.next();
+                    break;
+                }
+                Token::Define if depth == 0 && !in_block => break,
+                _ => (),
+            }
+            self.next();
+        }
+
+        // always make progress so the same error isn't reported forever
+        if self.curr_index.get() == start && !(in_block && self.is_tok(&Token::RBrace)) {
+            self.next();
+        }
+    }
+
+    fn is_tok(&self, token: &Token) -> bool {
+        self.current() == token
     }
 
     fn skip(&self, token: &Token) {
@@ -55,189 +151,542 @@ impl ParseState {
         }
     }
 
+    /// Skip `token` or fail with an unexpected token error
+    fn expect(&self, token: &Token, expected: &str) -> ParseResult<()> {
+        if self.is_tok(token) {
+            self.next();
+            Ok(())
+        } else {
+            Err(self.error(ParseErrorKind::UnexpectedToken, expected))
+        }
+    }
+
     fn is_op(&self) -> bool {
-        match self.current() {
+        matches!(
+            self.current(),
             Token::Operator(BinaryOperator::Add)
-            | Token::Operator(BinaryOperator::Subtract)
-            | Token::Operator(BinaryOperator::Multiply)
-            | Token::Operator(BinaryOperator::Divide)
-            | Token::Operator(BinaryOperator::Assign)
-            | Token::Operator(BinaryOperator::IsEq) => true,
-            _ => false,
-        }
+                | Token::Operator(BinaryOperator::Subtract)
+                | Token::Operator(BinaryOperator::Multiply)
+                | Token::Operator(BinaryOperator::Divide)
+                | Token::Operator(BinaryOperator::Modulo)
+                | Token::Operator(BinaryOperator::Assign)
+                | Token::Operator(BinaryOperator::IsEq)
+                | Token::Operator(BinaryOperator::IsNEq)
+                | Token::Operator(BinaryOperator::Less)
+                | Token::Operator(BinaryOperator::LessEq)
+                | Token::Operator(BinaryOperator::Greater)
+                | Token::Operator(BinaryOperator::GreaterEq)
+                | Token::Operator(BinaryOperator::And)
+                | Token::Operator(BinaryOperator::Or)
+                | Token::Operator(BinaryOperator::Range)
+                | Token::Operator(BinaryOperator::Has)
+        )
     }
 
-    fn maybe_binary(&self, left: AST, precedence: u8) -> AST {
+    fn maybe_binary(&self, left: Node, precedence: u8) -> ParseResult<Node> {
         if self.is_op() {
             if let Token::Operator(operator_token) = self.current() {
                 let new_precedence = operator_token.get_precedence();
                 if new_precedence > precedence {
                     self.next();
-                    let right = self.maybe_binary(self.parse_atom(), new_precedence);
+                    if matches!(self.current(), Token::Semicolon | Token::EOF) {
+                        return Err(self.error(ParseErrorKind::UnexpectedToken, "expression"));
+                    }
+                    let associativity = operator_token.get_associativity();
+                    // the right side of a right associative operator takes the rest of the chain
+                    let right_precedence = match associativity {
+                        Associativity::Right => new_precedence - 1,
+                        _ => new_precedence,
+                    };
+                    let right = self.maybe_binary(self.parse_primary()?, right_precedence)?;
+
+                    if associativity == Associativity::NonAssociative {
+                        if let Token::Operator(next) = self.current() {
+                            if next.get_precedence() == new_precedence {
+                                return Err(self.error(
+                                    ParseErrorKind::ChainedComparison,
+                                    "`and` or `or` between comparisons",
+                                ));
+                            }
+                        }
+                    }
+
+                    let span = left.span.to(&right.span);
                     return self.maybe_binary(
-                        AST::Binary {
-                            operator: *operator_token,
-                            left: Box::from(left),
-                            right: Box::from(right),
-                        },
+                        Node::new(
+                            AST::Binary {
+                                operator: *operator_token,
+                                left: Box::from(left),
+                                right: Box::from(right),
+                            },
+                            span,
+                        ),
                         precedence,
                     );
                 }
             } else {
-                panic!("Could not get operator.")
+                return Err(self.error(ParseErrorKind::UnexpectedToken, "operator"));
             }
         }
-        return left;
+        Ok(left)
+    }
+
+    /// Parse a statement or an expression along with any binary operators after it
+    fn parse_atom(&self) -> ParseResult<Node> {
+        let atom = self.parse_primary()?;
+        self.maybe_binary(atom, 0)
     }
 
-    fn parse_atom(&self) -> AST {
+    /// Parse a single statement or operand, leaving any operator after it
+    fn parse_primary(&self) -> ParseResult<Node> {
         if !self.has_next() {
-            return AST::EOF;
+            return Ok(Node::new(AST::EOF, self.current_span().clone()));
         }
 
-        return self.maybe_binary(
-            match self.current() {
-                // Token::Define => self.parse_proc(),
-                Token::Return => {
+        let start = self.current_span().clone();
+        let atom = match self.current() {
+            Token::Return => {
+                self.next();
+                AST::Return {
+                    value: Box::from(self.parse_atom()?),
+                }
+            }
+            Token::Number(val) => {
+                self.next();
+                AST::Number { value: *val }
+            }
+            Token::BigNumber(val) => {
+                self.next();
+                AST::BigNumber { value: val.clone() }
+            }
+            Token::Float(val) => {
+                self.next();
+                AST::Float { value: *val }
+            }
+            Token::Bool(val) => {
+                self.next();
+                AST::Bool { value: *val }
+            }
+            Token::Str(value) => {
+                self.next();
+                AST::Str {
+                    value: value.to_string(),
+                }
+            }
+            Token::Semicolon => {
+                self.next();
+                return Ok(self.spanned(AST::Semicolon, &start));
+            }
+            Token::Symbol(symbol) => {
+                self.next();
+                let atom = if self.is_tok(&Token::LParen) {
                     self.next();
-                    AST::Return {
-                        value: Box::from(self.parse_atom()),
+                    self.parse_call(symbol.to_string())?
+                } else {
+                    AST::Symbol {
+                        identifier: symbol.to_string(),
                     }
+                };
+                return self.maybe_index(self.spanned(atom, &start));
+            }
+            Token::LBracket => {
+                self.next();
+                let collection = self.parse_collection()?;
+                return self.maybe_index(self.spanned(collection, &start));
+            }
+            Token::Remove => {
+                self.next();
+                let key = self.parse_atom()?;
+                self.expect(&Token::From, "`from`")?;
+                AST::Remove {
+                    key: Box::from(key),
+                    map: Box::from(self.parse_atom()?),
                 }
-                Token::Number(val) => {
-                    self.next();
-                    AST::Number { value: *val }
+            }
+            Token::Append => {
+                self.next();
+                // the value stops at `to`, ie `append 1 + 2 to xs`
+                let value = self.parse_primary()?;
+                let value = self.maybe_binary(value, BinaryOperator::Range.get_precedence())?;
+                self.expect(&Token::Operator(BinaryOperator::Range), "`to`")?;
+                AST::Append {
+                    value: Box::from(value),
+                    list: Box::from(self.parse_atom()?),
                 }
-                Token::Bool(val) => {
-                    self.next();
-                    AST::Bool { value: *val }
+            }
+            Token::Print => {
+                self.next();
+                AST::Print {
+                    value: Box::from(self.parse_atom()?),
                 }
-                Token::Str(value) => {
-                    self.next();
-                    AST::Str {
-                        value: value.to_string(),
-                    }
+            }
+            Token::Define => {
+                self.next();
+                self.parse_define()?
+            }
+            Token::If => {
+                self.next();
+                self.parse_if()?
+            }
+            Token::Do => {
+                self.next();
+                self.parse_do()?
+            }
+            Token::Run => {
+                self.next();
+                self.parse_run()?
+            }
+            Token::ForEach => {
+                self.next();
+                self.parse_for_each()?
+            }
+            Token::Match => {
+                self.next();
+                self.parse_match()?
+            }
+            Token::While | Token::Until => {
+                let until = self.is_tok(&Token::Until);
+                self.next();
+                AST::While {
+                    conditional: Box::from(self.parse_atom()?),
+                    body: self.parse_proc_body()?,
+                    until,
                 }
-                Token::Semicolon => {
-                    self.next();
-                    return AST::Semicolon;
+            }
+            Token::Break => {
+                self.next();
+                AST::Break
+            }
+            Token::Continue => {
+                self.next();
+                AST::Continue
+            }
+            Token::Not | Token::LengthOf | Token::PopFrom => {
+                let operator = match self.current() {
+                    Token::Not => UnaryOperator::Not,
+                    Token::LengthOf => UnaryOperator::Length,
+                    _ => UnaryOperator::Pop,
+                };
+                self.next();
+                let operand = self.parse_primary()?;
+                AST::Unary {
+                    operator,
+                    value: Box::from(self.maybe_binary(operand, operator.get_precedence())?),
                 }
-                Token::Symbol(symbol) => {
+            }
+            Token::Throw => {
+                self.next();
+                if let Token::Str(message) = self.current() {
                     self.next();
-                    if self.is_tok(&Token::LParen) {
-                        self.next();
-                        return self.maybe_binary(self.parse_call(symbol.to_string()), 0);
-                    } else {
-                        AST::Symbol {
-                            identifier: symbol.to_string(),
-                        }
+                    AST::Throw {
+                        message: message.to_string(),
                     }
+                } else {
+                    return Err(self.error(ParseErrorKind::BadThrowOperand, "string message"));
                 }
-                Token::Print => {
+            }
+            Token::LParen => {
+                self.next();
+                let inner = self.parse_primary()?;
+                let inner = self.maybe_binary(inner, 0)?;
+                self.expect(&Token::RParen, "`)`")?;
+                // the parentheses are part of the expression's span
+                return self.maybe_index(self.spanned(inner.node, &start));
+            }
+            // negative literals are read as one number, anything else is negated when it runs
+            Token::Operator(BinaryOperator::Subtract) => {
+                // skip operator
+                self.next();
+
+                if let Token::Number(num) = self.current() {
                     self.next();
-                    AST::Print {
-                        value: Box::from(self.parse_atom()),
-                    }
+                    return Ok(self.spanned(AST::Number { value: -num }, &start));
                 }
-                Token::Define => {
+                if let Token::BigNumber(num) = self.current() {
                     self.next();
-                    self.parse_define()
-                    // self.parse_set()
+                    // -2147483648 still fits in a number
+                    let value = -num.clone();
+                    let atom = match i32::try_from(&value) {
+                        Ok(value) => AST::Number { value },
+                        Err(_) => AST::BigNumber { value },
+                    };
+                    return Ok(self.spanned(atom, &start));
                 }
-                Token::If => {
+                if let Token::Float(num) = self.current() {
                     self.next();
-                    self.parse_if()
+                    return Ok(self.spanned(AST::Float { value: -num }, &start));
                 }
-                Token::Do => {
-                    self.next();
-                    self.parse_do()
+
+                let operator = UnaryOperator::Negate;
+                let operand = self.parse_primary()?;
+                AST::Unary {
+                    operator,
+                    value: Box::from(self.maybe_binary(operand, operator.get_precedence())?),
                 }
-                Token::Run => {
-                    self.next();
-                    self.parse_run()
+            }
+            _ => return Err(self.error(ParseErrorKind::UnexpectedToken, "expression")),
+        };
+
+        Ok(self.spanned(atom, &start))
+    }
+
+    /// A list or map literal after its `[`, a `:` after the first item makes it a map
+    fn parse_collection(&self) -> ParseResult<AST> {
+        if self.is_tok(&Token::Colon) {
+            self.next();
+            self.expect(&Token::RBracket, "`]`")?;
+            return Ok(AST::Map { entries: vec![] });
+        }
+
+        let mut items = vec![];
+        let mut entries = vec![];
+        while !self.is_tok(&Token::RBracket) {
+            if !self.has_next() {
+                return Err(self.error(ParseErrorKind::UnexpectedToken, "`]`"));
+            }
+            let item = self.parse_atom()?;
+            let is_map = !entries.is_empty() || (items.is_empty() && self.is_tok(&Token::Colon));
+            if is_map {
+                self.expect(&Token::Colon, "`:`")?;
+                entries.push((item, self.parse_atom()?));
+            } else {
+                items.push(item);
+            }
+            if !self.is_tok(&Token::RBracket) {
+                self.expect(&Token::Comma, "`,` or `]`")?;
+            }
+        }
+        self.next();
+
+        if entries.is_empty() {
+            Ok(AST::List { items })
+        } else {
+            Ok(AST::Map { entries })
+        }
+    }
+
+    /// Indexes and fields after a value, ie `grid[y][x]` or `line.start.x`
+    fn maybe_index(&self, collection: Node) -> ParseResult<Node> {
+        let start = collection.span.clone();
+        let node = match self.current() {
+            Token::LBracket => {
+                self.next();
+                let index = self.parse_atom()?;
+                self.expect(&Token::RBracket, "`]`")?;
+                AST::Index {
+                    collection: Box::from(collection),
+                    index: Box::from(index),
                 }
-                Token::Throw => {
-                    self.next();
-                    if let Token::Str(message) = self.current() {
-                        self.next();
-                        AST::Throw {
-                            message: message.to_string(),
-                        }
-                    } else {
-                        panic!("Can't find message to throw!");
+            }
+            Token::Dot => {
+                self.next();
+                AST::Field {
+                    record: Box::from(collection),
+                    field: self.parse_name("field name")?,
+                }
+            }
+            _ => return Ok(collection),
+        };
+        self.maybe_index(self.spanned(node, &start))
+    }
+
+    /// The fields of a record declaration, ie `{ x: number, y: number }`
+    fn parse_record(&self, symbol: &str) -> ParseResult<AST> {
+        self.expect(&Token::LBrace, "`{`")?;
+        let mut fields = vec![];
+        while !self.is_tok(&Token::RBrace) {
+            let field = self.parse_name("field name")?;
+            self.expect(&Token::Colon, "`:`")?;
+            let type_name = self.parse_name("field type")?;
+            fields.push((field, type_name));
+            if !self.is_tok(&Token::RBrace) {
+                self.expect(&Token::Comma, "`,` or `}`")?;
+            }
+        }
+        self.next();
+        Ok(AST::Record {
+            identifier: symbol.to_string(),
+            fields,
+        })
+    }
+
+    /// The variants of an enum declaration, ie `Circle(r) or Square(s) or Empty`
+    fn parse_enum(&self, symbol: &str) -> ParseResult<AST> {
+        let mut variants = vec![];
+        loop {
+            // patterns tell variants from bindings by the capital letter
+            let variant = match self.current() {
+                Token::Symbol(name) if starts_uppercase(name) => self.parse_name("variant name")?,
+                _ => {
+                    return Err(self.error(
+                        ParseErrorKind::UnexpectedToken,
+                        "variant name starting with a capital letter",
+                    ))
+                }
+            };
+            let mut fields = vec![];
+            if self.is_tok(&Token::LParen) {
+                self.next();
+                while !self.is_tok(&Token::RParen) {
+                    fields.push(self.parse_name("field name")?);
+                    if !self.is_tok(&Token::RParen) {
+                        self.expect(&Token::Comma, "`,` or `)`")?;
                     }
                 }
-                // handle negative numbers
-                Token::Operator(operator) => {
-                    if *operator == BinaryOperator::Subtract {
-                        // skip operator
-                        self.next();
+                self.next();
+            }
+            variants.push((variant, fields));
 
-                        if let Token::Number(num) = self.current() {
-                            return AST::Number { value: -num };
+            if !self.is_tok(&Token::Operator(BinaryOperator::Or)) {
+                break;
+            }
+            self.next();
+        }
+        Ok(AST::Enum {
+            identifier: symbol.to_string(),
+            variants,
+        })
+    }
+
+    fn parse_match(&self) -> ParseResult<AST> {
+        let value = self.parse_atom()?;
+        let open = self.current_span().clone();
+        self.expect(&Token::LBrace, "`{`")?;
+        let mut arms = vec![];
+        while !self.is_tok(&Token::RBrace) {
+            if !self.has_next() {
+                return Err(ParseError {
+                    span: open,
+                    ..self.error(ParseErrorKind::UnterminatedBlock, "`}`")
+                });
+            }
+            let start = self.current_span().clone();
+            let pattern = self.parse_pattern()?;
+            let span = start.to(self.previous_span());
+            let bindings = pattern.bindings();
+            for (i, name) in bindings.iter().enumerate() {
+                if bindings[..i].contains(name) {
+                    return Err(ParseError {
+                        span,
+                        ..self.error(
+                            ParseErrorKind::DuplicateBinding,
+                            &format!("`{}` to be bound only once in a pattern", name),
+                        )
+                    });
+                }
+            }
+            arms.push(Arm {
+                pattern: Spanned::new(pattern, span),
+                body: self.parse_proc_body()?,
+            });
+        }
+        self.next();
+        Ok(AST::Match {
+            value: Box::from(value),
+            arms,
+        })
+    }
+
+    fn parse_pattern(&self) -> ParseResult<Pattern> {
+        match self.current() {
+            Token::Symbol(name) if name == "_" => {
+                self.next();
+                Ok(Pattern::Wildcard)
+            }
+            Token::Symbol(name) if starts_uppercase(name) => {
+                let identifier = self.parse_name("pattern")?;
+                let mut fields = vec![];
+                if self.is_tok(&Token::LParen) {
+                    self.next();
+                    while !self.is_tok(&Token::RParen) {
+                        fields.push(self.parse_pattern()?);
+                        if !self.is_tok(&Token::RParen) {
+                            self.expect(&Token::Comma, "`,` or `)`")?;
                         }
                     }
-
-                    panic!("Can't apply operator {}", operator.to_string());
+                    self.next();
                 }
-                _ => panic!(
-                    "parser::parse_atom unimplemented for {}",
-                    self.current().to_string()
-                ),
-            },
-            0,
-        );
+                Ok(Pattern::Variant { identifier, fields })
+            }
+            Token::Symbol(_) => Ok(Pattern::Binding(self.parse_name("pattern")?)),
+            Token::Number(_)
+            | Token::BigNumber(_)
+            | Token::Float(_)
+            | Token::Str(_)
+            | Token::Bool(_)
+            | Token::Operator(BinaryOperator::Subtract) => {
+                let literal = self.parse_primary()?;
+                match literal.node {
+                    AST::Number { .. }
+                    | AST::BigNumber { .. }
+                    | AST::Float { .. }
+                    | AST::Str { .. }
+                    | AST::Bool { .. } => Ok(Pattern::Literal(Box::from(literal))),
+                    _ => Err(ParseError {
+                        span: literal.span,
+                        ..self.error(ParseErrorKind::UnexpectedToken, "pattern")
+                    }),
+                }
+            }
+            _ => Err(self.error(ParseErrorKind::UnexpectedToken, "pattern")),
+        }
     }
 
-    fn parse_run(&self) -> AST {
+    fn parse_run(&self) -> ParseResult<AST> {
         if let Token::Symbol(symbol) = self.current() {
             // skip symbol
             self.next();
             // skip given token
-            let mut args: Vec<Box<AST>> = vec![];
+            let mut args: Vec<Node> = vec![];
             if self.is_tok(&Token::Given) {
                 self.next();
                 self.skip(&Token::LParen);
-                args = self.parse_call_args();
+                args = self.parse_call_args()?;
             }
-            AST::Call {
+            Ok(AST::Call {
                 identifier: symbol.to_string(),
-                args: args,
-            }
+                args,
+            })
         } else {
-            panic!(
-                "Could not find function name to call found: {:?}",
-                self.current()
-            );
+            Err(self.error(ParseErrorKind::UnexpectedToken, "function name to run"))
         }
     }
 
-    fn parse_define(&self) -> AST {
+    fn parse_define(&self) -> ParseResult<AST> {
         if let Token::Symbol(symbol) = self.current() {
             // skip past symbol
             self.next();
 
-            // assert the next token is "to be"
-            assert!(self.is_tok(&Token::ToBe));
+            // the next token has to be "to be"
+            if !self.is_tok(&Token::ToBe) {
+                return Err(self.error(ParseErrorKind::MissingToBe, "`to be`"));
+            }
             self.next();
 
             match self.current() {
                 // fn if next char is {
                 Token::LBrace => self.parse_proc(symbol),
+                Token::Record => {
+                    self.next();
+                    self.parse_record(symbol)
+                }
+                Token::OneOf => {
+                    self.next();
+                    self.parse_enum(symbol)
+                }
 
                 // othersie it's a var
                 _ => self.parse_set(symbol),
             }
         } else {
-            panic!("Could not get symbol for define found {:?}", self.current());
+            Err(self.error(ParseErrorKind::UnexpectedToken, "name to define"))
         }
     }
 
-    fn parse_do(&self) -> AST {
-        let body = self.parse_proc_body();
-        let count = self.parse_atom();
-        assert_eq!(self.current(), &Token::Times);
-        self.skip(&Token::Times);
+    fn parse_do(&self) -> ParseResult<AST> {
+        let body = self.parse_proc_body()?;
+        let count = self.parse_atom()?;
+        self.expect(&Token::Times, "`times`")?;
         let mut identifier_value: Option<Identifier> = None;
 
         // skip given tag
@@ -246,131 +695,167 @@ impl ParseState {
             self.next();
             identifier_value = Some(identifier.to_string());
         }
-        AST::Do {
+        Ok(AST::Do {
             count: Box::from(count),
             identifier: identifier_value,
-            body: body,
+            body,
+        })
+    }
+
+    fn parse_for_each(&self) -> ParseResult<AST> {
+        let identifier = self.parse_name("name for each item")?;
+        self.expect(&Token::In, "`in`")?;
+        let collection = self.parse_atom()?;
+        let mut index = None;
+        if self.is_tok(&Token::Given) {
+            self.next();
+            index = Some(self.parse_name("name for the index")?);
         }
+        Ok(AST::ForEach {
+            identifier,
+            index,
+            collection: Box::from(collection),
+            body: self.parse_proc_body()?,
+        })
     }
 
-    fn parse_if(&self) -> AST {
-        AST::If {
+    fn parse_name(&self, expected: &str) -> ParseResult<Identifier> {
+        if let Token::Symbol(identifier) = self.current() {
+            self.next();
+            Ok(identifier.to_string())
+        } else {
+            Err(self.error(ParseErrorKind::UnexpectedToken, expected))
+        }
+    }
+
+    fn parse_if(&self) -> ParseResult<AST> {
+        Ok(AST::If {
             this: If {
-                conditional: Box::from(self.parse_atom()),
-                body: self.parse_proc_body(),
+                conditional: Box::from(self.parse_atom()?),
+                body: self.parse_proc_body()?,
             },
-            elifs: self.parse_elif(),
-            el: self.parse_el(),
-        }
+            elifs: self.parse_elif()?,
+            el: self.parse_el()?,
+        })
     }
 
-    fn parse_elif(&self) -> Option<Vec<If>> {
+    fn parse_elif(&self) -> ParseResult<Option<Vec<If>>> {
         let mut elifs: Vec<If> = vec![];
         if self.is_tok(&Token::Else) {
             while self.is_tok(&Token::Else) {
                 self.skip(&Token::Else);
                 // else after if-else
                 if self.is_tok(&Token::LBrace) {
-                    return Some(elifs);
+                    return Ok(Some(elifs));
                 }
-                assert_eq!(*self.current(), Token::If);
-                self.next();
+                self.expect(&Token::If, "`if` or `{`")?;
                 elifs.push(If {
-                    conditional: Box::from(self.parse_atom()),
-                    body: self.parse_proc_body(),
+                    conditional: Box::from(self.parse_atom()?),
+                    body: self.parse_proc_body()?,
                 })
             }
         } else {
-            return None;
+            return Ok(None);
         }
 
-        Some(elifs)
+        Ok(Some(elifs))
     }
 
-    fn parse_el(&self) -> Option<Vec<Box<AST>>> {
+    fn parse_el(&self) -> ParseResult<Option<Vec<Node>>> {
         if self.is_tok(&Token::LBrace) {
-            Some(self.parse_proc_body())
+            Ok(Some(self.parse_proc_body()?))
         } else {
-            None
+            Ok(None)
         }
     }
 
-    fn parse_set(&self, symbol: &String) -> AST {
-        AST::Assign {
+    fn parse_set(&self, symbol: &str) -> ParseResult<AST> {
+        Ok(AST::Assign {
             identifier: symbol.to_string(),
-            value: Box::from(self.parse_atom()),
-        }
+            value: Box::from(self.parse_atom()?),
+        })
     }
 
-    fn parse_call(&self, symbol: String) -> AST {
+    fn parse_call(&self, symbol: String) -> ParseResult<AST> {
         self.skip(&Token::LParen);
-        AST::Call {
+        Ok(AST::Call {
             identifier: symbol,
-            args: self.parse_call_args(),
-        }
+            args: self.parse_call_args()?,
+        })
     }
 
-    fn parse_call_args(&self) -> Vec<Box<AST>> {
-        let mut asts: Vec<Box<AST>> = vec![];
+    fn parse_call_args(&self) -> ParseResult<Vec<Node>> {
+        let mut asts: Vec<Node> = vec![];
 
         while !self.is_tok(&Token::RParen) {
-            asts.push(Box::from(self.parse_atom()));
+            if !self.has_next() {
+                return Err(self.error(ParseErrorKind::UnexpectedToken, "`)`"));
+            }
+            asts.push(self.parse_atom()?);
             self.skip(&Token::Comma);
         }
 
         self.skip(&Token::RParen);
 
-        return asts;
+        Ok(asts)
     }
 
-    fn parse_func_args(&self) -> Vec<String> {
+    fn parse_func_args(&self) -> ParseResult<Vec<String>> {
         let mut tokens = vec![];
         // args after given keyword
         if self.is_tok(&Token::Given) {
             // skip given
             self.next();
+            let parenthesized = self.is_tok(&Token::LParen);
             self.skip(&Token::LParen);
-            loop {
-                if let Token::Symbol(symbol) = self.current() {
-                    self.next();
-                    tokens.push(symbol.to_string());
-                    self.skip(&Token::Comma);
-                } else {
-                    break;
-                }
+            while let Token::Symbol(symbol) = self.current() {
+                self.next();
+                tokens.push(symbol.to_string());
+                self.skip(&Token::Comma);
+            }
+            if parenthesized {
+                self.expect(&Token::RParen, "`)`")?;
             }
-            self.skip(&Token::RParen);
         }
-        return tokens;
+        Ok(tokens)
     }
 
-    fn parse_proc(&self, symbol: &String) -> AST {
-        AST::Proc {
+    fn parse_proc(&self, symbol: &str) -> ParseResult<AST> {
+        Ok(AST::Proc {
             identifier: symbol.to_string(),
-            body: self.parse_proc_body(),
-            args: self.parse_func_args(),
-        }
+            body: self.parse_proc_body()?,
+            args: self.parse_func_args()?,
+        })
     }
 
-    fn parse_proc_body(&self) -> Vec<Box<AST>> {
-        let mut proc_body: Vec<Box<AST>> = vec![];
-
-        if *self.current() == Token::LBrace {
-            self.next();
-            while *self.current() != Token::RBrace {
-                proc_body.push(Box::from(self.parse_atom()));
+    fn parse_proc_body(&self) -> ParseResult<Vec<Node>> {
+        let mut proc_body: Vec<Node> = vec![];
+
+        let open = self.current_span().clone();
+        self.expect(&Token::LBrace, "`{`")?;
+        while !self.is_tok(&Token::RBrace) {
+            if !self.has_next() {
+                return Err(ParseError {
+                    span: open,
+                    ..self.error(ParseErrorKind::UnterminatedBlock, "`}`")
+                });
+            }
+            let start = self.curr_index.get();
+            match self.parse_atom() {
+                Ok(atom) => proc_body.push(atom),
+                // every enclosing block is unterminated too, only report it once
+                Err(error) if error.kind == ParseErrorKind::UnterminatedBlock => return Err(error),
+                Err(error) => self.recover(error, start, true),
             }
-            // skip past '}'
-            self.skip(&Token::RBrace);
-        } else {
-            panic!(
-                "Expecting {{ found {:?} {:?} {:?}",
-                self.current(),
-                self.next(),
-                self.next()
-            );
         }
+        // skip past '}'
+        self.skip(&Token::RBrace);
 
-        return proc_body;
+        Ok(proc_body)
     }
 }
+
+/// Variants are capitalized, so a capitalized name in a pattern is never a binding
+fn starts_uppercase(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_uppercase())
+}
@@ -0,0 +1,132 @@
+#[allow(clippy::module_inception)]
+mod repl;
+pub use self::repl::Repl;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::diagnostics::Style;
+    use crate::types::options::Options;
+    use crate::types::primitive_value::PrimitiveValue;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn repl() -> Repl {
+        Repl::new(Options::debug(), Style::Plain)
+    }
+
+    /// Feed every line, returning what the repl responded with
+    fn feed_all(repl: &mut Repl, lines: &[&str]) -> Vec<Option<String>> {
+        lines.iter().map(|line| repl.feed(line)).collect()
+    }
+
+    #[test]
+    fn echoes_expressions() {
+        let mut repl = repl();
+        let responses = feed_all(
+            &mut repl,
+            &["1 + 2", "define a to be 5;", "a * 2", "\"hi\""],
+        );
+        assert_eq!(
+            responses,
+            vec![
+                Some("3".to_string()),
+                None,
+                Some("10".to_string()),
+                Some("hi".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn multi_line_input() {
+        let mut repl = repl();
+        assert_eq!(repl.prompt(), "eye> ");
+        assert_eq!(repl.feed("define add to be {"), None);
+        assert_eq!(repl.prompt(), "...> ");
+        // braces in strings and comments don't count
+        assert_eq!(repl.feed("  print \"}\"; // }"), None);
+        assert_eq!(repl.feed("  return a + b;"), None);
+        assert_eq!(repl.feed("} given (a, b);"), None);
+        assert_eq!(repl.prompt(), "eye> ");
+        assert_eq!(repl.feed("add(1, 2)"), Some("3".to_string()));
+
+        // neither do escaped quotes end a string
+        assert_eq!(repl.feed("\"\\\"{\""), Some("\\\"{".to_string()));
+        assert_eq!(repl.prompt(), "eye> ");
+    }
+
+    #[test]
+    fn symbols_persist_on_both_backends() {
+        for options in Options::every_backend() {
+            let printed = Rc::new(RefCell::new(vec![]));
+            let sink = printed.clone();
+            let options = options.with_print(move |value| sink.borrow_mut().push(value));
+            let mut repl = Repl::new(options, Style::Plain);
+
+            feed_all(&mut repl, &["define a to be 1;", "a = a + 1;", "print a;"]);
+            assert_eq!(*printed.borrow(), vec![PrimitiveValue::Num(2)]);
+        }
+    }
+
+    #[test]
+    fn errors_are_rendered() {
+        let mut repl = repl();
+        let error = repl.feed("print missing;").unwrap();
+        assert_eq!(
+            error,
+            "error[E0200]: Tried to access undefined symbol: missing
+ --> <repl>:1:7
+  |
+1 | print missing;
+  |       ^^^^^^^"
+        );
+        // a failed input doesn't stick around
+        assert_eq!(repl.feed("1"), Some("1".to_string()));
+    }
+
+    #[test]
+    fn commands() {
+        let mut repl = repl();
+        assert_eq!(
+            repl.feed(":tokens print 1;"),
+            Some("(Print, ) (Number, 1) (;, )".to_string())
+        );
+        assert_eq!(
+            repl.feed(":ast print 1;"),
+            Some("Print Number(1)".to_string())
+        );
+        assert_eq!(repl.feed(":env"), Some("nothing defined".to_string()));
+        feed_all(&mut repl, &["define b to be 2;", "define a to be true;"]);
+        assert_eq!(repl.feed(":env"), Some("a = true\nb = 2".to_string()));
+        assert_eq!(repl.feed(":reset"), Some("cleared all symbols".to_string()));
+        assert_eq!(repl.feed(":env"), Some("nothing defined".to_string()));
+        assert_eq!(
+            repl.feed(":nope"),
+            Some("unknown command :nope, try :help".to_string())
+        );
+        assert!(!repl.is_done());
+        repl.feed(":quit");
+        assert!(repl.is_done());
+    }
+
+    #[test]
+    fn load_file() {
+        let mut repl = repl();
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/functions.eye");
+        assert_eq!(repl.feed(&format!(":load {}", path)), None);
+        assert_eq!(repl.feed("add(20, 22)"), Some("42".to_string()));
+    }
+
+    #[test]
+    fn run_session() {
+        let mut repl = repl();
+        let input = "define a to be {\n return 4;\n} given ();\na()\n:quit\nprint 1;\n";
+        let mut output = vec![];
+        repl.run(input.as_bytes(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let session = output.lines().skip(1).collect::<Vec<&str>>().join("\n");
+        assert_eq!(session, "eye> ...> ...> eye> 4\neye> ");
+    }
+}
@@ -0,0 +1,195 @@
+use crate::diagnostics::Style;
+use crate::engine::Engine;
+use crate::file;
+use crate::lexer::tokenize_file;
+use crate::types::ast::AST;
+use crate::types::error::Error;
+use crate::types::options::Options;
+
+use std::io::{BufRead, Write};
+
+const FILE_NAME: &str = "<repl>";
+
+const HELP: &str = ":tokens <code>  show the tokens code lexes to
+:ast <code>     show the syntax tree code parses to
+:env            list the symbols defined so far
+:load <file>    run a source file, keeping what it defines
+:reset          forget everything defined so far
+:help           show this message
+:quit           leave the repl";
+
+/**
+ * An interactive session, every input runs in the same global scope.
+ *
+ * Input is buffered until its braces balance so blocks can be typed over
+ * several lines, the value of a trailing expression is echoed back.
+ */
+pub struct Repl {
+    engine: Engine,
+    /// lines of an input that isn't finished yet
+    buffer: String,
+    style: Style,
+    done: bool,
+}
+
+impl Repl {
+    pub fn new(options: Options, style: Style) -> Repl {
+        Repl {
+            engine: Engine::with_options(options),
+            buffer: String::new(),
+            style,
+            done: false,
+        }
+    }
+
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    /// Shown before each line, changes while an input continues over several lines
+    pub fn prompt(&self) -> &'static str {
+        if self.buffer.is_empty() {
+            "eye> "
+        } else {
+            "...> "
+        }
+    }
+
+    /// Whether `:quit` was entered
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /**
+     * Handle one line of input, returns anything to show the user.
+     * Output the program prints goes to the print hook as usual.
+     */
+    pub fn feed(&mut self, line: &str) -> Option<String> {
+        if self.buffer.is_empty() && line.trim_start().starts_with(':') {
+            return self.command(line.trim());
+        }
+
+        self.buffer.push_str(line);
+        self.buffer.push('\n');
+        if brace_depth(&self.buffer) > 0 {
+            return None;
+        }
+
+        let source = std::mem::take(&mut self.buffer);
+        if source.trim().is_empty() {
+            return None;
+        }
+        match self.engine.eval_optional(&source, FILE_NAME) {
+            Ok(value) => value.map(|value| value.to_string()),
+            Err(error) => Some(error.render(&source, self.style)),
+        }
+    }
+
+    /// Read lines from `input` until it ends or `:quit` is entered
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> std::io::Result<()> {
+        writeln!(
+            output,
+            "eyelang {}, :help for commands",
+            env!("CARGO_PKG_VERSION")
+        )?;
+        let mut lines = input.lines();
+        while !self.done {
+            write!(output, "{}", self.prompt())?;
+            output.flush()?;
+
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => break,
+            };
+            if let Some(response) = self.feed(&line) {
+                writeln!(output, "{}", response)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn command(&mut self, line: &str) -> Option<String> {
+        let (command, argument) = match line.find(char::is_whitespace) {
+            Some(index) => (&line[..index], line[index..].trim()),
+            None => (line, ""),
+        };
+
+        match command {
+            ":tokens" => Some(match tokenize_file(argument.to_string(), FILE_NAME) {
+                Ok(tokens) => tokens
+                    .iter()
+                    .map(|token| format!("{:?}", token.node))
+                    .collect::<Vec<String>>()
+                    .join(" "),
+                Err(error) => Error::from(error).render(argument, self.style),
+            }),
+            ":ast" => Some(match self.engine.parse(argument, FILE_NAME) {
+                Ok(program) => program
+                    .iter()
+                    .filter(|node| !matches!(node.node, AST::Semicolon | AST::EOF))
+                    .map(|node| format!("{:?}", node))
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+                Err(error) => error.render(argument, self.style),
+            }),
+            ":env" => {
+                let bindings = self.engine.globals().bindings();
+                if bindings.is_empty() {
+                    return Some("nothing defined".to_string());
+                }
+                Some(
+                    bindings
+                        .iter()
+                        .map(|(name, value)| format!("{} = {}", name, value))
+                        .collect::<Vec<String>>()
+                        .join("\n"),
+                )
+            }
+            ":load" => match self.engine.run_file(argument) {
+                Ok(_) => None,
+                Err(error) => {
+                    let source = file::read_source_file(argument).unwrap_or_default();
+                    Some(error.render(&source, self.style))
+                }
+            },
+            ":reset" => {
+                self.engine.reset();
+                Some("cleared all symbols".to_string())
+            }
+            ":help" => Some(HELP.to_string()),
+            ":quit" | ":q" => {
+                self.done = true;
+                None
+            }
+            _ => Some(format!("unknown command {}, try :help", command)),
+        }
+    }
+}
+
+/// How many more `{` than `}` the source has, ignoring strings and comments
+fn brace_depth(source: &str) -> i32 {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => in_string = !in_string,
+            // an escaped character never ends the string, like in the lexer
+            '\\' if in_string => {
+                chars.next();
+            }
+            '/' if !in_string && chars.peek() == Some(&'/') => {
+                // skip the rest of the line
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '{' if !in_string => depth += 1,
+            '}' if !in_string => depth -= 1,
+            _ => (),
+        }
+    }
+    depth
+}
@@ -1,28 +1,61 @@
 #[cfg(test)]
 mod test {
     use crate::interpreter::interpret;
-    use crate::lexer::tokenize;
+    use crate::lexer::tokenize_file;
     use crate::parser::build_program;
-    use crate::types::{ast, options, primitive_value, symbol_store};
+    use crate::types::primitive_value::PrimitiveValue;
+    use crate::types::{ast, options, symbol_store};
+    use num_bigint::BigInt;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Run a program with `options`, returning everything it printed
+    fn run_printing(source: &str, options: options::Options) -> Vec<PrimitiveValue> {
+        let printed = Rc::new(RefCell::new(vec![]));
+        let sink = printed.clone();
+        let options = options.with_print(move |value| sink.borrow_mut().push(value));
+
+        let program = setup_program(source);
+        interpret(program, symbol_store::create_symbol_store(), &options).unwrap();
+        printed.take()
+    }
+
     #[test]
-    fn print_neg_number() {
-        let program = setup_program("print -1;");
-        let mut options = options::Options::debug();
-        let symbols = symbol_store::SymbolStore::new();
+    fn examples_agree_on_both_backends() {
+        let examples = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+        let mut paths: Vec<_> = std::fs::read_dir(examples)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "eye"))
+            .collect();
+        paths.sort();
+        assert!(!paths.is_empty());
 
-        fn print_fn(a: primitive_value::PrimitiveValue) {
-            // check the 10th fib number
-            check_print(primitive_value::PrimitiveValue::Num(-1))(a)
+        for path in paths {
+            let source = std::fs::read_to_string(&path).unwrap();
+            let printed: Vec<_> = options::Options::every_backend()
+                .into_iter()
+                .map(|options| run_printing(&source, options))
+                .collect();
+            assert!(!printed[0].is_empty(), "{} printed nothing", path.display());
+            assert_eq!(
+                printed[0],
+                printed[1],
+                "backends disagree on {}",
+                path.display()
+            );
         }
-        options.print_fn = print_fn;
+    }
 
-        interpret(program, symbols, &options);
+    #[test]
+    fn print_neg_number() {
+        let printed = run_printing("print -1;", options::Options::debug());
+        assert_eq!(printed, vec![PrimitiveValue::Num(-1)]);
     }
 
     #[test]
     fn tenth_fb() {
-        let program = setup_program(
-            "define fib to be {
+        let program = "define fib to be {
             if n is 0 {
                 return 0;
             } else if n is 1 {
@@ -32,30 +65,283 @@ mod test {
             }
         } given (n);
         
-        print run fib given (10);",
-        );
+        print run fib given (10);";
 
-        let mut options = options::Options::debug();
+        // check the 10th fib number
+        let printed = run_printing(program, options::Options::debug());
+        assert_eq!(printed, vec![PrimitiveValue::Num(55)]);
+    }
 
-        fn print_fn(a: primitive_value::PrimitiveValue) {
-            // check the 10th fib number
-            check_print(primitive_value::PrimitiveValue::Num(55))(a)
+    #[test]
+    fn print_sequence() {
+        let program = "do { print i * i; } 4 times given i;
+        print \"done\";
+        print define a to be 1;";
+        let expected = vec![
+            PrimitiveValue::Num(0),
+            PrimitiveValue::Num(1),
+            PrimitiveValue::Num(4),
+            PrimitiveValue::Num(9),
+            PrimitiveValue::Str("done".to_string()),
+            PrimitiveValue::Str("''".to_string()),
+        ];
+        for options in options::Options::every_backend() {
+            assert_eq!(run_printing(program, options), expected);
         }
-        options.print_fn = print_fn;
-        interpret(program, symbol_store::create_symbol_store(), &options);
     }
 
-    fn setup_program(s: &str) -> ast::AST {
-        let tokens = tokenize(s.to_string()).unwrap();
-        build_program(tokens)
+    /// A `Write` sink the test can still read after handing it to `Options`
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl std::io::Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn print_to_writer() {
+        let buffer = SharedBuffer::default();
+        let options = options::Options::debug().print_to(buffer.clone());
+        let program = setup_program("print 1; print \"a\"; print 1 is 1;");
+        interpret(program, symbol_store::create_symbol_store(), &options).unwrap();
+
+        let output = String::from_utf8(buffer.0.take()).unwrap();
+        assert_eq!(output, "1\na\ntrue\n");
     }
 
-    fn check_print(
-        b: primitive_value::PrimitiveValue,
-    ) -> Box<dyn Fn(primitive_value::PrimitiveValue)> {
-        Box::new(move |a: primitive_value::PrimitiveValue| {
-            (options::Options::debug().print_fn)(a.clone());
-            assert_eq!(a, b);
-        })
+    /**
+     * Writes random expressions one precedence level at a time, working out
+     * what each one should evaluate to as it goes. The text leaves out any
+     * parentheses the precedence table makes unnecessary so the parser has
+     * to group it the same way. `None` means dividing by zero.
+     */
+    struct Expressions {
+        seed: u64,
+    }
+
+    type Number = (String, Option<BigInt>);
+    type Bool = (String, Option<bool>);
+
+    impl Expressions {
+        /// xorshift, good enough to spread out the cases and the same every run
+        fn below(&mut self, n: u64) -> u64 {
+            self.seed ^= self.seed << 13;
+            self.seed ^= self.seed >> 7;
+            self.seed ^= self.seed << 17;
+            self.seed % n
+        }
+
+        fn sum(&mut self, depth: u32) -> Number {
+            let (mut text, mut value) = self.term(depth);
+            for _ in 0..self.below(3) {
+                let (right_text, right) = self.term(depth);
+                let (operator, result) = match self.below(2) {
+                    0 => ("+", value.zip(right).map(|(a, b)| a + b)),
+                    _ => ("-", value.zip(right).map(|(a, b)| a - b)),
+                };
+                text = format!("{} {} {}", text, operator, right_text);
+                value = result;
+            }
+            (text, value)
+        }
+
+        fn term(&mut self, depth: u32) -> Number {
+            let (mut text, mut value) = self.factor(depth);
+            for _ in 0..self.below(3) {
+                let (right_text, right) = self.factor(depth);
+                let (operator, result) = match (self.below(3), value.zip(right)) {
+                    (0, values) => ("*", values.map(|(a, b)| a * b)),
+                    (1, Some((a, b))) if b != BigInt::from(0) => ("/", Some(a / b)),
+                    (1, _) => ("/", None),
+                    (_, Some((a, b))) if b != BigInt::from(0) => ("%", Some(a % b)),
+                    (_, _) => ("%", None),
+                };
+                text = format!("{} {} {}", text, operator, right_text);
+                value = result;
+            }
+            (text, value)
+        }
+
+        fn factor(&mut self, depth: u32) -> Number {
+            match self.below(if depth == 0 { 1 } else { 4 }) {
+                0 | 1 => {
+                    let n = self.below(20);
+                    (n.to_string(), Some(BigInt::from(n)))
+                }
+                2 => {
+                    let (text, value) = self.factor(depth - 1);
+                    (format!("-{}", text), value.map(|value| -value))
+                }
+                _ => {
+                    let (text, value) = self.sum(depth - 1);
+                    (format!("({})", text), value)
+                }
+            }
+        }
+
+        fn or(&mut self, depth: u32) -> Bool {
+            let (mut text, mut value) = self.and(depth);
+            for _ in 0..self.below(3) {
+                let (right_text, right) = self.and(depth);
+                text = format!("{} or {}", text, right_text);
+                // the right side only runs when the left is false
+                value = match value {
+                    Some(true) => Some(true),
+                    Some(false) => right,
+                    None => None,
+                };
+            }
+            (text, value)
+        }
+
+        fn and(&mut self, depth: u32) -> Bool {
+            let (mut text, mut value) = self.not(depth);
+            for _ in 0..self.below(3) {
+                let (right_text, right) = self.not(depth);
+                text = format!("{} and {}", text, right_text);
+                value = match value {
+                    Some(false) => Some(false),
+                    Some(true) => right,
+                    None => None,
+                };
+            }
+            (text, value)
+        }
+
+        fn not(&mut self, depth: u32) -> Bool {
+            if depth > 0 && self.below(4) == 0 {
+                let (text, value) = self.not(depth - 1);
+                return (format!("not {}", text), value.map(|value| !value));
+            }
+            self.equality(depth)
+        }
+
+        fn equality(&mut self, depth: u32) -> Bool {
+            let operator = ["is", "is not", "!="][self.below(3) as usize];
+            let negated = operator != "is";
+            match self.below(3) {
+                0 => {
+                    let (left_text, left) = self.sum(depth);
+                    let (right_text, right) = self.sum(depth);
+                    let value = left.zip(right).map(|(a, b)| (a == b) != negated);
+                    (format!("{} {} {}", left_text, operator, right_text), value)
+                }
+                1 => {
+                    let (left_text, left) = self.comparison(depth);
+                    let (right_text, right) = self.comparison(depth);
+                    let value = left.zip(right).map(|(a, b)| (a == b) != negated);
+                    (format!("{} {} {}", left_text, operator, right_text), value)
+                }
+                _ => self.comparison(depth),
+            }
+        }
+
+        fn comparison(&mut self, depth: u32) -> Bool {
+            match self.below(if depth == 0 { 2 } else { 4 }) {
+                0 => {
+                    let value = self.below(2) == 0;
+                    (value.to_string(), Some(value))
+                }
+                1 | 2 => {
+                    let (left_text, left) = self.sum(depth);
+                    let (right_text, right) = self.sum(depth);
+                    let (operator, compare): (&str, fn(&BigInt, &BigInt) -> bool) =
+                        match self.below(4) {
+                            0 => ("<", |a, b| a < b),
+                            1 => ("<=", |a, b| a <= b),
+                            2 => ("is greater than", |a, b| a > b),
+                            _ => (">=", |a, b| a >= b),
+                        };
+                    let value = left.zip(right).map(|(a, b)| compare(&a, &b));
+                    (format!("{} {} {}", left_text, operator, right_text), value)
+                }
+                _ => {
+                    let (text, value) = self.or(depth - 1);
+                    (format!("({})", text), value)
+                }
+            }
+        }
+    }
+
+    fn check_against_reference(source: &str, expected: Option<String>) {
+        for options in options::Options::every_backend() {
+            let backend = options.backend;
+            let engine = crate::Engine::with_options(options);
+            match (engine.eval(source), &expected) {
+                (Ok(value), Some(expected)) => {
+                    assert_eq!(&value.to_string(), expected, "{} on {:?}", source, backend)
+                }
+                (Err(error), None) => assert!(
+                    error.to_string().ends_with("Division by zero."),
+                    "{} on {:?} failed with {}",
+                    source,
+                    backend,
+                    error
+                ),
+                (result, _) => panic!(
+                    "{} on {:?} gave {:?} expected {:?}",
+                    source, backend, result, expected
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn arithmetic_precedence_matches_reference() {
+        let mut expressions = Expressions { seed: 0x5eed };
+        for _ in 0..200 {
+            let (source, value) = expressions.sum(3);
+            check_against_reference(&source, value.map(|value| value.to_string()));
+        }
+    }
+
+    #[test]
+    fn logic_precedence_matches_reference() {
+        let mut expressions = Expressions { seed: 0xb001 };
+        for _ in 0..200 {
+            let (source, value) = expressions.or(1);
+            check_against_reference(&source, value.map(|value| value.to_string()));
+        }
+    }
+
+    #[test]
+    fn associativity() {
+        for options in options::Options::every_backend() {
+            let engine = crate::Engine::with_options(options);
+            assert_eq!(engine.eval("100 / 10 / 5").unwrap(), PrimitiveValue::Num(2));
+            assert_eq!(engine.eval("10 - 4 - 3").unwrap(), PrimitiveValue::Num(3));
+            assert_eq!(
+                engine.eval("10 * 20 + 5 - 2").unwrap(),
+                PrimitiveValue::Num(203)
+            );
+
+            // assignment groups to the right and gives back the assigned value
+            engine.run("define a to be 0; define b to be 0;").unwrap();
+            assert_eq!(engine.eval("a = b = 3").unwrap(), PrimitiveValue::Num(3));
+            assert_eq!(engine.get("a"), Some(PrimitiveValue::Num(3)));
+            assert_eq!(engine.get("b"), Some(PrimitiveValue::Num(3)));
+        }
+
+        let error = crate::Engine::new().eval("1 < 2 < 3").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "<eval>:1:7: chained comparison, expected `and` or `or` between comparisons found `<`"
+        );
+        assert!(crate::Engine::new().eval("1 is 1 != false").is_err());
+        assert_eq!(
+            crate::Engine::new().eval("1 < 2 is true").unwrap(),
+            PrimitiveValue::Bool(true)
+        );
+    }
+
+    fn setup_program(s: &str) -> ast::AST {
+        let tokens = tokenize_file(s.to_string(), "test.eye").unwrap();
+        build_program(tokens).unwrap()
     }
 }
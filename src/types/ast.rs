@@ -1,21 +1,87 @@
 use crate::types::binary_operator::BinaryOperator;
-use crate::types::symbol_store::Identifier;
+use crate::types::primitive_value::format_float;
+use crate::types::span::Spanned;
+use crate::types::symbol_store::{Identifier, ScopeLink};
+use crate::types::unary_operator::UnaryOperator;
+use num_bigint::BigInt;
 
-pub type Block = Vec<Box<AST>>;
+/// An AST node along with the span of source it was parsed from
+pub type Node = Spanned<AST>;
+
+pub type Block = Vec<Node>;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct FunctionBody {
     pub body: Block,
     pub args: Vec<String>,
+    /// The scope the function was defined in, calls run in a child of it
+    pub scope: ScopeLink,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct If {
-    pub conditional: Box<AST>,
+    pub conditional: Box<Node>,
+    pub body: Block,
+}
+
+/// What a `match` arm checks the value against
+#[derive(Clone, Debug, PartialEq)]
+pub enum Pattern {
+    /// `_` matches anything
+    Wildcard,
+    /// a lowercase name matches anything and binds it for the arm
+    Binding(Identifier),
+    /// a number, string or bool matches values equal to it
+    Literal(Box<Node>),
+    /// `Circle(r)` matches values made by the variant or record type `Circle`,
+    /// a capitalized name without fields matches a variant that has none
+    Variant {
+        identifier: Identifier,
+        fields: Vec<Pattern>,
+    },
+}
+
+impl Pattern {
+    /// The names the pattern binds, in order
+    pub fn bindings(&self) -> Vec<Identifier> {
+        match self {
+            Pattern::Binding(identifier) => vec![identifier.to_string()],
+            Pattern::Variant { fields, .. } => fields.iter().flat_map(Pattern::bindings).collect(),
+            Pattern::Wildcard | Pattern::Literal(_) => vec![],
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Arm {
+    pub pattern: Spanned<Pattern>,
     pub body: Block,
 }
 
-#[derive(Clone, PartialEq, Eq)]
+impl Arm {
+    /**
+     * The arm's body split into the statements that run first and the
+     * expression it ends with, that's its value when the match is used as one
+     */
+    pub fn split_value(&self) -> (&[Node], Option<&Node>) {
+        let last = self
+            .body
+            .iter()
+            .rposition(|node| !matches!(node.node, AST::Semicolon | AST::EOF));
+        match last {
+            Some(last)
+                if self.body[last].node.is_expression()
+                    || matches!(self.body[last].node, AST::Match { .. }) =>
+            {
+                (&self.body[..last], Some(&self.body[last]))
+            }
+            _ => (&self.body, None),
+        }
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, PartialEq)]
 pub enum AST {
     Symbol {
         identifier: Identifier,
@@ -23,6 +89,13 @@ pub enum AST {
     Number {
         value: i32,
     },
+    /// integer literals too big for `Number`
+    BigNumber {
+        value: BigInt,
+    },
+    Float {
+        value: f64,
+    },
     Str {
         value: String,
     },
@@ -31,12 +104,16 @@ pub enum AST {
     },
     Binary {
         operator: BinaryOperator,
-        left: Box<AST>,
-        right: Box<AST>,
+        left: Box<Node>,
+        right: Box<Node>,
+    },
+    Unary {
+        operator: UnaryOperator,
+        value: Box<Node>,
     },
     Assign {
         identifier: Identifier,
-        value: Box<AST>,
+        value: Box<Node>,
     },
     Proc {
         identifier: Identifier,
@@ -48,7 +125,7 @@ pub enum AST {
         args: Block,
     },
     Return {
-        value: Box<AST>,
+        value: Box<Node>,
     },
     If {
         this: If,
@@ -56,16 +133,74 @@ pub enum AST {
         el: Option<Block>,
     },
     Print {
-        value: Box<AST>,
+        value: Box<Node>,
     },
     Program {
         program: Block,
     },
+    /// `while` runs the body as long as the condition is true, `until` as long as it's false
+    While {
+        conditional: Box<Node>,
+        body: Block,
+        until: bool,
+    },
+    Break,
+    Continue,
     Do {
-        count: Box<AST>,
+        count: Box<Node>,
         identifier: Option<Identifier>,
         body: Block,
     },
+    /// `[1, 2, 3]`
+    List {
+        items: Block,
+    },
+    /// `record { x: number, y: number }`, each field has a name and a type
+    Record {
+        identifier: Identifier,
+        fields: Vec<(Identifier, Identifier)>,
+    },
+    /// `one of Circle(r) or Square(s)`, each variant has a name and the names of its fields
+    Enum {
+        identifier: Identifier,
+        variants: Vec<(Identifier, Vec<Identifier>)>,
+    },
+    /// `match value { pattern { ... } }`, runs the first arm whose pattern fits
+    Match {
+        value: Box<Node>,
+        arms: Vec<Arm>,
+    },
+    /// `p.x`, assigning to one updates the field
+    Field {
+        record: Box<Node>,
+        field: Identifier,
+    },
+    /// `["key": value]`, `[:]` is an empty map
+    Map {
+        entries: Vec<(Node, Node)>,
+    },
+    /// `remove key from map`, gives back the key's value
+    Remove {
+        key: Box<Node>,
+        map: Box<Node>,
+    },
+    /// `xs[i]`, assigning to one sets the item
+    Index {
+        collection: Box<Node>,
+        index: Box<Node>,
+    },
+    /// `append value to list`
+    Append {
+        value: Box<Node>,
+        list: Box<Node>,
+    },
+    /// `for each item in collection given index`, items come from `interpreter::iterate`
+    ForEach {
+        identifier: Identifier,
+        index: Option<Identifier>,
+        collection: Box<Node>,
+        body: Block,
+    },
     Throw {
         message: String,
     },
@@ -73,47 +208,204 @@ pub enum AST {
     Semicolon,
 }
 
-impl std::string::ToString for AST {
-    fn to_string(&self) -> String {
+impl AST {
+    /// Whether the node is only an expression, statements that can be used as values aren't
+    pub fn is_expression(&self) -> bool {
+        matches!(
+            self,
+            AST::Number { .. }
+                | AST::BigNumber { .. }
+                | AST::Float { .. }
+                | AST::Str { .. }
+                | AST::Bool { .. }
+                | AST::Symbol { .. }
+                | AST::Binary { .. }
+                | AST::Unary { .. }
+                | AST::List { .. }
+                | AST::Map { .. }
+                | AST::Index { .. }
+                | AST::Remove { .. }
+                | AST::Field { .. }
+                | AST::Call { .. }
+        )
+    }
+}
+
+impl From<AST> for Box<Node> {
+    fn from(ast: AST) -> Box<Node> {
+        Box::new(Node::from(ast))
+    }
+}
+
+/// Call `visit` on every node of the program, including the ones inside blocks
+pub fn walk<'a>(nodes: &'a [Node], visit: &mut impl FnMut(&'a Node)) {
+    for node in nodes {
+        walk_node(node, visit);
+    }
+}
+
+fn walk_node<'a>(node: &'a Node, visit: &mut impl FnMut(&'a Node)) {
+    visit(node);
+    match &node.node {
+        AST::Binary { left, right, .. } => {
+            walk_node(left, visit);
+            walk_node(right, visit);
+        }
+        AST::Unary { value, .. }
+        | AST::Assign { value, .. }
+        | AST::Return { value }
+        | AST::Print { value } => walk_node(value, visit),
+        AST::Proc { body, .. } | AST::Program { program: body } => walk(body, visit),
+        AST::Call { args, .. } => walk(args, visit),
+        AST::If { this, elifs, el } => {
+            for branch in std::iter::once(this).chain(elifs.iter().flatten()) {
+                walk_node(&branch.conditional, visit);
+                walk(&branch.body, visit);
+            }
+            if let Some(el) = el {
+                walk(el, visit);
+            }
+        }
+        AST::Match { value, arms } => {
+            walk_node(value, visit);
+            for arm in arms {
+                walk(&arm.body, visit);
+            }
+        }
+        AST::While {
+            conditional: value,
+            body,
+            ..
+        }
+        | AST::Do {
+            count: value, body, ..
+        }
+        | AST::ForEach {
+            collection: value,
+            body,
+            ..
+        } => {
+            walk_node(value, visit);
+            walk(body, visit);
+        }
+        AST::List { items } => walk(items, visit),
+        AST::Map { entries } => {
+            for (key, value) in entries {
+                walk_node(key, visit);
+                walk_node(value, visit);
+            }
+        }
+        AST::Field { record: value, .. } => walk_node(value, visit),
+        AST::Index {
+            collection: left,
+            index: right,
+        }
+        | AST::Remove {
+            key: left,
+            map: right,
+        }
+        | AST::Append {
+            value: left,
+            list: right,
+        } => {
+            walk_node(left, visit);
+            walk_node(right, visit);
+        }
+        AST::Symbol { .. }
+        | AST::Number { .. }
+        | AST::BigNumber { .. }
+        | AST::Float { .. }
+        | AST::Str { .. }
+        | AST::Bool { .. }
+        | AST::Record { .. }
+        | AST::Enum { .. }
+        | AST::Break
+        | AST::Continue
+        | AST::Throw { .. }
+        | AST::EOF
+        | AST::Semicolon => (),
+    }
+}
+
+impl std::fmt::Display for AST {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             // literals
-            AST::Number { value } => format!("Number({})", value),
-            AST::Str { value } => format!("String({})", value),
-            AST::Bool { value } => format!("Bool({})", value),
-            AST::Symbol { identifier } => format!("Symbol ({})", identifier),
+            AST::Number { value } => write!(f, "Number({})", value),
+            AST::BigNumber { value } => write!(f, "Number({})", value),
+            AST::Float { value } => write!(f, "Float({})", format_float(*value)),
+            AST::Str { value } => write!(f, "String({})", value),
+            AST::Bool { value } => write!(f, "Bool({})", value),
+            AST::Symbol { identifier } => write!(f, "Symbol ({})", identifier),
             // ctrl characters
-            AST::Semicolon => format!(";"),
-            AST::EOF => format!("EOF"),
+            AST::Semicolon => write!(f, ";"),
+            AST::EOF => write!(f, "EOF"),
             // actions
-            AST::Assign { identifier, value } => format!("Assign {} = {:?}", identifier, value),
-            AST::Print { value } => format!("Print {:?}", value),
-            AST::Throw { message } => format!("Throw {}", message),
+            AST::Assign { identifier, value } => write!(f, "Assign {} = {:?}", identifier, value),
+            AST::Print { value } => write!(f, "Print {:?}", value),
+            AST::Throw { message } => write!(f, "Throw {}", message),
             AST::Binary {
                 operator,
                 left,
                 right,
-            } => format!("Binary {:?} {} {:?}", left, operator.to_string(), right),
+            } => write!(f, "Binary {:?} {} {:?}", left, operator, right),
+            AST::Unary { operator, value } => write!(f, "Unary {} {:?}", operator, value),
             // blocked calls
-            AST::Program { program } => format!("Program: {:?}", program),
-            AST::Call { identifier, args } => format!("Call {}({:?})", identifier, args),
-            AST::If { this, elifs, el } => format!("If {:?} {:?} {:?}", this, elifs, el),
-            AST::Return { value } => format!("Return <{:?}>", value),
+            AST::Program { program } => write!(f, "Program: {:?}", program),
+            AST::Call { identifier, args } => write!(f, "Call {}({:?})", identifier, args),
+            AST::If { this, elifs, el } => write!(f, "If {:?} {:?} {:?}", this, elifs, el),
+            AST::Return { value } => write!(f, "Return <{:?}>", value),
             AST::Proc {
                 identifier,
                 body,
                 args,
-            } => format!("Proc {} {:?}({:?})", identifier, body, args),
+            } => write!(f, "Proc {} {:?}({:?})", identifier, body, args),
+            AST::While {
+                conditional,
+                body,
+                until,
+            } => write!(
+                f,
+                "{} {:?} {:?}",
+                if *until { "Until" } else { "While" },
+                conditional,
+                body
+            ),
+            AST::Break => write!(f, "Break"),
+            AST::Continue => write!(f, "Continue"),
             AST::Do {
                 count,
                 identifier,
                 body,
-            } => format!("Do {:?}:{:?} {:?}", count, identifier, body),
+            } => write!(f, "Do {:?}:{:?} {:?}", count, identifier, body),
+            AST::List { items } => write!(f, "List {:?}", items),
+            AST::Map { entries } => write!(f, "Map {:?}", entries),
+            AST::Record { identifier, fields } => write!(f, "Record {} {:?}", identifier, fields),
+            AST::Enum {
+                identifier,
+                variants,
+            } => write!(f, "Enum {} {:?}", identifier, variants),
+            AST::Match { value, arms } => write!(f, "Match {:?} {:?}", value, arms),
+            AST::Field { record, field } => write!(f, "{:?}.{}", record, field),
+            AST::Remove { key, map } => write!(f, "Remove {:?} from {:?}", key, map),
+            AST::Index { collection, index } => write!(f, "{:?}[{:?}]", collection, index),
+            AST::Append { value, list } => write!(f, "Append {:?} to {:?}", value, list),
+            AST::ForEach {
+                identifier,
+                index,
+                collection,
+                body,
+            } => write!(
+                f,
+                "ForEach {}:{:?} in {:?} {:?}",
+                identifier, index, collection, body
+            ),
         }
     }
 }
 
 impl std::fmt::Debug for AST {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.to_string())
+        write!(f, "{}", self)
     }
 }
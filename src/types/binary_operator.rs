@@ -1,36 +1,90 @@
-#[derive(Copy, Clone, PartialEq, Eq)]
+/// How a chain of operators with the same precedence is grouped
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Associativity {
+    /// `a - b - c` is `(a - b) - c`
+    Left,
+    /// `a = b = c` is `a = (b = c)`
+    Right,
+    /// `a < b < c` is a syntax error
+    NonAssociative,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum BinaryOperator {
     Add,
     Subtract,
     Divide,
     Multiply,
+    Modulo,
     Assign,
     IsEq,
     IsNEq,
+    Less,
+    LessEq,
+    Greater,
+    GreaterEq,
+    And,
+    Or,
+    /// `1 to 10`, a range including both ends
+    Range,
+    /// `ages has "bo"`, whether a map has a key
+    Has,
 }
 
 impl BinaryOperator {
     pub fn get_precedence(&self) -> u8 {
         match self {
-            BinaryOperator::Assign => 0,
-            BinaryOperator::IsNEq | BinaryOperator::IsEq => 1,
+            // expressions are parsed starting from precedence 0 and only operators
+            // above it are picked up, so the loosest operator has to be 1
+            BinaryOperator::Assign => 1,
+            BinaryOperator::Or => 2,
+            BinaryOperator::And => 3,
+            BinaryOperator::IsNEq | BinaryOperator::IsEq => 4,
+            BinaryOperator::Less
+            | BinaryOperator::LessEq
+            | BinaryOperator::Greater
+            | BinaryOperator::GreaterEq
+            | BinaryOperator::Has => 5,
+            BinaryOperator::Range => 7,
             BinaryOperator::Add | BinaryOperator::Subtract => 10,
-            BinaryOperator::Multiply | BinaryOperator::Divide => 20,
+            BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo => 20,
+        }
+    }
+
+    pub fn get_associativity(&self) -> Associativity {
+        match self {
+            BinaryOperator::Assign => Associativity::Right,
+            BinaryOperator::IsEq
+            | BinaryOperator::IsNEq
+            | BinaryOperator::Less
+            | BinaryOperator::LessEq
+            | BinaryOperator::Greater
+            | BinaryOperator::GreaterEq
+            | BinaryOperator::Has => Associativity::NonAssociative,
+            _ => Associativity::Left,
         }
     }
 }
 
-impl std::string::ToString for BinaryOperator {
-    fn to_string(&self) -> String {
-        (match self {
+impl std::fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
             BinaryOperator::Add => "+",
             BinaryOperator::Subtract => "-",
             BinaryOperator::Multiply => "*",
             BinaryOperator::Divide => "/",
+            BinaryOperator::Modulo => "%",
             BinaryOperator::Assign => "=",
             BinaryOperator::IsEq => "==",
             BinaryOperator::IsNEq => "!=",
+            BinaryOperator::Less => "<",
+            BinaryOperator::LessEq => "<=",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::GreaterEq => ">=",
+            BinaryOperator::And => "and",
+            BinaryOperator::Or => "or",
+            BinaryOperator::Range => "to",
+            BinaryOperator::Has => "has",
         })
-        .to_string()
     }
 }
@@ -1,32 +1,348 @@
+use crate::diagnostics::{Diagnostic, Style};
+use crate::types::binary_operator::BinaryOperator;
 use crate::types::primitive_value::PrimitiveValue;
+use crate::types::span::Span;
+use crate::types::token::Token;
 
-#[derive(Debug)]
-pub struct TokenError;
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenErrorKind {
+    UnknownCharacter(char),
+    UnterminatedString,
+    MalformedNumber(String),
+    FloatOverflow(String),
+}
+
+impl std::fmt::Display for TokenErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenErrorKind::UnknownCharacter(c) => write!(f, "unknown character `{}`", c),
+            TokenErrorKind::UnterminatedString => write!(f, "unterminated string"),
+            TokenErrorKind::MalformedNumber(num) => write!(f, "malformed number `{}`", num),
+            TokenErrorKind::FloatOverflow(num) => {
+                write!(f, "float `{}` is too large to fit in a float", num)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenError {
+    pub kind: TokenErrorKind,
+    pub span: Span,
+}
+
+impl std::fmt::Display for TokenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.span, self.kind)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    UnexpectedToken,
+    MissingToBe,
+    UnterminatedBlock,
+    BadThrowOperand,
+    ChainedComparison,
+    /// a pattern binds the same name twice, ie `Pair(a, a)`
+    DuplicateBinding,
+}
+
+impl std::fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ParseErrorKind::UnexpectedToken => "unexpected token",
+            ParseErrorKind::MissingToBe => "missing `to be`",
+            ParseErrorKind::UnterminatedBlock => "unterminated block",
+            ParseErrorKind::BadThrowOperand => "bad throw operand",
+            ParseErrorKind::ChainedComparison => "chained comparison",
+            ParseErrorKind::DuplicateBinding => "duplicate binding",
+        })
+    }
+}
 
-#[derive(Debug, Clone)]
-pub struct NotImplemented {
-    a: String,
-    b: String,
+/// A syntax error, `expected` describes what the parser was looking for
+/// and `found` is the token it got instead
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub expected: String,
+    pub found: Token,
+    pub span: Span,
 }
 
-impl NotImplemented {
-    pub fn from(a: &PrimitiveValue, b: &PrimitiveValue) -> NotImplemented {
-        NotImplemented {
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {}, expected {} found {}",
+            self.span,
+            self.kind,
+            self.expected,
+            self.found.describe()
+        )
+    }
+}
+
+/// Why applying an operator to two values failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OperatorError {
+    /// the operator doesn't work on these types of values
+    NotImplemented {
+        a: String,
+        b: String,
+    },
+    DivisionByZero,
+    /// logic operators only work on bools
+    ExpectedBool {
+        operator: String,
+        found: String,
+    },
+    /// arithmetic operators only work on numbers
+    ExpectedNumber {
+        operator: String,
+        found: String,
+    },
+    /// the result doesn't fit in a number
+    Overflow {
+        a: String,
+        operator: BinaryOperator,
+        b: String,
+    },
+    /// list operators only work on lists
+    ExpectedList {
+        operator: String,
+        found: String,
+    },
+    ExpectedMap {
+        operator: String,
+        found: String,
+    },
+    /// maps only work with keys that are strings
+    ExpectedString {
+        operator: String,
+        found: String,
+    },
+    /// the operator works on a list or a map
+    ExpectedCollection {
+        operator: String,
+        found: String,
+    },
+    IndexOutOfBounds {
+        index: String,
+        length: usize,
+    },
+    MissingKey {
+        key: String,
+    },
+    /// only records have fields
+    ExpectedRecord {
+        field: String,
+        found: String,
+    },
+    MissingField {
+        record: String,
+        field: String,
+    },
+    /// a record field was given a value of the wrong type
+    FieldType {
+        record: String,
+        field: String,
+        expected: String,
+        found: String,
+    },
+    /// there's nothing in the list for the operator to take
+    EmptyList {
+        operator: String,
+    },
+}
+
+impl OperatorError {
+    pub fn not_implemented(a: &PrimitiveValue, b: &PrimitiveValue) -> OperatorError {
+        OperatorError::NotImplemented {
+            a: a.to_string(),
+            b: b.to_string(),
+        }
+    }
+
+    pub fn overflow(
+        a: &PrimitiveValue,
+        operator: BinaryOperator,
+        b: &PrimitiveValue,
+    ) -> OperatorError {
+        OperatorError::Overflow {
             a: a.to_string(),
+            operator,
             b: b.to_string(),
         }
     }
 }
 
+impl std::fmt::Display for OperatorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OperatorError::NotImplemented { a, b } => {
+                write!(f, "Operator not implemented for {} and {}.", a, b)
+            }
+            OperatorError::DivisionByZero => write!(f, "Division by zero."),
+            OperatorError::ExpectedBool { operator, found } => {
+                write!(f, "Expected a bool for {}, found {}.", operator, found)
+            }
+            OperatorError::ExpectedNumber { operator, found } => {
+                write!(f, "Expected a number for {}, found {}.", operator, found)
+            }
+            OperatorError::Overflow { a, operator, b } => {
+                write!(f, "Arithmetic overflow in {} {} {}.", a, operator, b)
+            }
+            OperatorError::ExpectedList { operator, found } => {
+                write!(f, "Expected a list for {}, found {}.", operator, found)
+            }
+            OperatorError::ExpectedMap { operator, found } => {
+                write!(f, "Expected a map for {}, found {}.", operator, found)
+            }
+            OperatorError::ExpectedString { operator, found } => {
+                write!(f, "Expected a string for {}, found {}.", operator, found)
+            }
+            OperatorError::ExpectedCollection { operator, found } => {
+                write!(
+                    f,
+                    "Expected a list or map for {}, found {}.",
+                    operator, found
+                )
+            }
+            OperatorError::ExpectedRecord { field, found } => {
+                write!(f, "Expected a record for .{}, found {}.", field, found)
+            }
+            OperatorError::MissingField { record, field } => {
+                write!(f, "{} has no field {}.", record, field)
+            }
+            OperatorError::FieldType {
+                record,
+                field,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Field {} of {} has to be a {}, found {}.",
+                field, record, expected, found
+            ),
+            OperatorError::MissingKey { key } => write!(f, "Key \"{}\" isn't in the map.", key),
+            OperatorError::IndexOutOfBounds { index, length } => write!(
+                f,
+                "Index {} is out of bounds for a list of length {}.",
+                index, length
+            ),
+            OperatorError::EmptyList { operator } => {
+                write!(f, "Can't {} an empty list.", operator)
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct RuntimeError {
     pub message: String,
+    pub span: Option<Span>,
 }
 
-impl std::convert::From<NotImplemented> for RuntimeError {
-    fn from(a: NotImplemented) -> RuntimeError {
+impl RuntimeError {
+    pub fn new(message: String) -> RuntimeError {
         RuntimeError {
-            message: format!("Operator not implemented for {} and {}.", a.a, a.b),
+            message,
+            span: None,
         }
     }
+
+    /// Point the error at `span` unless a more specific span was already set
+    pub fn or_span(mut self, span: &Span) -> RuntimeError {
+        if self.span.is_none() && !span.is_empty() {
+            self.span = Some(span.clone());
+        }
+        self
+    }
+}
+
+impl std::fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.span {
+            Some(span) => write!(f, "{}: {}", span, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::convert::From<OperatorError> for RuntimeError {
+    fn from(error: OperatorError) -> RuntimeError {
+        RuntimeError::new(error.to_string())
+    }
+}
+
+/**
+ * Anything that can go wrong running eye code through the library
+ */
+#[derive(Debug)]
+pub enum Error {
+    /// the source file couldn't be read
+    Io {
+        path: String,
+        error: std::io::Error,
+    },
+    Lex(TokenError),
+    /// every syntax error found in the program
+    Parse(Vec<ParseError>),
+    Runtime(RuntimeError),
+}
+
+impl Error {
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            Error::Io { .. } => vec![Diagnostic::error("E0300", self.to_string())],
+            Error::Lex(error) => vec![Diagnostic::from(error)],
+            Error::Parse(errors) => errors.iter().map(Diagnostic::from).collect(),
+            Error::Runtime(error) => vec![Diagnostic::from(error)],
+        }
+    }
+
+    /// Render every diagnostic against the program's source
+    pub fn render(&self, source: &str, style: Style) -> String {
+        self.diagnostics()
+            .iter()
+            .map(|diagnostic| diagnostic.render(source, style))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io { path, error } => write!(f, "Failed to read file: {}: {}", path, error),
+            Error::Lex(error) => write!(f, "{}", error),
+            Error::Parse(errors) => {
+                let lines: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+                write!(f, "{}", lines.join("\n"))
+            }
+            Error::Runtime(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl std::convert::From<TokenError> for Error {
+    fn from(error: TokenError) -> Error {
+        Error::Lex(error)
+    }
+}
+
+impl std::convert::From<Vec<ParseError>> for Error {
+    fn from(errors: Vec<ParseError>) -> Error {
+        Error::Parse(errors)
+    }
+}
+
+impl std::convert::From<RuntimeError> for Error {
+    fn from(error: RuntimeError) -> Error {
+        Error::Runtime(error)
+    }
 }
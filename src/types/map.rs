@@ -0,0 +1,95 @@
+use crate::types::primitive_value::PrimitiveValue;
+
+use std::collections::HashMap;
+
+/// Values looked up by string keys, entries stay in the order their keys were first added
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Map {
+    entries: Vec<(String, PrimitiveValue)>,
+    /// where each key's entry is in `entries`
+    positions: HashMap<String, usize>,
+}
+
+impl Map {
+    pub fn new() -> Map {
+        Map::default()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&PrimitiveValue> {
+        self.positions
+            .get(key)
+            .map(|position| &self.entries[*position].1)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.positions.contains_key(key)
+    }
+
+    /// Set the value of a key, a key that's already there keeps its place
+    pub fn insert(&mut self, key: String, value: PrimitiveValue) {
+        match self.positions.get(&key) {
+            Some(position) => self.entries[*position].1 = value,
+            None => {
+                self.positions.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<PrimitiveValue> {
+        let position = self.positions.remove(key)?;
+        let (_, value) = self.entries.remove(position);
+        // everything after the removed entry moved back one
+        for (key, _) in &self.entries[position..] {
+            *self.positions.get_mut(key).unwrap() -= 1;
+        }
+        Some(value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Every key and value in insertion order
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &PrimitiveValue)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+}
+
+impl std::iter::FromIterator<(String, PrimitiveValue)> for Map {
+    fn from_iter<I: IntoIterator<Item = (String, PrimitiveValue)>>(entries: I) -> Map {
+        let mut map = Map::new();
+        for (key, value) in entries {
+            map.insert(key, value);
+        }
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_insertion_order() {
+        let mut map: Map = vec![("b", 1), ("a", 2), ("c", 3)]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), PrimitiveValue::Num(value)))
+            .collect();
+        map.insert("b".to_string(), PrimitiveValue::Num(4));
+        assert_eq!(map.remove("a"), Some(PrimitiveValue::Num(2)));
+        assert_eq!(map.remove("a"), None);
+        map.insert("a".to_string(), PrimitiveValue::Num(5));
+
+        let keys: Vec<&String> = map.iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, vec!["b", "c", "a"]);
+        assert_eq!(map.get("c"), Some(&PrimitiveValue::Num(3)));
+        assert_eq!(map.get("b"), Some(&PrimitiveValue::Num(4)));
+        assert!(map.contains_key("a"));
+        assert_eq!(map.len(), 3);
+    }
+}
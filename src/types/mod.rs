@@ -1,7 +1,12 @@
 pub mod ast;
 pub mod binary_operator;
 pub mod error;
+pub mod map;
+pub mod native_function;
 pub mod options;
 pub mod primitive_value;
+pub mod record;
+pub mod span;
 pub mod symbol_store;
 pub mod token;
+pub mod unary_operator;
@@ -0,0 +1,255 @@
+use crate::types::error::RuntimeError;
+use crate::types::primitive_value::PrimitiveValue;
+
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+use std::rc::Rc;
+
+/// What a native function gets called with, arguments are already evaluated
+pub type NativeFn = dyn Fn(Vec<PrimitiveValue>) -> Result<Option<PrimitiveValue>, RuntimeError>;
+
+/**
+ * A function implemented in Rust by the program embedding eye.
+ * `call` checks the arity so the function always gets exactly
+ * `arity` arguments.
+ */
+#[derive(Clone)]
+pub struct NativeFunction {
+    pub name: String,
+    pub arity: usize,
+    function: Rc<NativeFn>,
+}
+
+impl NativeFunction {
+    pub fn new<F>(name: &str, arity: usize, function: F) -> NativeFunction
+    where
+        F: Fn(Vec<PrimitiveValue>) -> Result<Option<PrimitiveValue>, RuntimeError> + 'static,
+    {
+        NativeFunction {
+            name: name.to_string(),
+            arity,
+            function: Rc::new(function),
+        }
+    }
+
+    pub fn call(&self, args: Vec<PrimitiveValue>) -> Result<Option<PrimitiveValue>, RuntimeError> {
+        if args.len() != self.arity {
+            return Err(RuntimeError::new(format!(
+                "Function {} takes {} arguments but was given {}",
+                self.name,
+                self.arity,
+                args.len()
+            )));
+        }
+        (self.function)(args)
+    }
+}
+
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &NativeFunction) -> bool {
+        Rc::ptr_eq(&self.function, &other.function)
+    }
+}
+
+impl std::fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NativeFunction({}/{})", self.name, self.arity)
+    }
+}
+
+/// Rust types a native function can take as arguments
+pub trait FromValue: Sized {
+    /// what the type is called in error messages
+    const TYPE: &'static str;
+
+    fn from_value(value: &PrimitiveValue) -> Option<Self>;
+}
+
+impl FromValue for PrimitiveValue {
+    const TYPE: &'static str = "value";
+
+    fn from_value(value: &PrimitiveValue) -> Option<PrimitiveValue> {
+        Some(value.clone())
+    }
+}
+
+impl FromValue for i32 {
+    const TYPE: &'static str = "number";
+
+    fn from_value(value: &PrimitiveValue) -> Option<i32> {
+        match value {
+            PrimitiveValue::Num(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for i64 {
+    const TYPE: &'static str = "number";
+
+    fn from_value(value: &PrimitiveValue) -> Option<i64> {
+        value.as_big().and_then(|value| value.to_i64())
+    }
+}
+
+/// numbers are promoted to floats
+impl FromValue for f64 {
+    const TYPE: &'static str = "float";
+
+    fn from_value(value: &PrimitiveValue) -> Option<f64> {
+        value.as_float()
+    }
+}
+
+impl FromValue for bool {
+    const TYPE: &'static str = "bool";
+
+    fn from_value(value: &PrimitiveValue) -> Option<bool> {
+        match value {
+            PrimitiveValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for String {
+    const TYPE: &'static str = "string";
+
+    fn from_value(value: &PrimitiveValue) -> Option<String> {
+        match value {
+            PrimitiveValue::Str(value) => Some(value.to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// Rust types that can be handed back to eye code
+pub trait IntoValue {
+    fn into_value(self) -> PrimitiveValue;
+}
+
+impl IntoValue for PrimitiveValue {
+    fn into_value(self) -> PrimitiveValue {
+        self
+    }
+}
+
+impl IntoValue for i32 {
+    fn into_value(self) -> PrimitiveValue {
+        PrimitiveValue::Num(self)
+    }
+}
+
+impl IntoValue for i64 {
+    fn into_value(self) -> PrimitiveValue {
+        PrimitiveValue::from(BigInt::from(self))
+    }
+}
+
+impl IntoValue for f64 {
+    fn into_value(self) -> PrimitiveValue {
+        PrimitiveValue::Float(self)
+    }
+}
+
+impl IntoValue for bool {
+    fn into_value(self) -> PrimitiveValue {
+        PrimitiveValue::Bool(self)
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> PrimitiveValue {
+        PrimitiveValue::Str(self)
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> PrimitiveValue {
+        PrimitiveValue::Str(self.to_string())
+    }
+}
+
+/// What a native function can return, `()` and `None` return no value
+pub trait IntoReturn {
+    fn into_return(self) -> Result<Option<PrimitiveValue>, RuntimeError>;
+}
+
+impl<T: IntoValue> IntoReturn for T {
+    fn into_return(self) -> Result<Option<PrimitiveValue>, RuntimeError> {
+        Ok(Some(self.into_value()))
+    }
+}
+
+impl IntoReturn for () {
+    fn into_return(self) -> Result<Option<PrimitiveValue>, RuntimeError> {
+        Ok(None)
+    }
+}
+
+impl<T: IntoValue> IntoReturn for Option<T> {
+    fn into_return(self) -> Result<Option<PrimitiveValue>, RuntimeError> {
+        Ok(self.map(IntoValue::into_value))
+    }
+}
+
+impl<T: IntoReturn> IntoReturn for Result<T, RuntimeError> {
+    fn into_return(self) -> Result<Option<PrimitiveValue>, RuntimeError> {
+        self?.into_return()
+    }
+}
+
+/// Convert argument `position` of a call to `name`
+fn convert<T: FromValue>(
+    name: &str,
+    position: usize,
+    value: PrimitiveValue,
+) -> Result<T, RuntimeError> {
+    T::from_value(&value).ok_or_else(|| {
+        RuntimeError::new(format!(
+            "Function {} expected a {} for argument {}, found {}",
+            name,
+            T::TYPE,
+            position,
+            value
+        ))
+    })
+}
+
+/**
+ * Rust closures that can be registered as native functions, the arity comes
+ * from the closure's parameters and arguments are converted with `FromValue`
+ */
+pub trait IntoNative<Args> {
+    fn into_native(self, name: &str) -> NativeFunction;
+}
+
+macro_rules! impl_into_native {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg: FromValue),*> IntoNative<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R + 'static,
+            R: IntoReturn,
+        {
+            #[allow(unused_mut, unused_variables)]
+            fn into_native(self, name: &str) -> NativeFunction {
+                let arity = <[&str]>::len(&[$(stringify!($arg)),*]);
+                let fn_name = name.to_string();
+                NativeFunction::new(name, arity, move |args| {
+                    let mut args = args.into_iter().enumerate();
+                    self($({
+                        let (position, value) = args.next().unwrap();
+                        convert::<$arg>(&fn_name, position + 1, value)?
+                    }),*)
+                    .into_return()
+                })
+            }
+        }
+    };
+}
+
+impl_into_native!();
+impl_into_native!(A);
+impl_into_native!(A, B);
+impl_into_native!(A, B, C);
+impl_into_native!(A, B, C, D);
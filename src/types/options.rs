@@ -1,34 +1,80 @@
 use crate::types::primitive_value::PrimitiveValue;
 
-pub type PrintFn = fn(a: PrimitiveValue);
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
+
+/// Called with every value a program prints, clones of `Options` share it
+pub type PrintFn = Rc<RefCell<dyn FnMut(PrimitiveValue)>>;
+
+/// How a program gets run, both backends should behave the same
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// walk the AST directly
+    TreeWalk,
+    /// compile to bytecode and run it on the stack VM
+    Bytecode,
+}
 
 #[derive(Clone)]
 pub struct Options {
     pub print_fn: PrintFn,
+    pub backend: Backend,
     pub debug: bool,
 }
 
 impl Options {
-    #[allow(dead_code)]
     pub fn debug() -> Options {
         Options {
-            print_fn: debug_print,
             debug: true,
+            ..Options::default()
         }
     }
 
-    pub fn default() -> Options {
+    /// Hand printed values to `print_fn` instead of stdout
+    pub fn with_print<F: FnMut(PrimitiveValue) + 'static>(mut self, print_fn: F) -> Options {
+        self.print_fn = Rc::new(RefCell::new(print_fn));
+        self
+    }
+
+    /// Write printed values to `sink`, one per line
+    pub fn print_to<W: Write + 'static>(self, mut sink: W) -> Options {
+        self.with_print(move |value| {
+            // there's nowhere to report a failed write to, same as println!
+            let _ = writeln!(sink, "{}", value);
+        })
+    }
+
+    /// Print a value through the print hook
+    pub fn print(&self, value: PrimitiveValue) {
+        (self.print_fn.borrow_mut())(value);
+    }
+}
+
+#[cfg(test)]
+impl Options {
+    /// Debug options for each backend, for tests that check they behave the same
+    pub fn every_backend() -> Vec<Options> {
+        [Backend::TreeWalk, Backend::Bytecode]
+            .iter()
+            .map(|backend| Options {
+                backend: *backend,
+                ..Options::debug()
+            })
+            .collect()
+    }
+}
+
+impl Default for Options {
+    fn default() -> Options {
         Options {
-            print_fn: default_print,
+            print_fn: Rc::new(RefCell::new(default_print)),
+            backend: Backend::TreeWalk,
             debug: false,
         }
     }
 }
 
-fn debug_print(a: PrimitiveValue) {
-    default_print(a);
-}
-
 fn default_print(a: PrimitiveValue) {
-    println!("{}", a.to_string());
+    println!("{}", a);
 }
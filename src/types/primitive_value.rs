@@ -1,71 +1,329 @@
 use crate::types::ast::FunctionBody;
-use crate::types::error::NotImplemented;
+use crate::types::binary_operator::BinaryOperator;
+use crate::types::error::OperatorError;
+use crate::types::map::Map;
+use crate::types::native_function::NativeFunction;
+use crate::types::record::{Record, RecordType};
+use crate::types::unary_operator::UnaryOperator;
+use crate::vm::Closure;
 
-type OperatorValue<T> = Result<T, NotImplemented>;
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::rc::Rc;
+use std::thread::LocalKey;
 
-#[allow(dead_code)]
-#[derive(Debug, Clone, PartialEq)]
+type OperatorValue<T> = Result<T, OperatorError>;
+
+thread_local! {
+    /// lists, maps and records being printed further up the stack
+    static PRINTING: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+    /// pairs of lists, maps and records being compared further up the stack
+    static COMPARING: RefCell<Vec<(usize, usize)>> = const { RefCell::new(Vec::new()) };
+}
+
+/**
+ * Run `f` unless `key` is already being visited further up the stack, a list
+ * can hold itself so walking into it again would never end
+ */
+fn guard_cycle<K: PartialEq + 'static, T>(
+    visiting: &'static LocalKey<RefCell<Vec<K>>>,
+    key: K,
+    f: impl FnOnce() -> T,
+) -> Option<T> {
+    struct Leave<K: 'static>(&'static LocalKey<RefCell<Vec<K>>>);
+    impl<K> Drop for Leave<K> {
+        fn drop(&mut self) {
+            self.0.with(|visiting| visiting.borrow_mut().pop());
+        }
+    }
+
+    if visiting.with(|visiting| visiting.borrow().contains(&key)) {
+        return None;
+    }
+    visiting.with(|visiting| visiting.borrow_mut().push(key));
+    let _leave = Leave(visiting);
+    Some(f())
+}
+
+/// Key for printing a shared value
+fn pointer<T>(value: &Rc<T>) -> usize {
+    Rc::as_ptr(value) as *const () as usize
+}
+
+/// Key for comparing two shared values
+fn pair<T>(a: &Rc<T>, b: &Rc<T>) -> (usize, usize) {
+    (pointer(a), pointer(b))
+}
+
+#[derive(Clone)]
 pub enum PrimitiveValue {
     Str(String),
     Num(i32),
+    /// an integer that doesn't fit in `Num`, arithmetic moves between the two
+    BigNum(Rc<BigInt>),
+    Float(f64),
     Bool(bool),
+    /// every number from the first to the second, including both
+    Range(i32, i32),
+    /// lists are shared, changing one changes it everywhere it's used
+    List(Rc<RefCell<Vec<PrimitiveValue>>>),
+    /// maps are shared like lists
+    Map(Rc<RefCell<Map>>),
+    /// calling a record type constructs a record
+    RecordType(Rc<RecordType>),
+    /// records are shared like lists
+    Record(Rc<RefCell<Record>>),
     Function(FunctionBody),
+    /// a function compiled by the bytecode backend
+    Closure(Rc<Closure>),
+    /// a function provided by the program embedding eye
+    Native(NativeFunction),
 }
 
-impl std::ops::Not for PrimitiveValue {
-    type Output = PrimitiveValue;
-    fn not(self) -> PrimitiveValue {
-        if let PrimitiveValue::Bool(val) = self {
-            PrimitiveValue::Bool(!val)
-        } else {
-            PrimitiveValue::Bool(false)
+/// Like a derived `PartialEq`, but lists, maps and records that hold themselves don't recurse forever
+impl PartialEq for PrimitiveValue {
+    fn eq(&self, other: &PrimitiveValue) -> bool {
+        match (self, other) {
+            (PrimitiveValue::Str(a), PrimitiveValue::Str(b)) => a == b,
+            (PrimitiveValue::Num(a), PrimitiveValue::Num(b)) => a == b,
+            (PrimitiveValue::BigNum(a), PrimitiveValue::BigNum(b)) => a == b,
+            (PrimitiveValue::Float(a), PrimitiveValue::Float(b)) => a == b,
+            (PrimitiveValue::Bool(a), PrimitiveValue::Bool(b)) => a == b,
+            (PrimitiveValue::Range(a, b), PrimitiveValue::Range(c, d)) => (a, b) == (c, d),
+            (PrimitiveValue::List(a), PrimitiveValue::List(b)) => {
+                guard_cycle(&COMPARING, pair(a, b), || a == b).unwrap_or(true)
+            }
+            (PrimitiveValue::Map(a), PrimitiveValue::Map(b)) => {
+                guard_cycle(&COMPARING, pair(a, b), || a == b).unwrap_or(true)
+            }
+            (PrimitiveValue::RecordType(a), PrimitiveValue::RecordType(b)) => a == b,
+            (PrimitiveValue::Record(a), PrimitiveValue::Record(b)) => {
+                guard_cycle(&COMPARING, pair(a, b), || a == b).unwrap_or(true)
+            }
+            (PrimitiveValue::Function(a), PrimitiveValue::Function(b)) => a == b,
+            (PrimitiveValue::Closure(a), PrimitiveValue::Closure(b)) => a == b,
+            (PrimitiveValue::Native(a), PrimitiveValue::Native(b)) => a == b,
+            _ => false,
         }
     }
 }
 
-impl PrimitiveValue {
-    pub fn add(self: Self, other: Self) -> OperatorValue<Self> {
-        let err_val = NotImplemented::from(&self, &other);
+/// Like a derived `Debug`, a list, map or record inside itself shows as `...`
+impl std::fmt::Debug for PrimitiveValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let shared =
+            |f: &mut std::fmt::Formatter<'_>, name: &str, key, value: &dyn std::fmt::Debug| {
+                guard_cycle(&PRINTING, key, || f.debug_tuple(name).field(value).finish())
+                    .unwrap_or_else(|| write!(f, "{}(...)", name))
+            };
         match self {
-            PrimitiveValue::Str(a) => match other {
-                PrimitiveValue::Str(b) => Ok(PrimitiveValue::Str(format!("{}{}", a, b))),
-                _ => Err(err_val),
-            },
-            PrimitiveValue::Num(a) => match other {
-                PrimitiveValue::Num(b) => Ok(PrimitiveValue::Num(a + b)),
-                _ => Err(err_val),
-            },
-            _ => Err(err_val),
+            PrimitiveValue::Str(value) => f.debug_tuple("Str").field(value).finish(),
+            PrimitiveValue::Num(value) => f.debug_tuple("Num").field(value).finish(),
+            PrimitiveValue::BigNum(value) => f.debug_tuple("BigNum").field(value).finish(),
+            PrimitiveValue::Float(value) => f.debug_tuple("Float").field(value).finish(),
+            PrimitiveValue::Bool(value) => f.debug_tuple("Bool").field(value).finish(),
+            PrimitiveValue::Range(start, end) => {
+                f.debug_tuple("Range").field(start).field(end).finish()
+            }
+            PrimitiveValue::List(items) => shared(f, "List", pointer(items), items),
+            PrimitiveValue::Map(map) => shared(f, "Map", pointer(map), map),
+            PrimitiveValue::RecordType(record_type) => {
+                f.debug_tuple("RecordType").field(record_type).finish()
+            }
+            PrimitiveValue::Record(record) => shared(f, "Record", pointer(record), record),
+            PrimitiveValue::Function(function) => {
+                f.debug_tuple("Function").field(function).finish()
+            }
+            PrimitiveValue::Closure(closure) => f.debug_tuple("Closure").field(closure).finish(),
+            PrimitiveValue::Native(function) => f.debug_tuple("Native").field(function).finish(),
+        }
+    }
+}
+
+/// Integers are only bignums when they don't fit in a number
+impl From<BigInt> for PrimitiveValue {
+    fn from(value: BigInt) -> PrimitiveValue {
+        match i32::try_from(&value) {
+            Ok(value) => PrimitiveValue::Num(value),
+            Err(_) => PrimitiveValue::BigNum(Rc::new(value)),
         }
     }
+}
+
+impl From<Vec<PrimitiveValue>> for PrimitiveValue {
+    fn from(items: Vec<PrimitiveValue>) -> PrimitiveValue {
+        PrimitiveValue::List(Rc::new(RefCell::new(items)))
+    }
+}
+
+impl From<Map> for PrimitiveValue {
+    fn from(map: Map) -> PrimitiveValue {
+        PrimitiveValue::Map(Rc::new(RefCell::new(map)))
+    }
+}
+
+impl std::ops::Not for PrimitiveValue {
+    type Output = OperatorValue<PrimitiveValue>;
+    fn not(self) -> OperatorValue<PrimitiveValue> {
+        Ok(PrimitiveValue::Bool(!self.expect_bool(UnaryOperator::Not)?))
+    }
+}
+
+impl PrimitiveValue {
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(self, other: Self) -> OperatorValue<Self> {
+        if let (PrimitiveValue::Str(a), PrimitiveValue::Str(b)) = (&self, &other) {
+            return Ok(PrimitiveValue::Str(format!("{}{}", a, b)));
+        }
+        // joining lists makes a new list, neither side changes
+        if let (PrimitiveValue::List(a), PrimitiveValue::List(b)) = (&self, &other) {
+            let mut items = a.borrow().clone();
+            items.extend(b.borrow().iter().cloned());
+            return Ok(PrimitiveValue::from(items));
+        }
+        self.arithmetic(
+            other,
+            BinaryOperator::Add,
+            i32::checked_add,
+            |a, b| a + b,
+            |a, b| a + b,
+        )
+    }
 
     pub fn subtract(self, other: Self) -> OperatorValue<Self> {
-        let err_val = NotImplemented::from(&self, &other);
+        self.arithmetic(
+            other,
+            BinaryOperator::Subtract,
+            i32::checked_sub,
+            |a, b| a - b,
+            |a, b| a - b,
+        )
+    }
+
+    pub fn multiply(self, other: Self) -> OperatorValue<Self> {
+        self.arithmetic(
+            other,
+            BinaryOperator::Multiply,
+            i32::checked_mul,
+            |a, b| a * b,
+            |a, b| a * b,
+        )
+    }
+
+    /// Division of two numbers rounds towards zero, if either is a float so is the result
+    pub fn divide(self, other: Self) -> OperatorValue<Self> {
+        if other.is_zero() && self.is_number() {
+            return Err(OperatorError::DivisionByZero);
+        }
+        self.arithmetic(
+            other,
+            BinaryOperator::Divide,
+            i32::checked_div,
+            |a, b| a / b,
+            |a, b| a / b,
+        )
+    }
+
+    /// Remainder of dividing, it takes the sign of the left side like Rust's `%`
+    pub fn modulo(self, other: Self) -> OperatorValue<Self> {
+        if other.is_zero() && self.is_number() {
+            return Err(OperatorError::DivisionByZero);
+        }
+        self.arithmetic(
+            other,
+            BinaryOperator::Modulo,
+            i32::checked_rem,
+            |a, b| a % b,
+            |a, b| a % b,
+        )
+    }
+
+    /**
+     * Apply an arithmetic operator to two numbers. Integers that overflow move
+     * to bignums, either side being a float makes the result a float and float
+     * results that aren't finite are an overflow error.
+     */
+    fn arithmetic(
+        self,
+        other: Self,
+        operator: BinaryOperator,
+        int_op: fn(i32, i32) -> Option<i32>,
+        big_op: fn(BigInt, BigInt) -> BigInt,
+        float_op: fn(f64, f64) -> f64,
+    ) -> OperatorValue<Self> {
+        if let (PrimitiveValue::Num(a), PrimitiveValue::Num(b)) = (&self, &other) {
+            if let Some(value) = int_op(*a, *b) {
+                return Ok(PrimitiveValue::Num(value));
+            }
+        }
+        if let (Some(a), Some(b)) = (self.as_big(), other.as_big()) {
+            return Ok(PrimitiveValue::from(big_op(a, b)));
+        }
+
+        match (self.as_float(), other.as_float()) {
+            (Some(a), Some(b)) => {
+                let value = float_op(a, b);
+                if value.is_finite() {
+                    Ok(PrimitiveValue::Float(value))
+                } else {
+                    Err(OperatorError::overflow(&self, operator, &other))
+                }
+            }
+            _ => Err(OperatorError::not_implemented(&self, &other)),
+        }
+    }
+
+    fn is_number(&self) -> bool {
+        self.as_float().is_some()
+    }
+
+    fn is_zero(&self) -> bool {
+        self.as_float() == Some(0.0)
+    }
+
+    /// The value of any kind of number as a float
+    pub fn as_float(&self) -> Option<f64> {
         match self {
-            PrimitiveValue::Num(a) => match other {
-                PrimitiveValue::Num(b) => Ok(PrimitiveValue::Num(a - b)),
-                _ => Err(err_val),
-            },
-            _ => Err(err_val),
+            PrimitiveValue::Num(value) => Some(*value as f64),
+            PrimitiveValue::BigNum(value) => value.to_f64(),
+            PrimitiveValue::Float(value) => Some(*value),
+            _ => None,
         }
     }
 
-    pub fn multiply(self, other: Self) -> OperatorValue<Self> {
-        let err_val = NotImplemented::from(&self, &other);
+    /// The value of an integer of either size as a bignum
+    pub fn as_big(&self) -> Option<BigInt> {
         match self {
-            PrimitiveValue::Num(a) => match other {
-                PrimitiveValue::Num(b) => Ok(PrimitiveValue::Num(a * b)),
-                _ => Err(err_val),
-            },
-            _ => Err(err_val),
+            PrimitiveValue::Num(value) => Some(BigInt::from(*value)),
+            PrimitiveValue::BigNum(value) => Some(value.as_ref().clone()),
+            _ => None,
         }
     }
 
+    #[allow(clippy::wrong_self_convention)]
     pub fn is_equal(self, other: Self) -> OperatorValue<Self> {
-        let err_val = NotImplemented::from(&self, &other);
+        let err_val = OperatorError::not_implemented(&self, &other);
         match self {
             PrimitiveValue::Num(a) => match other {
                 PrimitiveValue::Num(b) => Ok(PrimitiveValue::Bool(a == b)),
+                PrimitiveValue::BigNum(_) => {
+                    Ok(PrimitiveValue::Bool(self.as_big() == other.as_big()))
+                }
+                PrimitiveValue::Float(b) => Ok(PrimitiveValue::Bool(self.as_float() == Some(b))),
+                _ => Err(err_val),
+            },
+            PrimitiveValue::BigNum(_) => match other {
+                PrimitiveValue::Num(_) | PrimitiveValue::BigNum(_) => {
+                    Ok(PrimitiveValue::Bool(self.as_big() == other.as_big()))
+                }
+                PrimitiveValue::Float(b) => Ok(PrimitiveValue::Bool(self.as_float() == Some(b))),
+                _ => Err(err_val),
+            },
+            PrimitiveValue::Float(a) => match other.as_float() {
+                Some(b) => Ok(PrimitiveValue::Bool(a == b)),
                 _ => Err(err_val),
             },
             PrimitiveValue::Bool(a) => match other {
@@ -76,36 +334,447 @@ impl PrimitiveValue {
                 PrimitiveValue::Str(b) => Ok(PrimitiveValue::Bool(a == b)),
                 _ => Err(err_val),
             },
+            PrimitiveValue::Range(a, b) => match other {
+                PrimitiveValue::Range(c, d) => Ok(PrimitiveValue::Bool((a, b) == (c, d))),
+                _ => Err(err_val),
+            },
+            // lists are equal when their items are, in order. Comparing a pair
+            // again means the lists hold themselves, any difference is found
+            // by the comparison already under way so the repeat counts as equal
+            PrimitiveValue::List(a) => match other {
+                PrimitiveValue::List(b) => guard_cycle(&COMPARING, pair(&a, &b), || {
+                    let (a, b) = (a.borrow(), b.borrow());
+                    if a.len() != b.len() {
+                        return Ok(PrimitiveValue::Bool(false));
+                    }
+                    for (a, b) in a.iter().zip(b.iter()) {
+                        if a.clone().is_equal(b.clone())? == PrimitiveValue::Bool(false) {
+                            return Ok(PrimitiveValue::Bool(false));
+                        }
+                    }
+                    Ok(PrimitiveValue::Bool(true))
+                })
+                .unwrap_or(Ok(PrimitiveValue::Bool(true))),
+                _ => Err(err_val),
+            },
+            // records are equal when they're the same type with equal fields,
+            // types are the same when they're declared with the same name and fields
+            PrimitiveValue::Record(a) => match other {
+                PrimitiveValue::Record(b) => guard_cycle(&COMPARING, pair(&a, &b), || {
+                    let (a, b) = (a.borrow(), b.borrow());
+                    if a.record_type != b.record_type {
+                        return Ok(PrimitiveValue::Bool(false));
+                    }
+                    for (a, b) in a.values.iter().zip(b.values.iter()) {
+                        if a.clone().is_equal(b.clone())? == PrimitiveValue::Bool(false) {
+                            return Ok(PrimitiveValue::Bool(false));
+                        }
+                    }
+                    Ok(PrimitiveValue::Bool(true))
+                })
+                .unwrap_or(Ok(PrimitiveValue::Bool(true))),
+                _ => Err(err_val),
+            },
+            PrimitiveValue::RecordType(a) => match other {
+                PrimitiveValue::RecordType(b) => Ok(PrimitiveValue::Bool(a == b)),
+                _ => Err(err_val),
+            },
+            // maps are equal when they have the same keys with equal values, in any order
+            PrimitiveValue::Map(a) => match other {
+                PrimitiveValue::Map(b) => guard_cycle(&COMPARING, pair(&a, &b), || {
+                    let (a, b) = (a.borrow(), b.borrow());
+                    if a.len() != b.len() {
+                        return Ok(PrimitiveValue::Bool(false));
+                    }
+                    for (key, a) in a.iter() {
+                        let equal = match b.get(key) {
+                            Some(b) => a.clone().is_equal(b.clone())?,
+                            None => PrimitiveValue::Bool(false),
+                        };
+                        if equal == PrimitiveValue::Bool(false) {
+                            return Ok(PrimitiveValue::Bool(false));
+                        }
+                    }
+                    Ok(PrimitiveValue::Bool(true))
+                })
+                .unwrap_or(Ok(PrimitiveValue::Bool(true))),
+                _ => Err(err_val),
+            },
             _ => Err(err_val),
         }
     }
 
-    #[allow(dead_code)]
-    pub fn assert_false(&self) {
-        PrimitiveValue::check(!self.clone());
+    /// The opposite of `is_equal`, values that can't be compared are still an error
+    pub fn is_not_equal(self, other: Self) -> OperatorValue<Self> {
+        match self.is_equal(other)? {
+            PrimitiveValue::Bool(equal) => Ok(PrimitiveValue::Bool(!equal)),
+            value => Ok(value),
+        }
     }
 
-    #[allow(dead_code)]
-    pub fn assert(&self) {
-        PrimitiveValue::check(self.clone());
+    pub fn negate(self) -> OperatorValue<Self> {
+        match self {
+            PrimitiveValue::Num(value) => Ok(match value.checked_neg() {
+                Some(value) => PrimitiveValue::Num(value),
+                None => PrimitiveValue::from(-BigInt::from(value)),
+            }),
+            PrimitiveValue::BigNum(value) => Ok(PrimitiveValue::from(-value.as_ref())),
+            PrimitiveValue::Float(value) => Ok(PrimitiveValue::Float(-value)),
+            value => Err(OperatorError::ExpectedNumber {
+                operator: UnaryOperator::Negate.to_string(),
+                found: value.to_string(),
+            }),
+        }
+    }
+
+    /// A range between two numbers, the end can be lower than the start for an empty range
+    pub fn range(self, other: Self) -> OperatorValue<Self> {
+        let bound = |value: &PrimitiveValue| match value {
+            PrimitiveValue::Num(value) => Ok(*value),
+            value => Err(OperatorError::ExpectedNumber {
+                operator: BinaryOperator::Range.to_string(),
+                found: value.to_string(),
+            }),
+        };
+        Ok(PrimitiveValue::Range(bound(&self)?, bound(&other)?))
     }
 
-    fn check(a: PrimitiveValue) {
-        if let PrimitiveValue::Bool(result) = a {
-            assert!(result);
-        } else {
-            panic!("Was not given a bool val.");
+    /**
+     * The item of a list at a position counting from 0, or a new list of the
+     * items in a range of positions, ie `xs[1 to 3]`, or the value of a map's key
+     */
+    pub fn get_index(self, index: Self) -> OperatorValue<Self> {
+        let list = match &self {
+            PrimitiveValue::Map(map) => {
+                let key = expect_key(&index)?;
+                let map = map.borrow();
+                return map
+                    .get(key)
+                    .cloned()
+                    .ok_or_else(|| OperatorError::MissingKey {
+                        key: key.to_string(),
+                    });
+            }
+            PrimitiveValue::List(list) => list,
+            value => return Err(not_a_collection("indexing", value)),
+        };
+        let items = list.borrow();
+        match index {
+            PrimitiveValue::Range(start, end) => {
+                // a range that ends before it starts is an empty slice
+                let start = position(&PrimitiveValue::Num(start), items.len() + 1)?;
+                if end < start as i32 {
+                    return Ok(PrimitiveValue::from(vec![]));
+                }
+                let end = position(&PrimitiveValue::Num(end), items.len())?;
+                Ok(PrimitiveValue::from(items[start..=end].to_vec()))
+            }
+            index => Ok(items[position(&index, items.len())?].clone()),
         }
     }
+
+    /// Replace the item of a list at a position, or set the value of a map's key
+    pub fn set_index(&self, index: Self, value: Self) -> OperatorValue<()> {
+        let list = match self {
+            PrimitiveValue::Map(map) => {
+                let key = expect_key(&index)?;
+                map.borrow_mut().insert(key.to_string(), value);
+                return Ok(());
+            }
+            PrimitiveValue::List(list) => list,
+            value => return Err(not_a_collection("indexing", value)),
+        };
+        let mut items = list.borrow_mut();
+        let index = position(&index, items.len())?;
+        items[index] = value;
+        Ok(())
+    }
+
+    /// Add an item to the end of a list
+    pub fn append(&self, value: Self) -> OperatorValue<()> {
+        self.expect_list("append")?.borrow_mut().push(value);
+        Ok(())
+    }
+
+    /// Take the last item off a list
+    pub fn pop(self) -> OperatorValue<Self> {
+        let operator = UnaryOperator::Pop.to_string();
+        let item = self.expect_list(&operator)?.borrow_mut().pop();
+        item.ok_or(OperatorError::EmptyList { operator })
+    }
+
+    /// The number of items in a list, map or range, or characters in a string
+    pub fn length(self) -> OperatorValue<Self> {
+        let length = match &self {
+            PrimitiveValue::Str(value) => value.chars().count(),
+            PrimitiveValue::List(items) => items.borrow().len(),
+            PrimitiveValue::Map(map) => map.borrow().len(),
+            PrimitiveValue::Range(start, end) => return Ok(range_length(*start, *end)),
+            value => return Err(not_a_collection(&UnaryOperator::Length.to_string(), value)),
+        };
+        Ok(PrimitiveValue::from(BigInt::from(length)))
+    }
+
+    /// The value of a record's field, ie `p.x`
+    pub fn get_field(self, field: &str) -> OperatorValue<Self> {
+        match self {
+            PrimitiveValue::Record(record) => record.borrow().get(field),
+            value => Err(not_a_record(field, &value)),
+        }
+    }
+
+    /// Update a record's field, ie `p.x = 1`
+    pub fn set_field(&self, field: &str, value: Self) -> OperatorValue<()> {
+        match self {
+            PrimitiveValue::Record(record) => record.borrow_mut().set(field, value),
+            record => Err(not_a_record(field, record)),
+        }
+    }
+
+    /// Whether a map has a key
+    pub fn has(self, key: Self) -> OperatorValue<Self> {
+        let map = self.expect_map(&BinaryOperator::Has.to_string())?;
+        let has = map.borrow().contains_key(expect_key(&key)?);
+        Ok(PrimitiveValue::Bool(has))
+    }
+
+    /// Take a key out of a map, giving back its value
+    pub fn remove(&self, key: Self) -> OperatorValue<Self> {
+        let map = self.expect_map("remove")?;
+        let key = expect_key(&key)?;
+        let value = map.borrow_mut().remove(key);
+        value.ok_or_else(|| OperatorError::MissingKey {
+            key: key.to_string(),
+        })
+    }
+
+    fn expect_map(&self, operator: &str) -> OperatorValue<&Rc<RefCell<Map>>> {
+        match self {
+            PrimitiveValue::Map(map) => Ok(map),
+            value => Err(OperatorError::ExpectedMap {
+                operator: operator.to_string(),
+                found: value.to_string(),
+            }),
+        }
+    }
+
+    fn expect_list(&self, operator: &str) -> OperatorValue<&Rc<RefCell<Vec<PrimitiveValue>>>> {
+        match self {
+            PrimitiveValue::List(items) => Ok(items),
+            value => Err(OperatorError::ExpectedList {
+                operator: operator.to_string(),
+                found: value.to_string(),
+            }),
+        }
+    }
+
+    /// Both sides have to be bools, short circuiting is up to whatever runs the program
+    pub fn and(self, other: Self) -> OperatorValue<Self> {
+        let a = self.expect_bool(BinaryOperator::And)?;
+        let b = other.expect_bool(BinaryOperator::And)?;
+        Ok(PrimitiveValue::Bool(a && b))
+    }
+
+    pub fn or(self, other: Self) -> OperatorValue<Self> {
+        let a = self.expect_bool(BinaryOperator::Or)?;
+        let b = other.expect_bool(BinaryOperator::Or)?;
+        Ok(PrimitiveValue::Bool(a || b))
+    }
+
+    /// The value of a bool operand of `operator`
+    pub fn expect_bool(&self, operator: impl std::fmt::Display) -> OperatorValue<bool> {
+        match self {
+            PrimitiveValue::Bool(value) => Ok(*value),
+            value => Err(OperatorError::ExpectedBool {
+                operator: operator.to_string(),
+                found: value.to_string(),
+            }),
+        }
+    }
+
+    pub fn is_less(self, other: Self) -> OperatorValue<Self> {
+        let ordering = self.ordering(&other)?;
+        Ok(PrimitiveValue::Bool(ordering == Ordering::Less))
+    }
+
+    pub fn is_less_or_equal(self, other: Self) -> OperatorValue<Self> {
+        let ordering = self.ordering(&other)?;
+        Ok(PrimitiveValue::Bool(ordering != Ordering::Greater))
+    }
+
+    pub fn is_greater(self, other: Self) -> OperatorValue<Self> {
+        let ordering = self.ordering(&other)?;
+        Ok(PrimitiveValue::Bool(ordering == Ordering::Greater))
+    }
+
+    pub fn is_greater_or_equal(self, other: Self) -> OperatorValue<Self> {
+        let ordering = self.ordering(&other)?;
+        Ok(PrimitiveValue::Bool(ordering != Ordering::Less))
+    }
+
+    /// Numbers of any kind order by value, strings order alphabetically by character
+    fn ordering(&self, other: &Self) -> OperatorValue<Ordering> {
+        match (self, other) {
+            (PrimitiveValue::Str(a), PrimitiveValue::Str(b)) => return Ok(a.cmp(b)),
+            // small numbers are compared without making big ones of them
+            (PrimitiveValue::Num(a), PrimitiveValue::Num(b)) => return Ok(a.cmp(b)),
+            _ => (),
+        }
+        if let (Some(a), Some(b)) = (self.as_big(), other.as_big()) {
+            return Ok(a.cmp(&b));
+        }
+        match (self.as_float(), other.as_float()) {
+            (Some(a), Some(b)) => a
+                .partial_cmp(&b)
+                .ok_or_else(|| OperatorError::not_implemented(self, other)),
+            _ => Err(OperatorError::not_implemented(self, other)),
+        }
+    }
+}
+
+/// Where `index` points in a list of `length` items, anything outside of it is an error
+fn position(index: &PrimitiveValue, length: usize) -> OperatorValue<usize> {
+    match index {
+        PrimitiveValue::Num(value) if *value >= 0 && (*value as usize) < length => {
+            Ok(*value as usize)
+        }
+        PrimitiveValue::Num(_) | PrimitiveValue::BigNum(_) => {
+            Err(OperatorError::IndexOutOfBounds {
+                index: index.to_string(),
+                length,
+            })
+        }
+        value => Err(OperatorError::ExpectedNumber {
+            operator: "indexing".to_string(),
+            found: value.to_string(),
+        }),
+    }
+}
+
+/// The string a map key has to be
+pub fn expect_key(key: &PrimitiveValue) -> OperatorValue<&str> {
+    match key {
+        PrimitiveValue::Str(key) => Ok(key),
+        key => Err(OperatorError::ExpectedString {
+            operator: "a map key".to_string(),
+            found: key.to_string(),
+        }),
+    }
 }
 
-impl std::string::ToString for PrimitiveValue {
-    fn to_string(&self) -> String {
+fn not_a_record(field: &str, value: &PrimitiveValue) -> OperatorError {
+    OperatorError::ExpectedRecord {
+        field: field.to_string(),
+        found: value.to_string(),
+    }
+}
+
+fn not_a_collection(operator: &str, value: &PrimitiveValue) -> OperatorError {
+    OperatorError::ExpectedCollection {
+        operator: operator.to_string(),
+        found: value.to_string(),
+    }
+}
+
+/// How a value reads inside a list or map, strings are quoted so `["1"]` and `[1]` differ
+fn write_item(f: &mut std::fmt::Formatter<'_>, item: &PrimitiveValue) -> std::fmt::Result {
+    match item {
+        PrimitiveValue::Str(value) => write!(f, "\"{}\"", value),
+        item => write!(f, "{}", item),
+    }
+}
+
+fn range_length(start: i32, end: i32) -> PrimitiveValue {
+    let length = (end as i64 - start as i64 + 1).max(0);
+    PrimitiveValue::from(BigInt::from(length))
+}
+
+/**
+ * Format a float so it reads back as the same float: whole floats keep a
+ * `.0` so they don't read back as numbers, very large and very small ones
+ * use exponents, ie 3.0, 0.1, 1e21 and 1.5e-7
+ */
+pub fn format_float(value: f64) -> String {
+    let magnitude = value.abs();
+    if magnitude != 0.0 && !(1e-5..1e16).contains(&magnitude) {
+        format!("{:e}", value)
+    } else if value.fract() == 0.0 {
+        format!("{:.1}", value)
+    } else {
+        format!("{}", value)
+    }
+}
+
+impl std::fmt::Display for PrimitiveValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            PrimitiveValue::Bool(val) => val.to_string(),
-            PrimitiveValue::Str(val) => val.to_string(),
-            PrimitiveValue::Num(val) => val.to_string(),
-            PrimitiveValue::Function(block) => format!("({:?}):{{{:?}}}", block.args, block.body),
+            PrimitiveValue::Bool(val) => write!(f, "{}", val),
+            PrimitiveValue::Str(val) => write!(f, "{}", val),
+            PrimitiveValue::Num(val) => write!(f, "{}", val),
+            PrimitiveValue::BigNum(val) => write!(f, "{}", val),
+            PrimitiveValue::Float(val) => f.write_str(&format_float(*val)),
+            PrimitiveValue::Range(start, end) => write!(f, "{} to {}", start, end),
+            // a list, map or record inside itself prints as `...`
+            PrimitiveValue::List(items) => guard_cycle(&PRINTING, pointer(items), || {
+                f.write_str("[")?;
+                for (i, item) in items.borrow().iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write_item(f, item)?;
+                }
+                f.write_str("]")
+            })
+            .unwrap_or_else(|| f.write_str("[...]")),
+            // entries print in insertion order so the same map always prints the same way
+            PrimitiveValue::Map(map) => guard_cycle(&PRINTING, pointer(map), || {
+                let map = map.borrow();
+                if map.is_empty() {
+                    return f.write_str("[:]");
+                }
+                f.write_str("[")?;
+                for (i, (key, value)) in map.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "\"{}\": ", key)?;
+                    write_item(f, value)?;
+                }
+                f.write_str("]")
+            })
+            .unwrap_or_else(|| f.write_str("[...]")),
+            PrimitiveValue::RecordType(record_type) => match &record_type.variant_of {
+                Some(enum_name) => write!(f, "<variant {} of {}>", record_type.name, enum_name),
+                None => write!(f, "<record {}>", record_type.name),
+            },
+            PrimitiveValue::Record(shared) => guard_cycle(&PRINTING, pointer(shared), || {
+                let record = shared.borrow();
+                // variants without fields are just their name
+                if record.values.is_empty() && record.record_type.variant_of.is_some() {
+                    return f.write_str(&record.record_type.name);
+                }
+                write!(f, "{}(", record.record_type.name)?;
+                for (i, ((field, _), value)) in record
+                    .record_type
+                    .fields
+                    .iter()
+                    .zip(&record.values)
+                    .enumerate()
+                {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: ", field)?;
+                    write_item(f, value)?;
+                }
+                f.write_str(")")
+            })
+            .unwrap_or_else(|| write!(f, "{}(...)", shared.borrow().record_type.name)),
+            PrimitiveValue::Function(block) => write!(f, "({:?}):{{{:?}}}", block.args, block.body),
+            PrimitiveValue::Closure(closure) => {
+                write!(f, "({:?}):{{{:?}}}", closure.proto.args, closure.proto.body)
+            }
+            PrimitiveValue::Native(function) => write!(f, "<native fn {}>", function.name),
         }
     }
 }
@@ -139,6 +808,40 @@ mod tests {
         assert!(a.add(b).is_err());
     }
 
+    #[test]
+    fn lists() {
+        let list = |items: &[i32]| {
+            PrimitiveValue::from(
+                items
+                    .iter()
+                    .map(|item| PrimitiveValue::Num(*item))
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let joined = list(&[1, 2]).add(list(&[3])).unwrap();
+        assert_eq!(joined, list(&[1, 2, 3]));
+        assert_eq!(
+            joined.clone().get_index(PrimitiveValue::Range(1, 2)),
+            Ok(list(&[2, 3]))
+        );
+        assert_eq!(
+            joined.clone().get_index(PrimitiveValue::Num(3)),
+            Err(OperatorError::IndexOutOfBounds {
+                index: "3".to_string(),
+                length: 3
+            })
+        );
+
+        // changes show up through every copy of the list
+        let copy = joined.clone();
+        copy.set_index(PrimitiveValue::Num(0), PrimitiveValue::Str("a".to_string()))
+            .unwrap();
+        copy.append(PrimitiveValue::Bool(true)).unwrap();
+        assert_eq!(joined.to_string(), "[\"a\", 2, 3, true]");
+        assert_eq!(joined.clone().pop(), Ok(PrimitiveValue::Bool(true)));
+        assert_eq!(joined.length(), Ok(PrimitiveValue::Num(3)));
+    }
+
     #[test]
     fn can_multiply() {
         let a = PrimitiveValue::Num(2);
@@ -160,20 +863,274 @@ mod tests {
     fn is_eq() {
         let a = PrimitiveValue::Num(2);
         let b = PrimitiveValue::Num(3);
-        a.clone().is_equal(b.clone()).unwrap().assert_false();
-        a.clone().is_equal(a.clone()).unwrap().assert();
+        assert_eq!(
+            a.clone().is_equal(b.clone()),
+            Ok(PrimitiveValue::Bool(false))
+        );
+        assert_eq!(a.clone().is_equal(a), Ok(PrimitiveValue::Bool(true)));
 
         let a = PrimitiveValue::Str("a".to_string());
         let b = PrimitiveValue::Str("b".to_string());
-        a.is_equal(b).unwrap().assert_false();
+        assert_eq!(a.is_equal(b), Ok(PrimitiveValue::Bool(false)));
 
         let a = PrimitiveValue::Bool(true);
         let b = PrimitiveValue::Bool(false);
-        a.is_equal(b).unwrap().assert_false();
+        assert_eq!(a.is_equal(b), Ok(PrimitiveValue::Bool(false)));
+    }
+
+    #[test]
+    fn can_divide() {
+        let result = PrimitiveValue::Num(7).divide(PrimitiveValue::Num(2));
+        assert_eq!(result, Ok(PrimitiveValue::Num(3)));
+        let result = PrimitiveValue::Num(-7).modulo(PrimitiveValue::Num(2));
+        assert_eq!(result, Ok(PrimitiveValue::Num(-1)));
+    }
+
+    #[test]
+    fn divide_by_zero() {
+        let result = PrimitiveValue::Num(1).divide(PrimitiveValue::Num(0));
+        assert_eq!(result, Err(OperatorError::DivisionByZero));
+        let result = PrimitiveValue::Num(1).modulo(PrimitiveValue::Num(0));
+        assert_eq!(result, Err(OperatorError::DivisionByZero));
+    }
+
+    #[test]
+    fn bignum_promotion() {
+        let max = PrimitiveValue::Num(i32::MAX);
+        let min = PrimitiveValue::Num(i32::MIN);
+        let big = max.clone().add(PrimitiveValue::Num(1)).unwrap();
+        assert_eq!(big.to_string(), "2147483648");
+        assert_eq!(
+            min.clone()
+                .subtract(PrimitiveValue::Num(1))
+                .unwrap()
+                .to_string(),
+            "-2147483649"
+        );
+        assert_eq!(
+            min.divide(PrimitiveValue::Num(-1)).unwrap().to_string(),
+            "2147483648"
+        );
+        assert_eq!(
+            big.clone().multiply(big.clone()).unwrap().to_string(),
+            "4611686018427387904"
+        );
+        // results that fit go back to being numbers
+        assert_eq!(
+            big.clone().subtract(PrimitiveValue::Num(1)),
+            Ok(max.clone())
+        );
+        assert_eq!(
+            big.clone().modulo(PrimitiveValue::Num(10)),
+            Ok(PrimitiveValue::Num(8))
+        );
+        assert_eq!(
+            big.clone().is_equal(big.clone()),
+            Ok(PrimitiveValue::Bool(true))
+        );
+        assert_eq!(big.clone().is_equal(max), Ok(PrimitiveValue::Bool(false)));
+        assert_eq!(
+            big.add(PrimitiveValue::Float(0.5)),
+            Ok(PrimitiveValue::Float(2147483648.5))
+        );
+    }
+
+    #[test]
+    fn inequality_of_every_pairing() {
+        let options = crate::Options {
+            backend: crate::Backend::Bytecode,
+            ..crate::Options::default()
+        };
+        let closure = crate::Engine::with_options(options)
+            .eval("define f to be { return 1; } given (); f")
+            .unwrap();
+        assert!(matches!(closure, PrimitiveValue::Closure(_)));
+        let point = Rc::new(RecordType {
+            name: "Point".to_string(),
+            fields: vec![("x".to_string(), "number".to_string())],
+            variant_of: None,
+        });
+
+        let values = [
+            PrimitiveValue::Str("1".to_string()),
+            PrimitiveValue::Num(1),
+            PrimitiveValue::from(BigInt::from(1) << 40),
+            PrimitiveValue::Float(1.0),
+            PrimitiveValue::Bool(true),
+            PrimitiveValue::Function(FunctionBody {
+                body: vec![],
+                args: vec![],
+                scope: crate::types::symbol_store::SymbolStore::new().into(),
+            }),
+            PrimitiveValue::Native(NativeFunction::new("f", 0, |_| Ok(None))),
+            closure,
+            PrimitiveValue::Range(1, 2),
+            PrimitiveValue::from(vec![PrimitiveValue::Num(1)]),
+            PrimitiveValue::from(
+                vec![("1".to_string(), PrimitiveValue::Num(1))]
+                    .into_iter()
+                    .collect::<Map>(),
+            ),
+            PrimitiveValue::RecordType(point.clone()),
+            point.construct(vec![PrimitiveValue::Num(1)]).unwrap(),
+        ];
+        let kind = |value: &PrimitiveValue| match value {
+            PrimitiveValue::Num(_) | PrimitiveValue::BigNum(_) | PrimitiveValue::Float(_) => {
+                "number"
+            }
+            PrimitiveValue::Str(_) => "string",
+            PrimitiveValue::Bool(_) => "bool",
+            PrimitiveValue::Range(..) => "range",
+            PrimitiveValue::List(_) => "list",
+            PrimitiveValue::Map(_) => "map",
+            PrimitiveValue::RecordType(_) => "record type",
+            PrimitiveValue::Record(_) => "record",
+            PrimitiveValue::Function(_)
+            | PrimitiveValue::Closure(_)
+            | PrimitiveValue::Native(_) => "function",
+        };
+
+        for (i, a) in values.iter().enumerate() {
+            for (j, b) in values.iter().enumerate() {
+                let result = a.clone().is_not_equal(b.clone());
+                if kind(a) != kind(b) || kind(a) == "function" {
+                    assert_eq!(result, Err(OperatorError::not_implemented(a, b)));
+                    continue;
+                }
+                // 1 and 1.0 are the same number
+                let same = i == j || (i == 1 && j == 3) || (i == 3 && j == 1);
+                assert_eq!(result, Ok(PrimitiveValue::Bool(!same)), "{} != {}", a, b);
+                assert_eq!(
+                    result.and_then(|value| !value),
+                    a.clone().is_equal(b.clone())
+                );
+            }
+        }
+
+        let other = PrimitiveValue::Str("2".to_string());
+        assert_eq!(
+            values[0].clone().is_not_equal(other),
+            Ok(PrimitiveValue::Bool(true))
+        );
+        assert_eq!(
+            PrimitiveValue::Bool(true).is_not_equal(PrimitiveValue::Bool(false)),
+            Ok(PrimitiveValue::Bool(true))
+        );
+    }
+
+    #[test]
+    fn ordering() {
+        let yes = Ok(PrimitiveValue::Bool(true));
+        let no = Ok(PrimitiveValue::Bool(false));
+        let big = PrimitiveValue::from(BigInt::from(i64::MAX));
+        assert_eq!(PrimitiveValue::Num(1).is_less(PrimitiveValue::Num(2)), yes);
+        assert_eq!(PrimitiveValue::Num(2).is_less(PrimitiveValue::Num(2)), no);
+        assert_eq!(
+            PrimitiveValue::Num(2).is_less_or_equal(PrimitiveValue::Num(2)),
+            yes
+        );
+        assert_eq!(PrimitiveValue::Num(3).is_greater(big.clone()), no);
+        assert_eq!(big.is_greater_or_equal(PrimitiveValue::Float(1.5)), yes);
+        assert_eq!(
+            PrimitiveValue::Float(1.5).is_greater(PrimitiveValue::Num(1)),
+            yes
+        );
+
+        let apple = PrimitiveValue::Str("apple".to_string());
+        let banana = PrimitiveValue::Str("banana".to_string());
+        assert_eq!(apple.clone().is_less(banana.clone()), yes);
+        assert_eq!(banana.is_less_or_equal(apple.clone()), no);
+
+        assert_eq!(
+            apple.is_less(PrimitiveValue::Num(1)),
+            Err(OperatorError::NotImplemented {
+                a: "apple".to_string(),
+                b: "1".to_string()
+            })
+        );
+        assert!(PrimitiveValue::Bool(true)
+            .is_greater(PrimitiveValue::Bool(false))
+            .is_err());
+    }
+
+    #[test]
+    fn float_promotion() {
+        let half = PrimitiveValue::Float(0.5);
+        assert_eq!(
+            PrimitiveValue::Num(3).add(half.clone()),
+            Ok(PrimitiveValue::Float(3.5))
+        );
+        assert_eq!(
+            half.clone().multiply(PrimitiveValue::Num(4)),
+            Ok(PrimitiveValue::Float(2.0))
+        );
+        assert_eq!(
+            PrimitiveValue::Num(1).divide(half.clone()),
+            Ok(PrimitiveValue::Float(2.0))
+        );
+        assert_eq!(
+            PrimitiveValue::Float(5.5).modulo(PrimitiveValue::Num(2)),
+            Ok(PrimitiveValue::Float(1.5))
+        );
+        assert_eq!(
+            PrimitiveValue::Num(2).is_equal(PrimitiveValue::Float(2.0)),
+            Ok(PrimitiveValue::Bool(true))
+        );
+
+        assert_eq!(
+            half.clone().divide(PrimitiveValue::Float(0.0)),
+            Err(OperatorError::DivisionByZero)
+        );
+        assert!(PrimitiveValue::Float(1e308)
+            .multiply(PrimitiveValue::Num(10))
+            .is_err());
+        assert!(half.add(PrimitiveValue::Bool(true)).is_err());
+    }
+
+    #[test]
+    fn float_formatting_round_trips() {
+        let cases = [
+            (3.0, "3.0"),
+            (-0.5, "-0.5"),
+            (0.1 + 0.2, "0.30000000000000004"),
+            (1e21, "1e21"),
+            (1.5e-7, "1.5e-7"),
+            (123456.789, "123456.789"),
+        ];
+        for (value, text) in cases {
+            assert_eq!(PrimitiveValue::Float(value).to_string(), text);
+            let tokens =
+                crate::lexer::tokenize_file(text.trim_start_matches('-').to_string(), "test.eye")
+                    .unwrap();
+            assert_eq!(
+                tokens[0].node,
+                crate::types::token::Token::Float(value.abs())
+            );
+        }
     }
 
     #[test]
     fn not() {
-        (!PrimitiveValue::Bool(false)).assert();
+        assert_eq!(!PrimitiveValue::Bool(false), Ok(PrimitiveValue::Bool(true)));
+        assert_eq!(
+            (!PrimitiveValue::Num(0)).unwrap_err().to_string(),
+            "Expected a bool for not, found 0."
+        );
+    }
+
+    #[test]
+    fn and_or() {
+        let t = PrimitiveValue::Bool(true);
+        let f = PrimitiveValue::Bool(false);
+        assert_eq!(t.clone().and(f.clone()), Ok(f.clone()));
+        assert_eq!(t.clone().or(f.clone()), Ok(t.clone()));
+        assert_eq!(
+            t.and(PrimitiveValue::Str("yes".to_string())),
+            Err(OperatorError::ExpectedBool {
+                operator: "and".to_string(),
+                found: "yes".to_string()
+            })
+        );
+        assert!(PrimitiveValue::Num(1).or(f).is_err());
     }
 }
@@ -0,0 +1,136 @@
+use crate::types::error::OperatorError;
+use crate::types::primitive_value::PrimitiveValue;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A record declared with `define Point to be record { x: number, y: number }`
+#[derive(Debug, PartialEq)]
+pub struct RecordType {
+    pub name: String,
+    /// each field's name and the name of its type
+    pub fields: Vec<(String, String)>,
+    /// the enum a variant belongs to, variants are records with untyped fields
+    pub variant_of: Option<String>,
+}
+
+/// A value of a record type, its fields are in the order the type declares them
+#[derive(Debug, PartialEq)]
+pub struct Record {
+    pub record_type: Rc<RecordType>,
+    pub values: Vec<PrimitiveValue>,
+}
+
+impl RecordType {
+    /**
+     * What a variant of `define Shape to be one of ...` is bound to: a type to
+     * call like a record's, or the variant's only value when it has no fields
+     */
+    pub fn variant(enum_name: &str, name: &str, fields: &[String]) -> PrimitiveValue {
+        let variant = Rc::new(RecordType {
+            name: name.to_string(),
+            fields: fields
+                .iter()
+                .map(|field| (field.to_string(), "any".to_string()))
+                .collect(),
+            variant_of: Some(enum_name.to_string()),
+        });
+        if fields.is_empty() {
+            PrimitiveValue::Record(Rc::new(RefCell::new(Record {
+                record_type: variant,
+                values: vec![],
+            })))
+        } else {
+            PrimitiveValue::RecordType(variant)
+        }
+    }
+
+    /// Make a record from a value for every field, in order
+    pub fn construct(
+        self: &Rc<Self>,
+        values: Vec<PrimitiveValue>,
+    ) -> Result<PrimitiveValue, OperatorError> {
+        for ((field, type_name), value) in self.fields.iter().zip(&values) {
+            self.check_field(field, type_name, value)?;
+        }
+        Ok(PrimitiveValue::Record(Rc::new(RefCell::new(Record {
+            record_type: self.clone(),
+            values,
+        }))))
+    }
+
+    fn position(&self, field: &str) -> Result<usize, OperatorError> {
+        self.fields
+            .iter()
+            .position(|(name, _)| name == field)
+            .ok_or_else(|| OperatorError::MissingField {
+                record: self.name.clone(),
+                field: field.to_string(),
+            })
+    }
+
+    fn check_field(
+        &self,
+        field: &str,
+        type_name: &str,
+        value: &PrimitiveValue,
+    ) -> Result<(), OperatorError> {
+        if has_type(value, type_name) {
+            Ok(())
+        } else {
+            Err(OperatorError::FieldType {
+                record: self.name.clone(),
+                field: field.to_string(),
+                expected: type_name.to_string(),
+                found: value.to_string(),
+            })
+        }
+    }
+}
+
+impl Record {
+    pub fn get(&self, field: &str) -> Result<PrimitiveValue, OperatorError> {
+        let position = self.record_type.position(field)?;
+        Ok(self.values[position].clone())
+    }
+
+    /// Update a field, the value has to have the field's type
+    pub fn set(&mut self, field: &str, value: PrimitiveValue) -> Result<(), OperatorError> {
+        let position = self.record_type.position(field)?;
+        let (_, type_name) = &self.record_type.fields[position];
+        self.record_type.check_field(field, type_name, &value)?;
+        self.values[position] = value;
+        Ok(())
+    }
+}
+
+/// Types every record can use for its fields, `has_type` says what fits them
+pub const BUILT_IN_TYPES: [&str; 9] = [
+    "any", "number", "float", "string", "bool", "range", "list", "map", "function",
+];
+
+/**
+ * Whether `value` fits the type called `type_name`, a number is any kind of
+ * number and any name that isn't built in is a record type or an enum
+ */
+fn has_type(value: &PrimitiveValue, type_name: &str) -> bool {
+    match (type_name, value) {
+        ("any", _) => true,
+        ("number", value) => value.as_float().is_some(),
+        ("float", PrimitiveValue::Float(_))
+        | ("string", PrimitiveValue::Str(_))
+        | ("bool", PrimitiveValue::Bool(_))
+        | ("range", PrimitiveValue::Range(..))
+        | ("list", PrimitiveValue::List(_))
+        | ("map", PrimitiveValue::Map(_)) => true,
+        ("function", value) => matches!(
+            value,
+            PrimitiveValue::Function(_) | PrimitiveValue::Closure(_) | PrimitiveValue::Native(_)
+        ),
+        (name, PrimitiveValue::Record(record)) => {
+            let record_type = &record.borrow().record_type;
+            record_type.name == name || record_type.variant_of.as_deref() == Some(name)
+        }
+        _ => false,
+    }
+}
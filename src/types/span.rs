@@ -0,0 +1,90 @@
+use std::rc::Rc;
+
+/// A 1-based line/column pair inside a source file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    pub fn start() -> Location {
+        Location { line: 1, column: 1 }
+    }
+}
+
+/// The region of a source file a token or AST node was read from.
+/// `end` points one column past the last character of the region.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Span {
+    pub file: Option<Rc<str>>,
+    pub start: Location,
+    pub end: Location,
+}
+
+impl Span {
+    pub fn new(file: Option<Rc<str>>, start: Location, end: Location) -> Span {
+        Span { file, start, end }
+    }
+
+    /// Span covering everything from the start of `self` to the end of `other`
+    pub fn to(&self, other: &Span) -> Span {
+        Span {
+            file: self.file.clone(),
+            start: self.start,
+            end: other.end,
+        }
+    }
+
+    /// Spans built with `Default` (ie for synthesized nodes) point nowhere
+    pub fn is_empty(&self) -> bool {
+        self.start.line == 0
+    }
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+        }
+        write!(f, "{}:{}", self.start.line, self.start.column)
+    }
+}
+
+/// A value tagged with the span it came from.
+/// Spans are positional metadata, so like `syn` identifiers they don't take
+/// part in equality: two nodes are equal if their contents are equal.
+#[derive(Clone)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(node: T, span: Span) -> Spanned<T> {
+        Spanned { node, span }
+    }
+}
+
+impl<T> From<T> for Spanned<T> {
+    fn from(node: T) -> Spanned<T> {
+        Spanned {
+            node,
+            span: Span::default(),
+        }
+    }
+}
+
+impl<T: PartialEq> PartialEq for Spanned<T> {
+    fn eq(&self, other: &Spanned<T>) -> bool {
+        self.node == other.node
+    }
+}
+
+impl<T: Eq> Eq for Spanned<T> {}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for Spanned<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.node)
+    }
+}
@@ -1,9 +1,221 @@
 use crate::types::primitive_value::PrimitiveValue;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::{Rc, Weak};
 
 pub type Identifier = String;
-pub type SymbolStore = HashMap<Identifier, PrimitiveValue>;
+
+struct Scope {
+    values: HashMap<Identifier, PrimitiveValue>,
+    parent: Option<SymbolStore>,
+}
+
+/**
+ * A lexical scope linked to the scope it was created in.
+ *
+ * - `define` always binds in the current scope, shadowing any outer symbol
+ * - reading a symbol walks outwards until it's found
+ * - assigning with `=` updates the nearest scope the symbol is bound in
+ *
+ * Cloning a store is cheap, clones share the same scope.
+ *
+ * Functions keep the scope they're defined in alive so they can be returned
+ * and still see it. While a function is bound in that same scope it only holds
+ * it weakly, otherwise the two would keep each other alive, and reading the
+ * function back out makes the link strong again.
+ */
+#[derive(Clone)]
+pub struct SymbolStore(Rc<RefCell<Scope>>);
+
+impl SymbolStore {
+    pub fn new() -> SymbolStore {
+        SymbolStore::with_parent(None)
+    }
+
+    fn with_parent(parent: Option<SymbolStore>) -> SymbolStore {
+        SymbolStore(Rc::new(RefCell::new(Scope {
+            values: HashMap::new(),
+            parent,
+        })))
+    }
+
+    /// A new scope nested inside this one
+    pub fn child(&self) -> SymbolStore {
+        SymbolStore::with_parent(Some(self.clone()))
+    }
+
+    pub fn get(&self, identifier: &str) -> Option<PrimitiveValue> {
+        let scope = self.0.borrow();
+        match scope.values.get(identifier) {
+            Some(value) => Some(ScopeLink::upgrade(value.clone())),
+            None => scope.parent.as_ref()?.get(identifier),
+        }
+    }
+
+    /// Symbols bound in this scope, not its parents, sorted by name
+    pub fn bindings(&self) -> Vec<(Identifier, PrimitiveValue)> {
+        let scope = self.0.borrow();
+        let mut bindings: Vec<(Identifier, PrimitiveValue)> = scope
+            .values
+            .iter()
+            .map(|(name, value)| (name.clone(), ScopeLink::upgrade(value.clone())))
+            .collect();
+        bindings.sort_by(|a, b| a.0.cmp(&b.0));
+        bindings
+    }
+
+    /// Bind `identifier` in this scope
+    pub fn define(&self, identifier: Identifier, value: PrimitiveValue) {
+        let value = ScopeLink::downgrade(value, self);
+        self.0.borrow_mut().values.insert(identifier, value);
+    }
+
+    /// Update the nearest binding of `identifier`, returns false if there isn't one
+    pub fn assign(&self, identifier: &str, value: PrimitiveValue) -> bool {
+        let mut scope = self.0.borrow_mut();
+        if let Some(current) = scope.values.get_mut(identifier) {
+            *current = ScopeLink::downgrade(value, self);
+            return true;
+        }
+        match &scope.parent {
+            Some(parent) => parent.assign(identifier, value),
+            None => false,
+        }
+    }
+}
+
+impl Default for SymbolStore {
+    fn default() -> SymbolStore {
+        SymbolStore::new()
+    }
+}
+
+// scopes are identities, functions stored in a scope also point back to it
+impl PartialEq for SymbolStore {
+    fn eq(&self, other: &SymbolStore) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl std::fmt::Debug for SymbolStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let scope = self.0.borrow();
+        let mut names: Vec<&Identifier> = scope.values.keys().collect();
+        names.sort();
+        write!(f, "Scope {:?}", names)
+    }
+}
+
+/// A function's link to the scope it was defined in
+#[derive(Clone)]
+pub struct ScopeLink(Link);
+
+#[derive(Clone)]
+enum Link {
+    Strong(SymbolStore),
+    /// the function is bound in the scope, which is alive whenever it's read
+    Weak(Weak<RefCell<Scope>>),
+}
+
+impl ScopeLink {
+    pub fn scope(&self) -> SymbolStore {
+        match &self.0 {
+            Link::Strong(scope) => scope.clone(),
+            Link::Weak(scope) => SymbolStore(
+                scope
+                    .upgrade()
+                    .expect("a function's scope is alive while the function is bound in it"),
+            ),
+        }
+    }
+
+    /// Weaken the link of a function about to be bound in the scope it links to
+    fn downgrade(value: PrimitiveValue, scope: &SymbolStore) -> PrimitiveValue {
+        match value {
+            PrimitiveValue::Function(mut function) => {
+                if let Link::Strong(defined_in) = &function.scope.0 {
+                    if defined_in == scope {
+                        function.scope = ScopeLink(Link::Weak(Rc::downgrade(&scope.0)));
+                    }
+                }
+                PrimitiveValue::Function(function)
+            }
+            value => value,
+        }
+    }
+
+    /// Strengthen the link of a function read out of a scope so it can outlive it
+    fn upgrade(value: PrimitiveValue) -> PrimitiveValue {
+        match value {
+            PrimitiveValue::Function(mut function) => {
+                function.scope = ScopeLink(Link::Strong(function.scope.scope()));
+                PrimitiveValue::Function(function)
+            }
+            value => value,
+        }
+    }
+}
+
+impl From<SymbolStore> for ScopeLink {
+    fn from(scope: SymbolStore) -> ScopeLink {
+        ScopeLink(Link::Strong(scope))
+    }
+}
+
+impl PartialEq for ScopeLink {
+    fn eq(&self, other: &ScopeLink) -> bool {
+        self.scope() == other.scope()
+    }
+}
+
+impl std::fmt::Debug for ScopeLink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.scope())
+    }
+}
 
 pub fn create_symbol_store() -> SymbolStore {
-    HashMap::new()
+    SymbolStore::new()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::options::Options;
+
+    fn run(source: &str, symbols: &SymbolStore) {
+        let tokens = crate::lexer::tokenize_file(source.to_string(), "test.eye").unwrap();
+        let program = crate::parser::build_program(tokens).unwrap();
+        crate::interpreter::interpret(program, symbols.clone(), &Options::debug()).unwrap();
+    }
+
+    #[test]
+    fn calls_free_their_scope() {
+        let globals = SymbolStore::new();
+        run(
+            "define f to be {
+                define g to be { return 1; };
+                return g();
+            };
+            define make to be {
+                define n to be 10;
+                define get to be { return n; };
+                return get;
+            };",
+            &globals,
+        );
+        let count = Rc::strong_count(&globals.0);
+
+        // the call's scope holds g, which links back to it
+        run("run f;", &globals);
+        assert_eq!(Rc::strong_count(&globals.0), count);
+
+        // a returned function keeps the call's scope until it's dropped
+        run("define got to be run make;", &globals);
+        assert_eq!(Rc::strong_count(&globals.0), count + 1);
+        run("define ten to be got();", &globals);
+        assert_eq!(globals.get("ten"), Some(PrimitiveValue::Num(10)));
+        run("got = 0;", &globals);
+        assert_eq!(Rc::strong_count(&globals.0), count);
+    }
 }
@@ -1,59 +1,142 @@
 use crate::types::binary_operator::BinaryOperator;
+use crate::types::primitive_value::format_float;
+use num_bigint::BigInt;
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, PartialEq)]
 pub enum Token {
     Symbol(String),
-    Type(String),
     Str(String),
     Number(i32),
+    /// an integer literal too big for `Number`
+    BigNumber(BigInt),
+    Float(f64),
     Bool(bool),
     Operator(BinaryOperator),
+    Not,
+    LengthOf,
+    PopFrom,
+    Append,
+    Remove,
+    From,
+    Record,
+    OneOf,
+    Match,
     LParen,
     RParen,
     LBrace,
     RBrace,
+    LBracket,
+    RBracket,
     Comma,
+    Colon,
+    Dot,
     Return,
     Print,
     If,
     Else,
     Do,
     Times,
+    While,
+    Until,
+    Break,
+    Continue,
+    ForEach,
+    In,
     Throw,
     Given,
     Define,
     Semicolon,
     ToBe,
     Run,
+    EOF,
 }
 
 impl Token {
     pub fn tuple(&self) -> (&Token, String) {
         match self {
             Token::Symbol(value) => (self, value.to_string()),
-            Token::Type(value) => (self, value.to_string()),
             Token::Number(value) => (self, value.to_string()),
+            Token::BigNumber(value) => (self, value.to_string()),
+            Token::Float(value) => (self, format_float(*value)),
             Token::Operator(value) => (self, value.to_string()),
             Token::Bool(value) => (self, value.to_string()),
             Token::Str(value) => (self, value.to_string()),
             _ => (self, "".to_string()),
         }
     }
+
+    /// How the token reads in error messages, ie "number `10`" or "`to be`"
+    pub fn describe(&self) -> String {
+        // every token without a value reads as its text, listed so new ones aren't missed
+        let text = match self {
+            Token::Symbol(value) => return format!("symbol `{}`", value),
+            Token::Str(value) => return format!("string \"{}\"", value),
+            Token::Number(value) => return format!("number `{}`", value),
+            Token::BigNumber(value) => return format!("number `{}`", value),
+            Token::Float(value) => return format!("number `{}`", format_float(*value)),
+            Token::Bool(value) => return format!("`{}`", value),
+            Token::Operator(value) => return format!("`{}`", value),
+            Token::EOF => return "end of input".to_string(),
+            Token::LParen => "(",
+            Token::RParen => ")",
+            Token::LBrace => "{",
+            Token::RBrace => "}",
+            Token::LBracket => "[",
+            Token::RBracket => "]",
+            Token::Comma => ",",
+            Token::Colon => ":",
+            Token::Dot => ".",
+            Token::Semicolon => ";",
+            Token::Return => "return",
+            Token::Print => "print",
+            Token::If => "if",
+            Token::Else => "else",
+            Token::Do => "do",
+            Token::Times => "times",
+            Token::While => "while",
+            Token::Until => "until",
+            Token::Break => "break",
+            Token::Continue => "continue",
+            Token::ForEach => "for each",
+            Token::In => "in",
+            Token::Throw => "throw",
+            Token::Given => "given",
+            Token::Define => "define",
+            Token::ToBe => "to be",
+            Token::Not => "not",
+            Token::LengthOf => "length of",
+            Token::PopFrom => "pop from",
+            Token::Append => "append",
+            Token::Remove => "remove",
+            Token::From => "from",
+            Token::Record => "record",
+            Token::OneOf => "one of",
+            Token::Match => "match",
+            Token::Run => "run",
+        };
+        format!("`{}`", text)
+    }
 }
 
-impl std::string::ToString for Token {
-    fn to_string(&self) -> String {
-        let str: &str = match self {
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
             Token::Symbol(_) => "Symbol",
             Token::Bool(_) => "Bool",
             Token::Number(_) => "Number",
-            Token::Type(_) => "Type",
+            Token::BigNumber(_) => "BigNumber",
+            Token::Float(_) => "Float",
             Token::Str(_) => "Str",
             Token::Operator(..) => "Operator",
             Token::Return => "Return",
             Token::Comma => "Comma",
+            Token::Colon => "Colon",
+            Token::Dot => "Dot",
             Token::LBrace => "{",
             Token::RBrace => "}",
+            Token::LBracket => "[",
+            Token::RBracket => "]",
             Token::LParen => "(",
             Token::RParen => ")",
             Token::Semicolon => ";",
@@ -67,15 +150,29 @@ impl std::string::ToString for Token {
             Token::ToBe => "ToBe",
             Token::Run => "Run",
             Token::Times => "Times",
-        };
-
-        str.to_string()
+            Token::While => "While",
+            Token::Until => "Until",
+            Token::Break => "Break",
+            Token::Continue => "Continue",
+            Token::ForEach => "ForEach",
+            Token::In => "In",
+            Token::Not => "Not",
+            Token::LengthOf => "LengthOf",
+            Token::PopFrom => "PopFrom",
+            Token::Append => "Append",
+            Token::Remove => "Remove",
+            Token::From => "From",
+            Token::Record => "Record",
+            Token::OneOf => "OneOf",
+            Token::Match => "Match",
+            Token::EOF => "EOF",
+        })
     }
 }
 
 impl std::fmt::Debug for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let value = self.tuple().1;
-        write!(f, "({}, {})", self.to_string(), value)
+        write!(f, "({}, {})", self, value)
     }
 }
@@ -0,0 +1,33 @@
+use crate::types::binary_operator::BinaryOperator;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UnaryOperator {
+    Not,
+    Negate,
+    /// `length of`, how many items a list or characters a string has
+    Length,
+    /// `pop from`, take the last item off a list
+    Pop,
+}
+
+impl UnaryOperator {
+    /// Binary operators with a higher precedence are part of the operand, `not a is b` is `not (a is b)`
+    pub fn get_precedence(&self) -> u8 {
+        match self {
+            UnaryOperator::Not => BinaryOperator::And.get_precedence(),
+            // binds tighter than any binary operator, `-a * b` is `(-a) * b`
+            UnaryOperator::Negate | UnaryOperator::Length | UnaryOperator::Pop => 30,
+        }
+    }
+}
+
+impl std::fmt::Display for UnaryOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            UnaryOperator::Not => "not",
+            UnaryOperator::Negate => "-",
+            UnaryOperator::Length => "length of",
+            UnaryOperator::Pop => "pop from",
+        })
+    }
+}
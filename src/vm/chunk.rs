@@ -0,0 +1,148 @@
+use crate::types::ast::{Block, Pattern};
+use crate::types::binary_operator::BinaryOperator;
+use crate::types::primitive_value::PrimitiveValue;
+use crate::types::span::Span;
+use crate::types::symbol_store::SymbolStore;
+use crate::types::unary_operator::UnaryOperator;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A variable shared between a function and the closures that capture it
+pub type Cell = Rc<RefCell<Option<PrimitiveValue>>>;
+
+/// Where a resolved variable lives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Var {
+    /// slot in the current call frame
+    Local(u16),
+    /// cell captured by the running closure
+    Upvalue(u16),
+    /// name in the global symbol store, indexes `FunctionProto::names`
+    Global(u16),
+}
+
+/// How a closure gets each of its upvalues when it's created
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capture {
+    Local(u16),
+    Upvalue(u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    /// push `constants[i]`
+    Constant(u16),
+    /// push "no value", what statements like `print` evaluate to
+    Nothing,
+    Pop,
+    Load(Var),
+    /// load a function and check it can be called with this many arguments
+    LoadCallee(Var, u8),
+    /// pop and bind in the current scope, "no value" leaves the symbol unbound
+    Define(Var),
+    /// assign to an existing binding leaving the value on the stack
+    Store(Var),
+    /// reset the locals of `blocks[i]`, every scope entry gets fresh variables
+    EnterBlock(u16),
+    Binary(BinaryOperator),
+    Unary(UnaryOperator),
+    /// pop this many items into a new list
+    List(u16),
+    /// pop this many keys and values into a new map
+    Map(u16),
+    /// pop a map and a key, push the value the key had
+    Remove,
+    /// pop a record, push the value of its field `names[i]`
+    GetField(u16),
+    /// pop a value and a record, set the field `names[i]` leaving the value on the stack
+    SetField(u16),
+    /// pop an index and a collection, push the item at the index
+    Index,
+    /// pop a value, an index and a collection, set the item leaving the value on the stack
+    SetIndex,
+    /// pop a list and a value, add the value to the end of the list
+    Append,
+    /// check the `and`/`or` operand on top of the stack, jump past the right
+    /// side keeping it as the result when it decides the result on its own
+    ShortCircuit(BinaryOperator, usize),
+    /// fail unless the top of the stack holds a value, `names[i]` is the function called
+    ExpectValue(u16),
+    /// raise `constants[i]` as a runtime error
+    Fail(u16),
+    Print,
+    /// pop an `if` condition: true falls through, false jumps to the first target
+    /// anything else skips the whole statement by jumping to the second target
+    If(usize, usize),
+    /// pop an `else if` condition, jump unless it's true
+    Elif(usize),
+    Jump(usize),
+    /// pop a loop condition, jump out of the loop when it equals the bool,
+    /// true for `until` and false for `while`
+    While(bool, usize),
+    /// pop a loop count into the local slot, starting the counter in the next slot at 0
+    LoopCount(u16),
+    /// push the counter in slot + 1, or jump out once it reaches the count in slot
+    LoopNext(u16, usize),
+    /// pop a collection and start iterating over it in the local slot
+    Iterate(u16),
+    /// push the next index then item from the slot, or jump out once there are none
+    IterateNext(u16, usize),
+    /// pop the operands of `patterns[i]` and check the value in the local slot against it,
+    /// push the values it binds last first or jump when it doesn't fit
+    Match(u16, u16, usize),
+    /// fail because no arm fits the value in the local slot
+    NoMatch(u16),
+    /// create a closure over `protos[i]`
+    Closure(u16),
+    Call(u8),
+    Return,
+}
+
+/**
+ * A compiled function, the top level of a program is compiled into one too
+ */
+#[derive(Debug, Default)]
+pub struct FunctionProto {
+    pub name: String,
+    pub arity: usize,
+    pub code: Vec<Op>,
+    /// span of the node each op was compiled from
+    pub spans: Vec<Span>,
+    pub constants: Vec<PrimitiveValue>,
+    pub names: Vec<String>,
+    /// name of each local slot, used to fall back to globals for unbound slots
+    pub local_names: Vec<String>,
+    /// slots captured by a closure live in a `Cell`
+    pub captured: Vec<bool>,
+    /// slots declared in each block
+    pub blocks: Vec<Vec<u16>>,
+    pub captures: Vec<Capture>,
+    pub upvalue_names: Vec<String>,
+    pub protos: Vec<Rc<FunctionProto>>,
+    /// patterns of the `match` arms in the function
+    pub patterns: Vec<Pattern>,
+    /// source of the function so it prints the same as an interpreted one
+    pub args: Vec<String>,
+    pub body: Block,
+}
+
+/// A function value created by the bytecode VM
+pub struct Closure {
+    pub proto: Rc<FunctionProto>,
+    pub upvalues: Vec<Cell>,
+    /// store the program's top level symbols live in
+    pub globals: SymbolStore,
+}
+
+impl PartialEq for Closure {
+    fn eq(&self, other: &Closure) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl std::fmt::Debug for Closure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Closure({})", self.proto.name)
+    }
+}
@@ -0,0 +1,860 @@
+use crate::interpreter::{
+    no_arm_value_error, operands, outside_loop_error, return_in_value_error, statement_name,
+};
+use crate::types::ast::{Arm, Block, If, Node, AST};
+use crate::types::binary_operator::BinaryOperator;
+use crate::types::error::RuntimeError;
+use crate::types::primitive_value::PrimitiveValue;
+use crate::types::record::RecordType;
+use crate::types::span::Span;
+use crate::vm::chunk::{Capture, FunctionProto, Op, Var};
+
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+/**
+ * Compile a program into the function the VM runs for the top level.
+ *
+ * Variables resolve the same way the tree-walker's scopes do: symbols defined at
+ * the top level are globals looked up by name, every other `define` gets a slot
+ * in its call frame. Straight line code only sees symbols defined before it,
+ * closures see everything their enclosing blocks define since they run later.
+ * Programs with more constants, symbols or arguments than an operand holds don't compile.
+ */
+pub fn compile(program: &[Node]) -> Result<FunctionProto, RuntimeError> {
+    let mut compiler = Compiler {
+        functions: vec![FunctionState {
+            proto: FunctionProto {
+                name: "<program>".to_string(),
+                ..FunctionProto::default()
+            },
+            blocks: vec![],
+            loops: vec![],
+            value_statements: vec![],
+        }],
+        error: None,
+    };
+
+    compiler.compile_body(program);
+    let end = compiler.end_span();
+    compiler.emit(Op::Nothing, &end);
+    compiler.emit(Op::Return, &end);
+
+    match compiler.error {
+        Some(error) => Err(error),
+        None => Ok(compiler.functions.pop().unwrap().proto),
+    }
+}
+
+struct BlockScope {
+    /// index into `FunctionProto::blocks`
+    index: u16,
+    /// slot of each name the block binds
+    slots: HashMap<String, u16>,
+    /// names bound so far, straight line code can only see these
+    visible: HashSet<String>,
+    /// every name the block defines, including ones defined further down
+    names: HashSet<String>,
+}
+
+struct FunctionState {
+    proto: FunctionProto,
+    /// open block scopes, the top level of the program has none since it uses globals
+    blocks: Vec<BlockScope>,
+    /// loops being compiled, innermost last
+    loops: Vec<LoopState>,
+    /// statements used as values being compiled and their spans, they can't return
+    value_statements: Vec<(&'static str, Span)>,
+}
+
+/// What a match leaves on the stack
+#[derive(Clone, Copy, PartialEq)]
+enum MatchUse {
+    /// nothing, it's a statement and its arms can return
+    Statement,
+    /// the value of the arm that ran or no value, like what a `define` takes
+    Optional,
+    /// the value of the arm that ran, like an operand needs
+    Required,
+}
+
+struct LoopState {
+    /// where `continue` jumps to, the op that starts the next iteration
+    next: usize,
+    /// `break` jumps to patch once the end of the loop is known
+    breaks: Vec<usize>,
+}
+
+struct Compiler {
+    functions: Vec<FunctionState>,
+    /// the first operand that didn't fit, the program can't run if there is one
+    error: Option<RuntimeError>,
+}
+
+/// Narrow an index or count into an operand, recording an error instead of wrapping
+fn narrow<T: TryFrom<usize> + Default>(
+    value: usize,
+    what: &str,
+    error: &mut Option<RuntimeError>,
+) -> T {
+    T::try_from(value).unwrap_or_else(|_| {
+        error.get_or_insert_with(|| {
+            RuntimeError::new(format!("Too many {} for the bytecode VM", what))
+        });
+        T::default()
+    })
+}
+
+/// Names a block defines directly, nested blocks get their own scope
+fn defined_names(body: &[Node]) -> HashSet<String> {
+    let mut names = HashSet::new();
+    for node in body {
+        let mut node = node;
+        // `print define a to be 1;` still defines a
+        while let AST::Print { value } | AST::Return { value } = &node.node {
+            node = value;
+        }
+        match &node.node {
+            AST::Assign { identifier, .. }
+            | AST::Proc { identifier, .. }
+            | AST::Record { identifier, .. } => {
+                names.insert(identifier.to_string());
+            }
+            AST::Enum { variants, .. } => {
+                names.extend(variants.iter().map(|(variant, _)| variant.to_string()));
+            }
+            _ => (),
+        }
+    }
+    names
+}
+
+impl Compiler {
+    fn current(&mut self) -> &mut FunctionState {
+        self.functions.last_mut().unwrap()
+    }
+
+    fn emit(&mut self, op: Op, span: &Span) -> usize {
+        let proto = &mut self.current().proto;
+        proto.code.push(op);
+        proto.spans.push(span.clone());
+        proto.code.len() - 1
+    }
+
+    fn next_index(&mut self) -> usize {
+        self.current().proto.code.len()
+    }
+
+    /// Point the jump at `index` to the next op emitted
+    fn patch(&mut self, index: usize) {
+        let target = self.next_index();
+        let op = &mut self.current().proto.code[index];
+        *op = match *op {
+            Op::Jump(_) => Op::Jump(target),
+            Op::Elif(_) => Op::Elif(target),
+            Op::While(until, _) => Op::While(until, target),
+            Op::ShortCircuit(operator, _) => Op::ShortCircuit(operator, target),
+            Op::LoopNext(slot, _) => Op::LoopNext(slot, target),
+            Op::IterateNext(slot, _) => Op::IterateNext(slot, target),
+            Op::Match(slot, pattern, _) => Op::Match(slot, pattern, target),
+            op => op,
+        };
+    }
+
+    fn end_span(&mut self) -> Span {
+        match self.current().proto.spans.last() {
+            Some(span) => span.clone(),
+            None => Span::default(),
+        }
+    }
+
+    fn operand<T: TryFrom<usize> + Default>(&mut self, value: usize, what: &str) -> T {
+        narrow(value, what, &mut self.error)
+    }
+
+    fn constant(&mut self, value: PrimitiveValue) -> u16 {
+        let constants = &mut self.current().proto.constants;
+        constants.push(value);
+        let index = constants.len() - 1;
+        self.operand(index, "constants in one function")
+    }
+
+    fn name(&mut self, name: &str) -> u16 {
+        let names = &mut self.current().proto.names;
+        let index = match names.iter().position(|existing| existing == name) {
+            Some(index) => index,
+            None => {
+                names.push(name.to_string());
+                names.len() - 1
+            }
+        };
+        self.operand(index, "global names in one function")
+    }
+
+    fn fail(&mut self, message: String, span: &Span) {
+        let message = self.constant(PrimitiveValue::Str(message));
+        self.emit(Op::Fail(message), span);
+    }
+
+    fn new_slot(function: &mut FunctionState, name: &str, error: &mut Option<RuntimeError>) -> u16 {
+        let proto = &mut function.proto;
+        proto.local_names.push(name.to_string());
+        proto.captured.push(false);
+        narrow(proto.local_names.len() - 1, "locals in one function", error)
+    }
+
+    fn new_local(&mut self, name: &str) -> u16 {
+        let function = self.functions.last_mut().unwrap();
+        Compiler::new_slot(function, name, &mut self.error)
+    }
+
+    /// Slot for `name` in a block of `function`, allocating it if needed
+    fn slot_in_block(
+        function: &mut FunctionState,
+        block: usize,
+        name: &str,
+        error: &mut Option<RuntimeError>,
+    ) -> u16 {
+        if let Some(slot) = function.blocks[block].slots.get(name) {
+            return *slot;
+        }
+        let slot = Compiler::new_slot(function, name, error);
+        let scope = &mut function.blocks[block];
+        scope.slots.insert(name.to_string(), slot);
+        function.proto.blocks[scope.index as usize].push(slot);
+        slot
+    }
+
+    /// Bind `name` in the innermost scope
+    fn declare(&mut self, name: &str) -> Var {
+        if self.current().blocks.is_empty() {
+            let name = self.name(name);
+            return Var::Global(name);
+        }
+
+        let function = self.functions.last_mut().unwrap();
+        let block = function.blocks.len() - 1;
+        let slot = Compiler::slot_in_block(function, block, name, &mut self.error);
+        function.blocks[block].visible.insert(name.to_string());
+        Var::Local(slot)
+    }
+
+    fn resolve(&mut self, name: &str) -> Var {
+        let function = self.current();
+        for block in function.blocks.iter().rev() {
+            if block.visible.contains(name) {
+                return Var::Local(block.slots[name]);
+            }
+        }
+
+        let depth = self.functions.len() - 1;
+        if let Some(upvalue) = self.resolve_upvalue(depth, name) {
+            return Var::Upvalue(upvalue);
+        }
+
+        let name = self.name(name);
+        Var::Global(name)
+    }
+
+    /// Capture `name` from the functions enclosing `functions[depth]`
+    fn resolve_upvalue(&mut self, depth: usize, name: &str) -> Option<u16> {
+        if depth == 0 {
+            return None;
+        }
+
+        let function = &self.functions[depth];
+        if let Some(index) = function.proto.upvalue_names.iter().position(|n| n == name) {
+            return Some(self.operand(index, "captured names in one function"));
+        }
+
+        let parent = &mut self.functions[depth - 1];
+        let mut capture = None;
+        for block in (0..parent.blocks.len()).rev() {
+            let scope = &parent.blocks[block];
+            if scope.visible.contains(name) || scope.names.contains(name) {
+                let slot = Compiler::slot_in_block(parent, block, name, &mut self.error);
+                parent.proto.captured[slot as usize] = true;
+                capture = Some(Capture::Local(slot));
+                break;
+            }
+        }
+
+        if capture.is_none() {
+            capture = Some(Capture::Upvalue(self.resolve_upvalue(depth - 1, name)?));
+        }
+
+        let proto = &mut self.functions[depth].proto;
+        proto.captures.push(capture.unwrap());
+        proto.upvalue_names.push(name.to_string());
+        let index = proto.captures.len() - 1;
+        Some(self.operand(index, "captured names in one function"))
+    }
+
+    fn begin_block(&mut self, names: HashSet<String>, span: &Span) {
+        self.current().proto.blocks.push(vec![]);
+        let index = self.current().proto.blocks.len() - 1;
+        let index = self.operand(index, "blocks in one function");
+        self.current().blocks.push(BlockScope {
+            index,
+            slots: HashMap::new(),
+            visible: HashSet::new(),
+            names,
+        });
+        self.emit(Op::EnterBlock(index), span);
+    }
+
+    fn end_block(&mut self) {
+        self.current().blocks.pop();
+    }
+
+    /// Compile the body of an if or loop in its own scope
+    fn compile_block(&mut self, body: &[Node], span: &Span) {
+        self.begin_block(defined_names(body), span);
+        self.compile_body(body);
+        self.end_block();
+    }
+
+    fn compile_body(&mut self, body: &[Node]) {
+        for node in body {
+            match &node.node {
+                AST::Return { value } => {
+                    self.compile_expr(value);
+                    match self.current().value_statements.last().cloned() {
+                        Some((statement, span)) => {
+                            self.fail(return_in_value_error(statement).message, &span)
+                        }
+                        None => {
+                            self.emit(Op::Return, &node.span);
+                        }
+                    }
+                }
+                AST::Match { value, arms } => {
+                    self.compile_match(value, arms, MatchUse::Statement, &node.span)
+                }
+                AST::If { this, elifs, el } => self.compile_if(this, elifs, el, &node.span),
+                AST::Do {
+                    count,
+                    identifier,
+                    body,
+                } => self.compile_do(count, identifier, body, &node.span),
+                AST::ForEach {
+                    identifier,
+                    index,
+                    collection,
+                    body,
+                } => self.compile_for_each(identifier, index, collection, body, &node.span),
+                AST::While {
+                    conditional,
+                    body,
+                    until,
+                } => self.compile_while(conditional, body, *until, &node.span),
+                AST::Break => match self.current().loops.last() {
+                    Some(_) => {
+                        let jump = self.emit(Op::Jump(0), &node.span);
+                        self.current().loops.last_mut().unwrap().breaks.push(jump);
+                    }
+                    None => self.fail(outside_loop_error("break").message, &node.span),
+                },
+                AST::Continue => match self.current().loops.last() {
+                    Some(state) => {
+                        let next = state.next;
+                        self.emit(Op::Jump(next), &node.span);
+                    }
+                    None => self.fail(outside_loop_error("continue").message, &node.span),
+                },
+                _ => {
+                    self.compile_expr(node);
+                    self.emit(Op::Pop, &node.span);
+                }
+            }
+        }
+    }
+
+    /// Compile a node that may not produce a value, like a statement
+    fn compile_expr(&mut self, node: &Node) {
+        let span = &node.span;
+        match &node.node {
+            AST::Number { value } => {
+                let constant = self.constant(PrimitiveValue::Num(*value));
+                self.emit(Op::Constant(constant), span);
+            }
+            AST::BigNumber { value } => {
+                let constant = self.constant(PrimitiveValue::from(value.clone()));
+                self.emit(Op::Constant(constant), span);
+            }
+            AST::Float { value } => {
+                let constant = self.constant(PrimitiveValue::Float(*value));
+                self.emit(Op::Constant(constant), span);
+            }
+            AST::Bool { value } => {
+                let constant = self.constant(PrimitiveValue::Bool(*value));
+                self.emit(Op::Constant(constant), span);
+            }
+            AST::Str { value } => {
+                let constant = self.constant(PrimitiveValue::Str(value.to_string()));
+                self.emit(Op::Constant(constant), span);
+            }
+            AST::Symbol { identifier } => {
+                let var = self.resolve(identifier);
+                self.emit(Op::Load(var), span);
+            }
+            AST::Binary {
+                operator,
+                left,
+                right,
+            } => self.compile_binary(*operator, left, right, span),
+            AST::Unary { operator, value } => {
+                self.compile_value(value);
+                self.emit(Op::Unary(*operator), span);
+            }
+            AST::List { items } => {
+                for item in items {
+                    self.compile_value(item);
+                }
+                let length = self.operand(items.len(), "items in one list");
+                self.emit(Op::List(length), span);
+            }
+            AST::Map { entries } => {
+                for (key, value) in entries {
+                    self.compile_value(key);
+                    self.compile_value(value);
+                }
+                let length = self.operand(entries.len(), "entries in one map");
+                self.emit(Op::Map(length), span);
+            }
+            AST::Remove { key, map } => {
+                self.compile_value(key);
+                self.compile_value(map);
+                self.emit(Op::Remove, span);
+            }
+            AST::Index { collection, index } => {
+                self.compile_value(collection);
+                self.compile_value(index);
+                self.emit(Op::Index, span);
+            }
+            AST::Field { record, field } => {
+                self.compile_value(record);
+                let name = self.name(field);
+                self.emit(Op::GetField(name), span);
+            }
+            AST::Record { identifier, fields } => {
+                let record_type = RecordType {
+                    name: identifier.to_string(),
+                    fields: fields.clone(),
+                    variant_of: None,
+                };
+                let constant = self.constant(PrimitiveValue::RecordType(Rc::new(record_type)));
+                self.emit(Op::Constant(constant), span);
+                let var = self.declare(identifier);
+                self.emit(Op::Define(var), span);
+                self.emit(Op::Nothing, span);
+            }
+            AST::Enum {
+                identifier,
+                variants,
+            } => {
+                for (variant, fields) in variants {
+                    let value = RecordType::variant(identifier, variant, fields);
+                    let constant = self.constant(value);
+                    self.emit(Op::Constant(constant), span);
+                    let var = self.declare(variant);
+                    self.emit(Op::Define(var), span);
+                }
+                self.emit(Op::Nothing, span);
+            }
+            AST::Append { value, list } => {
+                self.compile_value(value);
+                self.compile_value(list);
+                self.emit(Op::Append, span);
+                self.emit(Op::Nothing, span);
+            }
+            AST::Call { identifier, args } => self.compile_call(identifier, args, span),
+            AST::Return { value } => self.compile_expr(value),
+            AST::Assign { identifier, value } => {
+                self.compile_expr(value);
+                let var = self.declare(identifier);
+                self.emit(Op::Define(var), span);
+                self.emit(Op::Nothing, span);
+            }
+            AST::Proc {
+                identifier,
+                args,
+                body,
+            } => {
+                self.compile_function(identifier, args, body, span);
+                let var = self.declare(identifier);
+                self.emit(Op::Define(var), span);
+                self.emit(Op::Nothing, span);
+            }
+            AST::Print { value } => {
+                self.compile_expr(value);
+                self.emit(Op::Print, span);
+                self.emit(Op::Nothing, span);
+            }
+            AST::Match { value, arms } => self.compile_match(value, arms, MatchUse::Optional, span),
+            // a statement used as a value, ie `print if ...`, gives no value
+            AST::If { .. } | AST::Do { .. } | AST::ForEach { .. } | AST::While { .. } => {
+                let statement = statement_name(&node.node);
+                self.compile_statement_value(statement, span, |compiler| {
+                    compiler.compile_body(std::slice::from_ref(node))
+                });
+                self.emit(Op::Nothing, span);
+            }
+            // there's no loop for these to leave from inside an expression
+            AST::Break => {
+                self.fail(outside_loop_error("break").message, span);
+                self.emit(Op::Nothing, span);
+            }
+            AST::Continue => {
+                self.fail(outside_loop_error("continue").message, span);
+                self.emit(Op::Nothing, span);
+            }
+            AST::Throw { message } => {
+                self.fail(message.to_string(), span);
+                self.emit(Op::Nothing, span);
+            }
+            AST::Semicolon | AST::EOF => {
+                self.emit(Op::Nothing, span);
+            }
+            AST::Program { .. } => {
+                self.fail("Found program in AST.".to_string(), span);
+                self.emit(Op::Nothing, span);
+            }
+        }
+    }
+
+    /// Compile a node that has to produce a value, like an operand or argument
+    fn compile_value(&mut self, node: &Node) {
+        match &node.node {
+            AST::Number { .. }
+            | AST::BigNumber { .. }
+            | AST::Float { .. }
+            | AST::Bool { .. }
+            | AST::Str { .. }
+            | AST::Symbol { .. }
+            | AST::Binary { .. }
+            | AST::Unary { .. }
+            | AST::List { .. }
+            | AST::Map { .. }
+            | AST::Index { .. }
+            | AST::Remove { .. }
+            | AST::Field { .. } => self.compile_expr(node),
+            AST::Call { identifier, args } => {
+                self.compile_call(identifier, args, &node.span);
+                let name = self.name(identifier);
+                self.emit(Op::ExpectValue(name), &node.span);
+            }
+            AST::Match { value, arms } => {
+                self.compile_match(value, arms, MatchUse::Required, &node.span)
+            }
+            ast => self.fail(
+                format!("Value of AST could not be determined {:?}", ast),
+                &node.span,
+            ),
+        }
+    }
+
+    fn compile_binary(&mut self, operator: BinaryOperator, left: &Node, right: &Node, span: &Span) {
+        if operator == BinaryOperator::Assign {
+            match &left.node {
+                AST::Symbol { identifier } => {
+                    self.compile_value(right);
+                    let var = self.resolve(identifier);
+                    self.emit(Op::Store(var), &left.span);
+                }
+                AST::Index { collection, index } => {
+                    self.compile_value(collection);
+                    self.compile_value(index);
+                    self.compile_value(right);
+                    self.emit(Op::SetIndex, &left.span);
+                }
+                AST::Field { record, field } => {
+                    self.compile_value(record);
+                    self.compile_value(right);
+                    let name = self.name(field);
+                    self.emit(Op::SetField(name), &left.span);
+                }
+                node => self.fail(format!("Can't assign to {:?}", node), &left.span),
+            }
+            return;
+        }
+
+        self.compile_value(left);
+        if matches!(operator, BinaryOperator::And | BinaryOperator::Or) {
+            let short_circuit = self.emit(Op::ShortCircuit(operator, 0), span);
+            self.compile_value(right);
+            self.emit(Op::Binary(operator), span);
+            self.patch(short_circuit);
+            return;
+        }
+        self.compile_value(right);
+        self.emit(Op::Binary(operator), span);
+    }
+
+    fn compile_call(&mut self, identifier: &str, args: &[Node], span: &Span) {
+        let var = self.resolve(identifier);
+        let count = self.operand(args.len(), "arguments in one call");
+        self.emit(Op::LoadCallee(var, count), span);
+        for arg in args {
+            self.compile_value(arg);
+        }
+        self.emit(Op::Call(count), span);
+    }
+
+    fn compile_function(&mut self, identifier: &str, args: &[String], body: &Block, span: &Span) {
+        let mut names = defined_names(body);
+        names.extend(args.iter().cloned());
+
+        self.functions.push(FunctionState {
+            proto: FunctionProto {
+                name: identifier.to_string(),
+                arity: args.len(),
+                args: args.to_vec(),
+                body: body.clone(),
+                ..FunctionProto::default()
+            },
+            blocks: vec![],
+            loops: vec![],
+            value_statements: vec![],
+        });
+
+        // arguments take the first slots, the VM fills them in before the body runs
+        self.current().proto.blocks.push(vec![]);
+        let mut root = BlockScope {
+            index: 0,
+            slots: HashMap::new(),
+            visible: HashSet::new(),
+            names,
+        };
+        for arg in args {
+            let slot = self.new_local(arg);
+            root.slots.insert(arg.to_string(), slot);
+            root.visible.insert(arg.to_string());
+        }
+        self.current().blocks.push(root);
+        self.emit(Op::EnterBlock(0), span);
+
+        self.compile_body(body);
+        let end = self.end_span();
+        self.emit(Op::Nothing, &end);
+        self.emit(Op::Return, &end);
+
+        let proto = self.functions.pop().unwrap().proto;
+        let protos = &mut self.current().proto.protos;
+        protos.push(Rc::new(proto));
+        let index = protos.len() - 1;
+        let index = self.operand(index, "functions in one function");
+        self.emit(Op::Closure(index), span);
+    }
+
+    fn compile_if(&mut self, this: &If, elifs: &Option<Vec<If>>, el: &Option<Block>, span: &Span) {
+        let mut ends = vec![];
+
+        self.compile_expr(&this.conditional);
+        let condition = self.emit(Op::If(0, 0), span);
+        self.compile_block(&this.body, span);
+        ends.push(self.emit(Op::Jump(0), span));
+        let else_target = self.next_index();
+
+        if let Some(elifs) = elifs {
+            for elif in elifs {
+                self.compile_expr(&elif.conditional);
+                let next = self.emit(Op::Elif(0), span);
+                self.compile_block(&elif.body, span);
+                ends.push(self.emit(Op::Jump(0), span));
+                self.patch(next);
+            }
+        }
+
+        if let Some(el) = el {
+            self.compile_block(el, span);
+        }
+
+        let end = self.next_index();
+        self.current().proto.code[condition] = Op::If(else_target, end);
+        for jump in ends {
+            self.patch(jump);
+        }
+    }
+
+    fn compile_do(
+        &mut self,
+        count: &Node,
+        identifier: &Option<String>,
+        body: &[Node],
+        span: &Span,
+    ) {
+        self.compile_value(count);
+
+        // the loop scope holds the index, each iteration gets a fresh scope for the body
+        let mut names = HashSet::new();
+        if let Some(identifier) = identifier {
+            names.insert(identifier.to_string());
+        }
+        self.begin_block(names, span);
+        let counter = self.new_local("");
+        self.new_local("");
+        self.emit(Op::LoopCount(counter), span);
+
+        let start = self.emit(Op::LoopNext(counter, 0), span);
+        match identifier {
+            Some(identifier) => {
+                let var = self.declare(identifier);
+                self.emit(Op::Define(var), span)
+            }
+            None => self.emit(Op::Pop, span),
+        };
+        self.begin_loop(start);
+        self.compile_block(body, span);
+        self.emit(Op::Jump(start), span);
+        self.patch(start);
+        self.end_loop();
+
+        self.end_block();
+    }
+
+    fn compile_for_each(
+        &mut self,
+        identifier: &str,
+        index: &Option<String>,
+        collection: &Node,
+        body: &[Node],
+        span: &Span,
+    ) {
+        self.compile_value(collection);
+
+        // like `do`, the loop scope holds the item and index and the body gets a fresh scope
+        let mut names = HashSet::new();
+        names.insert(identifier.to_string());
+        if let Some(index) = index {
+            names.insert(index.to_string());
+        }
+        self.begin_block(names, span);
+        let items = self.new_local("");
+        self.emit(Op::Iterate(items), &collection.span);
+
+        let start = self.emit(Op::IterateNext(items, 0), span);
+        let var = self.declare(identifier);
+        self.emit(Op::Define(var), span);
+        match index {
+            Some(index) => {
+                let var = self.declare(index);
+                self.emit(Op::Define(var), span)
+            }
+            None => self.emit(Op::Pop, span),
+        };
+        self.begin_loop(start);
+        self.compile_block(body, span);
+        self.emit(Op::Jump(start), span);
+        self.patch(start);
+        self.end_loop();
+
+        self.end_block();
+    }
+
+    fn compile_match(&mut self, value: &Node, arms: &[Arm], usage: MatchUse, span: &Span) {
+        self.compile_value(value);
+
+        // the match scope holds the value, each arm's bindings get a scope around its body
+        self.begin_block(HashSet::new(), span);
+        let subject = self.new_local("");
+        self.emit(Op::Define(Var::Local(subject)), span);
+
+        let mut ends = vec![];
+        for arm in arms {
+            let pattern_span = &arm.pattern.span;
+            let names = arm.pattern.node.bindings();
+            self.begin_block(names.iter().cloned().collect(), pattern_span);
+            for operand in operands(&arm.pattern.node, pattern_span) {
+                self.compile_value(&operand);
+            }
+            let patterns = &mut self.current().proto.patterns;
+            patterns.push(arm.pattern.node.clone());
+            let pattern = patterns.len() - 1;
+            let pattern = self.operand(pattern, "patterns in one function");
+            let next = self.emit(Op::Match(subject, pattern, 0), pattern_span);
+            for name in &names {
+                let var = self.declare(name);
+                self.emit(Op::Define(var), pattern_span);
+            }
+            match usage {
+                MatchUse::Statement => self.compile_block(&arm.body, span),
+                _ => self.compile_arm_value(arm, usage == MatchUse::Required, span),
+            }
+            ends.push(self.emit(Op::Jump(0), span));
+            self.end_block();
+            self.patch(next);
+        }
+        self.emit(Op::NoMatch(subject), span);
+
+        for jump in ends {
+            self.patch(jump);
+        }
+        self.end_block();
+    }
+
+    /// Compile an arm's body leaving the value it ends with, `span` is the match's
+    fn compile_arm_value(&mut self, arm: &Arm, required: bool, span: &Span) {
+        let (body, value) = arm.split_value();
+        self.compile_statement_value("a match", span, |compiler| {
+            compiler.begin_block(defined_names(&arm.body), span);
+            compiler.compile_body(body);
+            match value {
+                Some(value) if required => compiler.compile_value(value),
+                Some(value) => compiler.compile_expr(value),
+                None => {
+                    if required {
+                        compiler.fail(no_arm_value_error().message, span);
+                    }
+                    compiler.emit(Op::Nothing, span);
+                }
+            }
+            compiler.end_block();
+        });
+    }
+
+    /// Compile part of a statement used as a value, `return`, `break` and `continue`
+    /// can't leave the expression it's in
+    fn compile_statement_value(
+        &mut self,
+        statement: &'static str,
+        span: &Span,
+        compile: impl FnOnce(&mut Compiler),
+    ) {
+        let loops = std::mem::take(&mut self.current().loops);
+        self.current()
+            .value_statements
+            .push((statement, span.clone()));
+        compile(self);
+        self.current().value_statements.pop();
+        self.current().loops = loops;
+    }
+
+    fn compile_while(&mut self, conditional: &Node, body: &[Node], until: bool, span: &Span) {
+        let start = self.next_index();
+        self.compile_value(conditional);
+        let exit = self.emit(Op::While(until, 0), &conditional.span);
+
+        self.begin_loop(start);
+        self.compile_block(body, span);
+        self.emit(Op::Jump(start), span);
+        self.patch(exit);
+        self.end_loop();
+    }
+
+    fn begin_loop(&mut self, next: usize) {
+        self.current().loops.push(LoopState {
+            next,
+            breaks: vec![],
+        });
+    }
+
+    /// Point every `break` in the loop at the next op emitted
+    fn end_loop(&mut self) {
+        let state = self.current().loops.pop().unwrap();
+        for jump in state.breaks {
+            self.patch(jump);
+        }
+    }
+}
@@ -0,0 +1,925 @@
+mod chunk;
+mod compiler;
+#[allow(clippy::module_inception)]
+mod vm;
+
+pub use self::chunk::Closure;
+pub use self::vm::{call, run};
+
+#[cfg(test)]
+mod test {
+    use crate::interpreter::interpret;
+    use crate::types::ast::AST;
+    use crate::types::options::{Backend, Options};
+    use crate::types::primitive_value::PrimitiveValue;
+    use crate::types::symbol_store::SymbolStore;
+
+    fn program(source: &str) -> Vec<crate::types::ast::Node> {
+        let tokens = crate::lexer::tokenize_file(source.to_string(), "test.eye").unwrap();
+        match crate::parser::build_program(tokens).unwrap() {
+            AST::Program { program } => program,
+            _ => panic!("Expected a program"),
+        }
+    }
+
+    /// Run `source` on both backends, they have to agree on the result
+    fn run(source: &str) -> Result<Option<PrimitiveValue>, String> {
+        let mut results: Vec<_> = Options::every_backend()
+            .iter()
+            .map(|options| {
+                crate::interpreter::run_program(program(source), &SymbolStore::new(), options)
+                    .map_err(|error| error.to_string())
+            })
+            .collect();
+        let bytecode = results.pop().unwrap();
+        let tree = results.pop().unwrap();
+
+        assert_eq!(tree, bytecode, "backends disagree on {}", source);
+        bytecode
+    }
+
+    fn run_num(source: &str) -> i32 {
+        match run(source).unwrap() {
+            Some(PrimitiveValue::Num(value)) => value,
+            value => panic!("Expected a number found {:?}", value),
+        }
+    }
+
+    #[test]
+    fn arithmetic_and_globals() {
+        assert_eq!(run_num("define a to be 2; a = a * 10; return a + 3;"), 23);
+    }
+
+    #[test]
+    fn recursion() {
+        let fib = "define fib to be {
+            if n is 0 { return 0; } else if n is 1 { return 1; }
+            return fib(n - 1) + fib(n - 2);
+        } given (n);
+        return fib(15);";
+        assert_eq!(run_num(fib), 610);
+    }
+
+    #[test]
+    fn big_numbers() {
+        let fib = "define fib to be {
+            define a to be 0;
+            define b to be 1;
+            do {
+                define next to be a + b;
+                a = b;
+                b = next;
+            } n times;
+            return a;
+        } given (n);
+        return fib(50);";
+        let value = run(fib).unwrap().unwrap();
+        assert_eq!(value.to_string(), "12586269025");
+        let value = run("return 3 * -9999999999;").unwrap().unwrap();
+        assert_eq!(value.to_string(), "-29999999997");
+        let value = run("return 29999999997 - 29999999990;").unwrap();
+        assert_eq!(value, Some(PrimitiveValue::Num(7)));
+    }
+
+    #[test]
+    fn comparisons() {
+        let fib = "define fib to be {
+            if n < 2 { return n; }
+            return fib(n - 1) + fib(n - 2);
+        } given (n);
+        return fib(15);";
+        assert_eq!(run_num(fib), 610);
+
+        let cases = [
+            ("return 1 < 2;", true),
+            ("return 2 <= 1;", false),
+            ("return 2.5 > 2;", true),
+            ("return 3 >= 3;", true),
+            ("return 3 is less than 1 + 1;", false),
+            ("return 2 is at least 2.5;", false),
+            ("return \"abc\" is greater than \"abd\";", false),
+            ("return 4294967296 is at most 4294967296;", true),
+        ];
+        for (source, expected) in cases.iter() {
+            assert_eq!(
+                run(source).unwrap(),
+                Some(PrimitiveValue::Bool(*expected)),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn inequality() {
+        let cases = [
+            ("return 1 != 2;", true),
+            ("return 1 != 1.0;", false),
+            ("return \"a\" is not \"b\";", true),
+            ("return true != true;", false),
+            ("return 4294967296 is not 4294967296;", false),
+            (
+                "define a to be 3; if a != 3 { return false; } return true;",
+                true,
+            ),
+        ];
+        for (source, expected) in cases.iter() {
+            assert_eq!(
+                run(source).unwrap(),
+                Some(PrimitiveValue::Bool(*expected)),
+                "{}",
+                source
+            );
+        }
+        assert_eq!(
+            run("return 1 != \"1\";").unwrap_err(),
+            "test.eye:1:8: Operator not implemented for 1 and 1."
+        );
+    }
+
+    #[test]
+    fn unary_and_parentheses() {
+        assert_eq!(
+            run("return -1.5 + 2;").unwrap(),
+            Some(PrimitiveValue::Float(0.5))
+        );
+        assert_eq!(run_num("return -(2 + 3) * 4;"), -20);
+        assert_eq!(run_num("return (10 - 4) / (1 + 2);"), 2);
+        assert_eq!(run_num("define x to be 7; return -x * 2;"), -14);
+        assert_eq!(run_num("define x to be 7; return 1 - -x;"), 8);
+        assert_eq!(run_num("return ((((1))));"), 1);
+        let value = run("define min to be -2147483648; return -min;").unwrap();
+        assert_eq!(value.unwrap().to_string(), "2147483648");
+        assert_eq!(
+            run("return not (true and false);").unwrap(),
+            Some(PrimitiveValue::Bool(true))
+        );
+        assert_eq!(
+            run("return -true;").unwrap_err(),
+            "test.eye:1:8: Expected a number for -, found true."
+        );
+    }
+
+    #[test]
+    fn logic() {
+        let cases = [
+            ("return true and false;", false),
+            ("return false or true;", true),
+            ("return not false;", true),
+            ("return not 1 is 2;", true),
+            ("return 1 < 2 and 2 < 3 or false;", true),
+            ("return false and true or true;", true),
+            ("return true or true and false;", true),
+            ("return not true or true;", true),
+            ("define a to be 4; return a > 3 and a % 2 is 0;", true),
+        ];
+        for (source, expected) in cases.iter() {
+            assert_eq!(
+                run(source).unwrap(),
+                Some(PrimitiveValue::Bool(*expected)),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn logic_short_circuits() {
+        // the right side would fail if it ran
+        let source = "define fail to be { throw \"evaluated\"; } given ();
+        define a to be false and fail();
+        define b to be true or fail();
+        return a or b;";
+        assert_eq!(run(source).unwrap(), Some(PrimitiveValue::Bool(true)));
+        assert_eq!(
+            run("define fail to be { throw \"evaluated\"; } given (); return true and fail();")
+                .unwrap_err(),
+            "test.eye:1:21: evaluated"
+        );
+
+        assert_eq!(
+            run("return 1 and true;").unwrap_err(),
+            "test.eye:1:8: Expected a bool for and, found 1."
+        );
+        assert_eq!(
+            run("return false or \"yes\";").unwrap_err(),
+            "test.eye:1:8: Expected a bool for or, found yes."
+        );
+        assert_eq!(
+            run("return not 0;").unwrap_err(),
+            "test.eye:1:8: Expected a bool for not, found 0."
+        );
+    }
+
+    #[test]
+    fn while_and_until() {
+        assert_eq!(
+            run_num("define i to be 0; while i < 5 { i = i + 1; } return i;"),
+            5
+        );
+        assert_eq!(
+            run_num("define i to be 10; until i is at most 3 { i = i - 2; } return i;"),
+            2
+        );
+        // the body never runs when the condition already stops the loop
+        assert_eq!(
+            run_num("define i to be 0; while false { i = 1; } return i;"),
+            0
+        );
+    }
+
+    #[test]
+    fn break_and_continue() {
+        let source = "define total to be 0;
+        define i to be 0;
+        while true {
+            i = i + 1;
+            if i is 3 { continue; }
+            if i > 6 { if true { break; } }
+            total = total + i;
+        }
+        return total;";
+        // 1 + 2 + 4 + 5 + 6
+        assert_eq!(run_num(source), 18);
+
+        let source = "define total to be 0;
+        do {
+            if i % 2 is 0 { continue; }
+            if i > 6 { break; }
+            total = total + i;
+        } 100 times given i;
+        return total;";
+        // 1 + 3 + 5
+        assert_eq!(run_num(source), 9);
+
+        // break only leaves the innermost loop
+        let source = "define count to be 0;
+        do {
+            while true { count = count + 1; break; }
+        } 3 times;
+        return count;";
+        assert_eq!(run_num(source), 3);
+    }
+
+    #[test]
+    fn return_from_inside_loops() {
+        let source = "define first to be {
+            define i to be 0;
+            while true {
+                if i * i > n { return i; }
+                i = i + 1;
+            }
+        } given (n);
+        return first(20);";
+        assert_eq!(run_num(source), 5);
+
+        let source = "define f to be {
+            if true { return; }
+            throw \"kept running\";
+        } given ();
+        run f;
+        return 1;";
+        assert_eq!(run_num(source), 1);
+    }
+
+    #[test]
+    fn for_each() {
+        assert_eq!(
+            run_num(
+                "define total to be 0; for each n in 1 to 10 { total = total + n; } return total;"
+            ),
+            55
+        );
+        // the index counts from 0 alongside the items
+        assert_eq!(
+            run_num(
+                "define total to be 0;
+                for each n in 5 to 7 given i { total = total + n * i; }
+                return total;"
+            ),
+            20
+        );
+        let source = "define word to be \"\";
+        for each letter in \"loop\" given i {
+            if i is 0 { continue; }
+            word = letter + word;
+        }
+        return word;";
+        assert_eq!(
+            run(source).unwrap(),
+            Some(PrimitiveValue::Str("poo".to_string()))
+        );
+
+        // empty ranges run nothing and break leaves early
+        assert_eq!(
+            run_num(
+                "define count to be 0; for each n in 3 to 1 { count = count + 1; } return count;"
+            ),
+            0
+        );
+        assert_eq!(
+            run_num("define last to be 0; for each n in 1 to 100 { if n > 4 { break; } last = n; } return last;"),
+            4
+        );
+        assert_eq!(
+            run("return 1 to 3;").unwrap(),
+            Some(PrimitiveValue::Range(1, 3))
+        );
+    }
+
+    #[test]
+    fn for_each_errors() {
+        assert_eq!(
+            run("for each n in 5 { print n; }").unwrap_err(),
+            "test.eye:1:15: Expected a range, list, map or string to loop over, found 5"
+        );
+        assert_eq!(
+            run("return 1 to 2.5;").unwrap_err(),
+            "test.eye:1:8: Expected a number for to, found 2.5."
+        );
+    }
+
+    #[test]
+    fn lists() {
+        let source = "define xs to be [1, 2, 3];
+        xs[0] = xs[1] + xs[2];
+        append 10 to xs;
+        define last to be pop from xs;
+        return xs[0] * 100 + last + length of xs;";
+        assert_eq!(run_num(source), 513);
+
+        // lists are shared between the names they're bound to
+        let source = "define xs to be [];
+        define ys to be xs;
+        append \"a\" to ys;
+        return length of xs;";
+        assert_eq!(run_num(source), 1);
+
+        let source = "define grid to be [[1, 2], [3, 4]];
+        grid[1][0] = 5;
+        define total to be 0;
+        for each row in grid { for each cell in row { total = total + cell; } }
+        return total;";
+        assert_eq!(run_num(source), 12);
+
+        let value = run("return [1, 2] + [\"three\"];").unwrap().unwrap();
+        assert_eq!(value.to_string(), "[1, 2, \"three\"]");
+        let value = run("define xs to be [0, 1, 2, 3, 4]; return xs[1 to 3];")
+            .unwrap()
+            .unwrap();
+        assert_eq!(value.to_string(), "[1, 2, 3]");
+        let value = run("return [0, 1][2 to 1];").unwrap().unwrap();
+        assert_eq!(value.to_string(), "[]");
+        assert_eq!(
+            run("return [1, [2]] is [1, [2]] and [1] is not [1, 1];").unwrap(),
+            Some(PrimitiveValue::Bool(true))
+        );
+        assert_eq!(run_num("return length of \"eyes\";"), 4);
+    }
+
+    #[test]
+    fn lists_holding_themselves() {
+        let lists = "define xs to be [1];
+        append xs to xs;
+        define ys to be [1];
+        append ys to ys;
+        define zs to be [2];
+        append zs to zs;\n";
+        let run_lists = |source: &str| run(&format!("{}{}", lists, source)).unwrap().unwrap();
+        assert_eq!(run_lists("return xs;").to_string(), "[1, [...]]");
+        assert_eq!(
+            format!("{:?}", run_lists("return xs;")),
+            "List(RefCell { value: [Num(1), List(...)] })"
+        );
+        assert_eq!(
+            run_lists("return [xs, xs];").to_string(),
+            "[[1, [...]], [1, [...]]]"
+        );
+        assert_eq!(
+            run_lists("return [xs is xs, xs is ys, xs is zs];").to_string(),
+            "[true, true, false]"
+        );
+    }
+
+    #[test]
+    fn list_errors() {
+        assert_eq!(
+            run("define xs to be [1, 2, 3];\nprint xs[3];").unwrap_err(),
+            "test.eye:2:7: Index 3 is out of bounds for a list of length 3."
+        );
+        assert_eq!(
+            run("define xs to be [1];\nxs[-1] = 2;").unwrap_err(),
+            "test.eye:2:1: Index -1 is out of bounds for a list of length 1."
+        );
+        assert_eq!(
+            run("return [1, 2][1 to 5];").unwrap_err(),
+            "test.eye:1:8: Index 5 is out of bounds for a list of length 2."
+        );
+        assert_eq!(
+            run("return [1][\"0\"];").unwrap_err(),
+            "test.eye:1:8: Expected a number for indexing, found 0."
+        );
+        assert_eq!(
+            run("return pop from [];").unwrap_err(),
+            "test.eye:1:8: Can't pop from an empty list."
+        );
+        assert_eq!(
+            run("append 1 to 2;").unwrap_err(),
+            "test.eye:1:1: Expected a list for append, found 2."
+        );
+        assert_eq!(
+            run("return length of 2;").unwrap_err(),
+            "test.eye:1:8: Expected a list or map for length of, found 2."
+        );
+    }
+
+    #[test]
+    fn maps() {
+        let source = "define ages to be [\"bo\": 3, \"al\": 5];
+        ages[\"cy\"] = 7;
+        ages[\"bo\"] = ages[\"bo\"] + 1;
+        define removed to be remove \"al\" from ages;
+        return ages;";
+        let value = run(source).unwrap().unwrap();
+        // updated keys keep their place, new keys go at the end
+        assert_eq!(value.to_string(), "[\"bo\": 4, \"cy\": 7]");
+
+        let source = "define m to be [:];
+        m[\"a\"] = 1;
+        return m has \"a\" and not (m has \"b\");";
+        assert_eq!(run(source).unwrap(), Some(PrimitiveValue::Bool(true)));
+
+        // loops see values in insertion order, indexed by their keys
+        let source = "define m to be [\"x\": 1, \"y\": 2, \"z\": 3];
+        define keys to be \"\";
+        define total to be 0;
+        for each value in m given key { keys = keys + key; total = total + value; }
+        return [keys, total, length of m];";
+        let value = run(source).unwrap().unwrap();
+        assert_eq!(value.to_string(), "[\"xyz\", 6, 3]");
+
+        // keys and values can be names like anywhere else
+        let source = "define k to be \"a\";
+        define v to be 1;
+        define m to be [k: v];
+        return m;";
+        let value = run(source).unwrap().unwrap();
+        assert_eq!(value.to_string(), "[\"a\": 1]");
+
+        // order doesn't matter for equality
+        assert_eq!(
+            run("return [\"a\": 1, \"b\": [2]] is [\"b\": [2], \"a\": 1];").unwrap(),
+            Some(PrimitiveValue::Bool(true))
+        );
+        assert_eq!(
+            run("return [\"a\": 1] is not [\"a\": 2];").unwrap(),
+            Some(PrimitiveValue::Bool(true))
+        );
+    }
+
+    #[test]
+    fn maps_holding_themselves() {
+        let maps = "define a to be [\"n\": 1];
+        a[\"self\"] = a;
+        define b to be [\"n\": 1];
+        b[\"self\"] = b;
+        define c to be [\"n\": 2];
+        c[\"self\"] = c;\n";
+        let run_maps = |source: &str| run(&format!("{}{}", maps, source)).unwrap().unwrap();
+        assert_eq!(
+            run_maps("return a;").to_string(),
+            "[\"n\": 1, \"self\": [...]]"
+        );
+        assert_eq!(
+            run_maps("return [a is a, a is b, a is c];").to_string(),
+            "[true, true, false]"
+        );
+    }
+
+    #[test]
+    fn map_errors() {
+        assert_eq!(
+            run("define m to be [\"a\": 1];\nprint m[\"b\"];").unwrap_err(),
+            "test.eye:2:7: Key \"b\" isn't in the map."
+        );
+        assert_eq!(
+            run("return remove \"b\" from [:];").unwrap_err(),
+            "test.eye:1:8: Key \"b\" isn't in the map."
+        );
+        assert_eq!(
+            run("return [1: 2];").unwrap_err(),
+            "test.eye:1:8: Expected a string for a map key, found 1."
+        );
+        assert_eq!(
+            run("return [\"a\": 1][0];").unwrap_err(),
+            "test.eye:1:8: Expected a string for a map key, found 0."
+        );
+        assert_eq!(
+            run("return [1] has \"a\";").unwrap_err(),
+            "test.eye:1:8: Expected a map for has, found [1]."
+        );
+        assert_eq!(
+            run("define n to be 1; return n[0];").unwrap_err(),
+            "test.eye:1:26: Expected a list or map for indexing, found 1."
+        );
+    }
+
+    #[test]
+    fn records() {
+        let source = "define Point to be record { x: number, y: number };
+        define Line to be record { start: Point, end: Point, name: string };
+        define line to be Line(Point(0, 0), Point(3, 4), \"diagonal\");
+        line.end.x = line.end.x * 2;
+        define moved to be line.start;
+        moved.y = 1;
+        return line;";
+        let value = run(source).unwrap().unwrap();
+        // records are shared, moving `moved` moved the line's start
+        assert_eq!(
+            value.to_string(),
+            "Line(start: Point(x: 0, y: 1), end: Point(x: 6, y: 4), name: \"diagonal\")"
+        );
+
+        let source = "define Pair to be record { a: any, b: any };
+        return Pair(1, [2]) is Pair(1, [2]) and Pair(1, 2) is not Pair(2, 1);";
+        assert_eq!(run(source).unwrap(), Some(PrimitiveValue::Bool(true)));
+
+        // fields can hold floats as numbers and records can be passed around
+        let source = "define Point to be record { x: number, y: number };
+        define length to be { return p.x * p.x + p.y * p.y; } given (p);
+        return length(Point(1.5, 2));";
+        assert_eq!(run(source).unwrap(), Some(PrimitiveValue::Float(6.25)));
+    }
+
+    #[test]
+    fn records_holding_themselves() {
+        let source = "define Link to be record { value: number, next: any };
+        define a to be Link(1, 0);
+        a.next = a;
+        define b to be Link(1, 0);
+        b.next = b;
+        return [a, a is b, a is Link(1, Link(2, 0))];";
+        assert_eq!(
+            run(source).unwrap().unwrap().to_string(),
+            "[Link(value: 1, next: Link(...)), true, false]"
+        );
+    }
+
+    #[test]
+    fn record_errors() {
+        let point = "define Point to be record { x: number, y: number };\n";
+        let run_point = |source: &str| run(&format!("{}{}", point, source)).unwrap_err();
+        assert_eq!(
+            run_point("define p to be Point(1);"),
+            "test.eye:2:16: Record Point has 2 fields but was given 1"
+        );
+        assert_eq!(
+            run_point("define p to be Point(1, \"2\");"),
+            "test.eye:2:16: Field y of Point has to be a number, found 2."
+        );
+        assert_eq!(
+            run_point("define p to be Point(1, 2);\nprint p.z;"),
+            "test.eye:3:7: Point has no field z."
+        );
+        assert_eq!(
+            run_point("define p to be Point(1, 2);\np.x = true;"),
+            "test.eye:3:1: Field x of Point has to be a number, found true."
+        );
+        assert_eq!(
+            run_point("define n to be 1;\nprint n.x;"),
+            "test.eye:3:7: Expected a record for .x, found 1."
+        );
+
+        // field types are checked when the program starts, not when a record is made
+        assert_eq!(
+            run_point("print 1;\ndefine Bad to be record { x: nmber };"),
+            "test.eye:3:1: Field x of Bad has the type nmber, which isn't defined."
+        );
+        let source = "define Tree to be record { left: Node, right: Shape };
+        define Node to be record { value: number };
+        define Shape to be one of Circle(r) or Empty;
+        return Tree(Node(1), Empty).left.value;";
+        assert_eq!(run(source).unwrap(), Some(PrimitiveValue::Num(1)));
+    }
+
+    #[test]
+    fn enums_and_match() {
+        let shapes = "define Shape to be one of Circle(r) or Rect(w, h) or Empty;
+        define area to be {
+            match shape {
+                Circle(r) { return 3 * r * r; }
+                Rect(w, h) { return w * h; }
+                Empty { return 0; }
+            }
+        } given (shape);\n";
+        let run_shapes = |source: &str| run(&format!("{}{}", shapes, source));
+        assert_eq!(
+            run_shapes("return [area(Circle(2)), area(Rect(2, 3)), area(Empty)];")
+                .unwrap()
+                .unwrap()
+                .to_string(),
+            "[12, 6, 0]"
+        );
+        assert_eq!(
+            run_shapes("return [Circle(1), Empty, Circle, Circle(1) is Circle(1)];")
+                .unwrap()
+                .unwrap()
+                .to_string(),
+            "[Circle(r: 1), Empty, <variant Circle of Shape>, true]"
+        );
+
+        // literals, nested variants, wildcards and bindings, the first arm that fits runs
+        let source = "define Option to be one of Some(value) or None;
+        define names to be [];
+        for each x in [1, \"two\", -3, Some(Some(4)), Some(None), None, true] {
+            match x {
+                1 { append \"one\" to names; }
+                \"two\" { append \"two\" to names; }
+                -3 { append \"minus three\" to names; }
+                Some(Some(n)) { append n to names; }
+                Some(_) { append \"some\" to names; }
+                None { append \"none\" to names; }
+                other { append other to names; }
+            }
+        }
+        return names;";
+        assert_eq!(
+            run(source).unwrap().unwrap().to_string(),
+            "[\"one\", \"two\", \"minus three\", 4, \"some\", \"none\", true]"
+        );
+
+        // records destructure like variants, and arms can break out of loops
+        let source = "define Point to be record { x: number, y: number };
+        define Shape to be one of Dot(at) or Blank;
+        define total to be 0;
+        for each shape in [Dot(Point(1, 2)), Blank, Dot(Point(5, 5))] {
+            match shape {
+                Dot(Point(x, y)) { total = total + x + y; }
+                Blank { break; }
+            }
+        }
+        return total;";
+        assert_eq!(run_num(source), 3);
+    }
+
+    #[test]
+    fn match_as_a_value() {
+        let shapes = "define Shape to be one of Circle(r) or Rect(w, h) or Empty;\n";
+        let run_shapes = |source: &str| run(&format!("{}{}", shapes, source));
+
+        // the value is what the arm that ran ends with
+        let source = "define x to be match Circle(2) { Circle(r) { r } _ { 0 } };
+        return x;";
+        assert_eq!(run_shapes(source).unwrap(), Some(PrimitiveValue::Num(2)));
+        let source = "return 1 + match Rect(2, 3) {
+            Circle(r) { 3 * r * r }
+            Rect(w, h) { define area to be w * h; area; }
+            Empty { 0 }
+        } * 10;";
+        assert_eq!(run_shapes(source).unwrap(), Some(PrimitiveValue::Num(61)));
+        let source = "define describe to be {
+            return match s { Circle(r) { match r { 1 { \"dot\" } _ { \"circle\" } } } _ { \"other\" } };
+        } given (s);
+        return [describe(Circle(1)), describe(Circle(5)), describe(Empty)];";
+        assert_eq!(
+            run_shapes(source).unwrap().unwrap().to_string(),
+            "[\"dot\", \"circle\", \"other\"]"
+        );
+
+        // statements don't give a value, operands need one
+        let source = "define x to be match Empty { _ { print 1; } };\nreturn x;";
+        assert_eq!(
+            run_shapes(source).unwrap_err(),
+            "test.eye:3:8: Tried to access undefined symbol: x"
+        );
+        assert_eq!(
+            run_shapes("print 1 + match Empty { _ { print 1; } };").unwrap_err(),
+            "test.eye:2:11: Match arm didn't produce a value"
+        );
+        assert_eq!(
+            run_shapes("print 1 + match Empty {\nCircle(r) { r }\n};").unwrap_err(),
+            "test.eye:2:11: No arm of the match fits Empty"
+        );
+
+        // the value can't be skipped by leaving the match some other way
+        let source = "define f to be { print match Empty { _ { return 1; } }; } given ();
+        run f;";
+        assert_eq!(
+            run_shapes(source).unwrap_err(),
+            "test.eye:2:24: Can't return from a match used as a value"
+        );
+        let source = "while true { print match Empty {\n_ { break; }\n}; }";
+        assert_eq!(
+            run_shapes(source).unwrap_err(),
+            "test.eye:3:5: Can't break outside of a loop"
+        );
+    }
+
+    #[test]
+    fn statements_as_values() {
+        // they run but don't give a value
+        let source = "define x to be 0;\nprint if true { x = 1; };\nreturn x;";
+        assert_eq!(run(source), Ok(Some(PrimitiveValue::Num(1))));
+        assert_eq!(run("return while true { break; };"), Ok(None));
+
+        // the value can't be skipped by returning from inside the statement
+        let source = "define f to be { print if true { return 5; }; return 7; } given ();
+        print f();";
+        assert_eq!(
+            run(source).unwrap_err(),
+            "test.eye:1:24: Can't return from an if used as a value"
+        );
+        let source = "define f to be { print do { return 5; } 2 times; } given ();\nrun f;";
+        assert_eq!(
+            run(source).unwrap_err(),
+            "test.eye:1:24: Can't return from a loop used as a value"
+        );
+    }
+
+    #[test]
+    fn statements_as_values_keep_loop_control() {
+        // a loop used as a value still handles its own break and continue
+        let source = "for each n in [1, 2] { print do { continue; } 2 times; }";
+        assert_eq!(run(source), Ok(None));
+
+        // but they can't leave the loop the expression is in
+        let source = "while true { define y to be if true {\nbreak;\n}; }\nprint \"done\";";
+        assert_eq!(
+            run(source).unwrap_err(),
+            "test.eye:2:1: Can't break outside of a loop"
+        );
+        let source = "for each n in [1] { print if true {\ncontinue;\n}; }";
+        assert_eq!(
+            run(source).unwrap_err(),
+            "test.eye:2:1: Can't continue outside of a loop"
+        );
+    }
+
+    #[test]
+    fn match_errors() {
+        assert_eq!(
+            run("match 3 {\n1 { print 1; }\n}").unwrap_err(),
+            "test.eye:1:1: No arm of the match fits 3"
+        );
+        let shapes = "define Shape to be one of Circle(r) or Empty;\n";
+        let run_shapes = |source: &str| run(&format!("{}{}", shapes, source)).unwrap_err();
+        assert_eq!(
+            run_shapes("match Empty {\nCircle(a, b) { print a; }\n}"),
+            "test.eye:3:1: Circle has 1 fields but the pattern gives 2"
+        );
+        assert_eq!(
+            run_shapes("match Empty {\nSquare(s) { print s; }\n}"),
+            "test.eye:3:1: Tried to access undefined symbol: Square"
+        );
+        assert_eq!(
+            run_shapes("define Nope to be 1;\nmatch Empty {\nNope { print 1; }\n}"),
+            "test.eye:4:1: Expected a variant or record type for the pattern Nope, found 1"
+        );
+        assert_eq!(
+            run_shapes("print Circle(1, 2);"),
+            "test.eye:2:7: Variant Circle has 1 fields but was given 2"
+        );
+    }
+
+    #[test]
+    fn loop_errors() {
+        assert_eq!(
+            run("print 1;\nbreak;").unwrap_err(),
+            "test.eye:2:1: Can't break outside of a loop"
+        );
+        assert_eq!(
+            run("define f to be { continue; } given (); run f;").unwrap_err(),
+            "test.eye:1:18: Can't continue outside of a loop"
+        );
+        assert_eq!(
+            run("while 1 { print 1; }").unwrap_err(),
+            "test.eye:1:7: Expected a bool for while, found 1."
+        );
+    }
+
+    #[test]
+    fn closures_share_captured_variables() {
+        let source = "if true {
+            define count to be 0;
+            define bump to be { count = count + 1; } given ();
+            run bump given ();
+            run bump given ();
+            return count;
+        }";
+        assert_eq!(run_num(source), 2);
+    }
+
+    #[test]
+    fn closures_see_later_definitions() {
+        let source = "define outer to be {
+            define first to be { return second(); } given ();
+            define second to be { return n * 2; } given ();
+            return first();
+        } given (n);
+        return outer(21);";
+        assert_eq!(run_num(source), 42);
+    }
+
+    #[test]
+    fn nested_closures_capture_arguments() {
+        let source = "define make to be {
+            define add to be {
+                define inner to be { return a + b; } given ();
+                return inner();
+            } given (b);
+            return add(10);
+        } given (a);
+        return make(5);";
+        assert_eq!(run_num(source), 15);
+    }
+
+    #[test]
+    fn loops_get_fresh_scopes() {
+        let source = "define total to be 0;
+        do {
+            define step to be i * 2;
+            total = total + step;
+        } 5 times given i;
+        return total;";
+        assert_eq!(run_num(source), 20);
+    }
+
+    #[test]
+    fn blocks_shadow_outer_symbols() {
+        let source = "define a to be 1;
+        if true { define a to be 2; a = 3; }
+        return a;";
+        assert_eq!(run_num(source), 1);
+    }
+
+    #[test]
+    fn errors_match() {
+        let errors = [
+            "print b;",
+            "b = 1;",
+            "define a to be 1; run a given ();",
+            "define f to be { return 1; } given (x); run f given ();",
+            "define f to be { print 1; } given (); print f() + 1;",
+            "do { print 1; } true times;",
+            "print 1 + true;",
+            "print 1 / 0;",
+            "print 10 % 0;",
+            "print 1e308 * 10;",
+            "print \"a\" < 1;",
+            "define f to be {\n  throw \"bad\";\n} given ();\nrun f given ();",
+        ];
+        for source in errors {
+            assert!(run(source).is_err(), "{} should fail", source);
+        }
+        assert_eq!(
+            run("print 1;\nprint missing;"),
+            Err("test.eye:2:7: Tried to access undefined symbol: missing".to_string())
+        );
+    }
+
+    #[test]
+    fn operands_that_overflow_fail_to_compile() {
+        let run_bytecode = |program: Vec<crate::types::ast::Node>| {
+            let options = Options {
+                backend: Backend::Bytecode,
+                ..Options::debug()
+            };
+            crate::vm::run(&program, &SymbolStore::new(), &options)
+                .map_err(|error| error.to_string())
+        };
+
+        // build the statements directly, lexing 66,000 of them takes too long
+        let append = program("append 1 to xs;").remove(0);
+        let mut statements = program("define xs to be [];");
+        statements.extend(vec![append; 66_000]);
+        statements.extend(program("print xs[65536];"));
+        assert_eq!(
+            run_bytecode(statements).unwrap_err(),
+            "Too many constants in one function for the bytecode VM"
+        );
+
+        let args = vec!["0"; 256].join(", ");
+        let source = format!(
+            "define f to be {{ return 1; }} given (); print f({});",
+            args
+        );
+        assert_eq!(
+            run_bytecode(program(&source)).unwrap_err(),
+            "Too many arguments in one call for the bytecode VM"
+        );
+    }
+
+    #[test]
+    fn interpret_selects_backend() {
+        let options = Options {
+            backend: Backend::Bytecode,
+            ..Options::debug()
+        };
+        let ast = AST::Program {
+            program: program("define a to be 1;"),
+        };
+        let symbols = SymbolStore::new();
+        interpret(ast, symbols.clone(), &options).unwrap();
+        assert_eq!(symbols.get("a"), Some(PrimitiveValue::Num(1)));
+    }
+}
@@ -0,0 +1,482 @@
+use crate::interpreter::{
+    apply_operator, apply_unary_operator, call_function, check_call, iterate, match_pattern,
+    no_match_error, operands, repeat_count_error, short_circuits, Items,
+};
+use crate::types::ast::Node;
+use crate::types::error::RuntimeError;
+use crate::types::map::Map;
+use crate::types::options::Options;
+use crate::types::primitive_value::{expect_key, PrimitiveValue};
+use crate::types::span::Span;
+use crate::types::symbol_store::SymbolStore;
+use crate::vm::chunk::{Capture, Cell, Closure, Op, Var};
+use crate::vm::compiler::compile;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/**
+ * Compile and run a program against `globals`, returns anything the top level returns
+ */
+pub fn run(
+    program: &[Node],
+    globals: &SymbolStore,
+    options: &Options,
+) -> Result<Option<PrimitiveValue>, RuntimeError> {
+    let script = Rc::new(Closure {
+        proto: Rc::new(compile(program)?),
+        upvalues: vec![],
+        globals: globals.clone(),
+    });
+    call(&script, vec![], options)
+}
+
+/**
+ * Call a closure with evaluated arguments, the caller checks the arity
+ */
+pub fn call(
+    closure: &Rc<Closure>,
+    args: Vec<PrimitiveValue>,
+    options: &Options,
+) -> Result<Option<PrimitiveValue>, RuntimeError> {
+    let mut vm = VM {
+        stack: vec![],
+        locals: vec![],
+        frames: vec![],
+        options,
+    };
+    vm.push_frame(closure.clone(), args);
+    vm.execute()
+}
+
+/// A local slot, captured slots hold a cell shared with closures
+enum Local {
+    Unset,
+    Value(PrimitiveValue),
+    Cell(Cell),
+    /// the items a `for each` loop has left
+    Items(Items),
+}
+
+struct Frame {
+    closure: Rc<Closure>,
+    ip: usize,
+    /// index of the frame's first local
+    base: usize,
+    /// stack height when the frame was entered
+    stack_base: usize,
+}
+
+struct VM<'a> {
+    /// "no value" is pushed as `None`, like `run_ast` returning nothing
+    stack: Vec<Option<PrimitiveValue>>,
+    locals: Vec<Local>,
+    frames: Vec<Frame>,
+    options: &'a Options,
+}
+
+fn new_cell(value: Option<PrimitiveValue>) -> Cell {
+    Rc::new(RefCell::new(value))
+}
+
+impl<'a> VM<'a> {
+    fn push_frame(&mut self, closure: Rc<Closure>, args: Vec<PrimitiveValue>) {
+        let base = self.locals.len();
+        let proto = &closure.proto;
+        self.locals
+            .extend((0..proto.local_names.len()).map(|_| Local::Unset));
+        for (slot, value) in args.into_iter().enumerate() {
+            self.locals[base + slot] = if proto.captured[slot] {
+                Local::Cell(new_cell(Some(value)))
+            } else {
+                Local::Value(value)
+            };
+        }
+
+        self.frames.push(Frame {
+            closure,
+            ip: 0,
+            base,
+            stack_base: self.stack.len(),
+        });
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn pop(&mut self) -> Option<PrimitiveValue> {
+        self.stack.pop().unwrap()
+    }
+
+    /// Pop a value the compiler made sure is there
+    fn pop_value(&mut self) -> PrimitiveValue {
+        self.pop().expect("operand should have a value")
+    }
+
+    fn var_name(closure: &Closure, var: Var) -> &str {
+        let proto = &closure.proto;
+        match var {
+            Var::Local(slot) => &proto.local_names[slot as usize],
+            Var::Upvalue(index) => &proto.upvalue_names[index as usize],
+            Var::Global(index) => &proto.names[index as usize],
+        }
+    }
+
+    /// Read a variable, slots that aren't bound yet fall back to the globals
+    fn load(&self, closure: &Closure, base: usize, var: Var) -> Option<PrimitiveValue> {
+        let value = match var {
+            Var::Local(slot) => match &self.locals[base + slot as usize] {
+                Local::Unset | Local::Items(_) => None,
+                Local::Value(value) => Some(value.clone()),
+                Local::Cell(cell) => cell.borrow().clone(),
+            },
+            Var::Upvalue(index) => closure.upvalues[index as usize].borrow().clone(),
+            Var::Global(_) => None,
+        };
+        value.or_else(|| closure.globals.get(VM::var_name(closure, var)))
+    }
+
+    fn define(&mut self, closure: &Closure, base: usize, var: Var, value: PrimitiveValue) {
+        match var {
+            Var::Local(slot) => match &mut self.locals[base + slot as usize] {
+                Local::Cell(cell) => *cell.borrow_mut() = Some(value),
+                local => *local = Local::Value(value),
+            },
+            Var::Upvalue(index) => *closure.upvalues[index as usize].borrow_mut() = Some(value),
+            Var::Global(_) => closure
+                .globals
+                .define(VM::var_name(closure, var).to_string(), value),
+        }
+    }
+
+    /// Update an existing binding, returns false if there isn't one
+    fn store(&mut self, closure: &Closure, base: usize, var: Var, value: PrimitiveValue) -> bool {
+        match var {
+            Var::Local(slot) => match &mut self.locals[base + slot as usize] {
+                Local::Value(current) => {
+                    *current = value;
+                    return true;
+                }
+                Local::Cell(cell) if cell.borrow().is_some() => {
+                    *cell.borrow_mut() = Some(value);
+                    return true;
+                }
+                _ => (),
+            },
+            Var::Upvalue(index) => {
+                let cell = &closure.upvalues[index as usize];
+                if cell.borrow().is_some() {
+                    *cell.borrow_mut() = Some(value);
+                    return true;
+                }
+            }
+            Var::Global(_) => (),
+        }
+        closure.globals.assign(VM::var_name(closure, var), value)
+    }
+
+    /// Share a local slot with a closure, turning it into a cell if needed
+    fn capture(&mut self, slot: usize) -> Cell {
+        let local = std::mem::replace(&mut self.locals[slot], Local::Unset);
+        let cell = match local {
+            Local::Cell(cell) => cell,
+            Local::Value(value) => new_cell(Some(value)),
+            Local::Unset | Local::Items(_) => new_cell(None),
+        };
+        self.locals[slot] = Local::Cell(cell.clone());
+        cell
+    }
+
+    /// Run until the frame `execute` was called with returns
+    fn execute(&mut self) -> Result<Option<PrimitiveValue>, RuntimeError> {
+        let depth = self.frames.len();
+        loop {
+            let frame = self.frame();
+            let closure = frame.closure.clone();
+            let ip = frame.ip;
+            frame.ip += 1;
+
+            match self.step(&closure, closure.proto.code[ip]) {
+                Ok(None) => (),
+                Ok(Some(value)) => {
+                    if self.frames.len() < depth {
+                        return Ok(value);
+                    }
+                    self.stack.push(value);
+                }
+                Err(error) => return Err(error.or_span(&closure.proto.spans[ip])),
+            }
+        }
+    }
+
+    /// Run a single op, returns `Some` when the current frame returns
+    #[allow(clippy::option_option)]
+    fn step(
+        &mut self,
+        closure: &Rc<Closure>,
+        op: Op,
+    ) -> Result<Option<Option<PrimitiveValue>>, RuntimeError> {
+        let proto = &closure.proto;
+        let base = self.frame().base;
+
+        match op {
+            Op::Constant(index) => self
+                .stack
+                .push(Some(proto.constants[index as usize].clone())),
+            Op::Nothing => self.stack.push(None),
+            Op::Pop => {
+                self.pop();
+            }
+            Op::Load(var) => match self.load(closure, base, var) {
+                Some(value) => self.stack.push(Some(value)),
+                None => {
+                    return Err(RuntimeError::new(format!(
+                        "Tried to access undefined symbol: {}",
+                        VM::var_name(closure, var)
+                    )))
+                }
+            },
+            Op::LoadCallee(var, arg_count) => {
+                let identifier = VM::var_name(closure, var);
+                match self.load(closure, base, var) {
+                    Some(function) => {
+                        check_call(identifier, &function, arg_count as usize)?;
+                        self.stack.push(Some(function));
+                    }
+                    None => {
+                        return Err(RuntimeError::new(format!(
+                            "Symbol {} does not exist.",
+                            identifier
+                        )))
+                    }
+                }
+            }
+            Op::Define(var) => {
+                if let Some(value) = self.pop() {
+                    self.define(closure, base, var, value);
+                }
+            }
+            Op::Store(var) => {
+                let value = self.pop_value();
+                if !self.store(closure, base, var, value.clone()) {
+                    return Err(RuntimeError::new(format!(
+                        "Tried to assign undefined symbol: {}",
+                        VM::var_name(closure, var)
+                    )));
+                }
+                self.stack.push(Some(value));
+            }
+            Op::EnterBlock(index) => {
+                for slot in &proto.blocks[index as usize] {
+                    let slot = *slot as usize;
+                    self.locals[base + slot] = if proto.captured[slot] {
+                        Local::Cell(new_cell(None))
+                    } else {
+                        Local::Unset
+                    };
+                }
+            }
+            Op::Binary(operator) => {
+                let right = self.pop_value();
+                let left = self.pop_value();
+                self.stack
+                    .push(Some(apply_operator(operator, left, right)?));
+            }
+            Op::Unary(operator) => {
+                let value = self.pop_value();
+                self.stack
+                    .push(Some(apply_unary_operator(operator, value)?));
+            }
+            Op::List(count) => {
+                let items = self.stack.split_off(self.stack.len() - count as usize);
+                let items = items.into_iter().map(Option::unwrap).collect::<Vec<_>>();
+                self.stack.push(Some(PrimitiveValue::from(items)));
+            }
+            Op::Map(count) => {
+                let entries = self.stack.split_off(self.stack.len() - 2 * count as usize);
+                let mut map = Map::new();
+                for entry in entries.chunks(2) {
+                    let (key, value) = (entry[0].as_ref().unwrap(), entry[1].clone().unwrap());
+                    map.insert(expect_key(key)?.to_string(), value);
+                }
+                self.stack.push(Some(PrimitiveValue::from(map)));
+            }
+            Op::Remove => {
+                let map = self.pop_value();
+                let key = self.pop_value();
+                self.stack.push(Some(map.remove(key)?));
+            }
+            Op::GetField(name) => {
+                let record = self.pop_value();
+                let field = &proto.names[name as usize];
+                self.stack.push(Some(record.get_field(field)?));
+            }
+            Op::SetField(name) => {
+                let value = self.pop_value();
+                let field = &proto.names[name as usize];
+                self.pop_value().set_field(field, value.clone())?;
+                self.stack.push(Some(value));
+            }
+            Op::Index => {
+                let index = self.pop_value();
+                let collection = self.pop_value();
+                self.stack.push(Some(collection.get_index(index)?));
+            }
+            Op::SetIndex => {
+                let value = self.pop_value();
+                let index = self.pop_value();
+                self.pop_value().set_index(index, value.clone())?;
+                self.stack.push(Some(value));
+            }
+            Op::Append => {
+                let list = self.pop_value();
+                list.append(self.pop_value())?;
+            }
+            Op::ShortCircuit(operator, target) => {
+                let left = self.stack.last().unwrap().as_ref().unwrap();
+                if short_circuits(operator, left)? {
+                    self.frame().ip = target;
+                }
+            }
+            Op::ExpectValue(name) => {
+                if self.stack.last().unwrap().is_none() {
+                    return Err(RuntimeError::new(format!(
+                        "Function {} didn't return value",
+                        proto.names[name as usize]
+                    )));
+                }
+            }
+            Op::Fail(message) => {
+                return Err(RuntimeError::new(
+                    proto.constants[message as usize].to_string(),
+                ))
+            }
+            // same hook the tree-walker prints through
+            Op::Print => match self.pop() {
+                Some(value) => self.options.print(value),
+                None => self.options.print(PrimitiveValue::Str("''".to_string())),
+            },
+            Op::If(else_target, end) => match self.pop() {
+                Some(PrimitiveValue::Bool(true)) => (),
+                Some(PrimitiveValue::Bool(false)) => self.frame().ip = else_target,
+                _ => self.frame().ip = end,
+            },
+            Op::Elif(next) => {
+                if self.pop() != Some(PrimitiveValue::Bool(true)) {
+                    self.frame().ip = next;
+                }
+            }
+            Op::Jump(target) => self.frame().ip = target,
+            Op::While(until, exit) => {
+                let keyword = if until { "until" } else { "while" };
+                if self.pop_value().expect_bool(keyword)? == until {
+                    self.frame().ip = exit;
+                }
+            }
+            Op::LoopCount(slot) => {
+                let slot = base + slot as usize;
+                match self.pop_value() {
+                    PrimitiveValue::Num(count) => {
+                        self.locals[slot] = Local::Value(PrimitiveValue::Num(count));
+                        self.locals[slot + 1] = Local::Value(PrimitiveValue::Num(0));
+                    }
+                    value => return Err(repeat_count_error(&value)),
+                }
+            }
+            Op::LoopNext(slot, exit) => {
+                let slot = base + slot as usize;
+                if let (
+                    Local::Value(PrimitiveValue::Num(count)),
+                    Local::Value(PrimitiveValue::Num(counter)),
+                ) = (&self.locals[slot], &self.locals[slot + 1])
+                {
+                    let (count, counter) = (*count, *counter);
+                    if counter >= count {
+                        self.frame().ip = exit;
+                    } else {
+                        self.locals[slot + 1] = Local::Value(PrimitiveValue::Num(counter + 1));
+                        self.stack.push(Some(PrimitiveValue::Num(counter)));
+                    }
+                }
+            }
+            Op::Iterate(slot) => {
+                let items = iterate(self.pop_value())?;
+                self.locals[base + slot as usize] = Local::Items(items);
+            }
+            Op::IterateNext(slot, exit) => {
+                if let Local::Items(items) = &mut self.locals[base + slot as usize] {
+                    match items.next() {
+                        Some((item, index)) => {
+                            self.stack.push(Some(index));
+                            self.stack.push(Some(item));
+                        }
+                        None => self.frame().ip = exit,
+                    }
+                }
+            }
+            Op::Match(slot, pattern, fail) => {
+                let pattern = &proto.patterns[pattern as usize];
+                let start = self.stack.len() - operands(pattern, &Span::default()).len();
+                let values: Vec<PrimitiveValue> = self
+                    .stack
+                    .split_off(start)
+                    .into_iter()
+                    .map(|operand| operand.expect("pattern operands have values"))
+                    .collect();
+                let value = self.load(closure, base, Var::Local(slot)).unwrap();
+                let mut bound = vec![];
+                if match_pattern(pattern, &value, &mut values.into_iter(), &mut bound)? {
+                    self.stack.extend(bound.into_iter().rev().map(Some));
+                } else {
+                    self.frame().ip = fail;
+                }
+            }
+            Op::NoMatch(slot) => {
+                let value = self.load(closure, base, Var::Local(slot)).unwrap();
+                return Err(no_match_error(&value));
+            }
+            Op::Closure(index) => {
+                let function = &proto.protos[index as usize];
+                let mut upvalues = vec![];
+                for capture in &function.captures {
+                    upvalues.push(match capture {
+                        Capture::Local(slot) => self.capture(base + *slot as usize),
+                        Capture::Upvalue(index) => closure.upvalues[*index as usize].clone(),
+                    });
+                }
+                self.stack
+                    .push(Some(PrimitiveValue::Closure(Rc::new(Closure {
+                        proto: function.clone(),
+                        upvalues,
+                        globals: closure.globals.clone(),
+                    }))));
+            }
+            Op::Call(arg_count) => {
+                let start = self.stack.len() - arg_count as usize;
+                let args = self
+                    .stack
+                    .split_off(start)
+                    .into_iter()
+                    .map(|arg| arg.expect("argument should have a value"))
+                    .collect();
+                match self.pop_value() {
+                    PrimitiveValue::Closure(callee) => self.push_frame(callee, args),
+                    function => {
+                        let value = call_function(function, args, self.options)?;
+                        self.stack.push(value);
+                    }
+                }
+            }
+            Op::Return => {
+                let value = self.pop();
+                let frame = self.frames.pop().unwrap();
+                self.locals.truncate(frame.base);
+                self.stack.truncate(frame.stack_base);
+                return Ok(Some(value));
+            }
+        }
+
+        Ok(None)
+    }
+}